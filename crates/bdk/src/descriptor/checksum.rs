@@ -12,10 +12,23 @@
 //! Descriptor checksum
 //!
 //! This module contains a re-implementation of the function used by Bitcoin Core to calculate the
-//! checksum of a descriptor
+//! checksum of a descriptor, and helpers to verify, strip or append the checksum of a descriptor
+//! string and to canonicalize it, without constructing a [`Wallet`](crate::Wallet).
+//!
+//! ## Example
+//!
+//! ```
+//! # use bdk::descriptor::checksum::*;
+//! let desc = "wpkh(tpubD6NzVbkrYhZ4XHndKkuB8FifXm8r5FQHwrN6oZuWCz13qb93rtgKvD4PQsqC4HP4yhV3tA2fqr2RbY5mNXfM7RxXUoeABoDtsFUq2zJq6YK/84h/1h/0h/0/*)";
+//! let canonical = canonicalize(desc)?;
+//! assert!(verify_checksum(&canonical).is_ok());
+//! assert_eq!(strip_checksum(&canonical), canonical.split_once('#').unwrap().0);
+//! # Ok::<_, bdk::descriptor::DescriptorError>(())
+//! ```
 
-use crate::descriptor::DescriptorError;
+use crate::descriptor::{DescriptorError, ExtendedDescriptor};
 use alloc::string::String;
+use bitcoin::secp256k1::Secp256k1;
 
 const INPUT_CHARSET: &[u8] = b"0123456789()[],'/*abcdefgh@:$%{}IJKLMNOPQRSTUVWXYZ&+-.;<=>?!^_|~ijklmnopqrstuvwxyzABCDEFGH`#\"\\ ";
 const CHECKSUM_CHARSET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
@@ -107,6 +120,43 @@ pub fn calc_checksum(desc: &str) -> Result<String, DescriptorError> {
         .map(|b| unsafe { String::from_utf8_unchecked(b.to_vec()) })
 }
 
+/// Verify the checksum of a descriptor
+///
+/// Returns [`DescriptorError::InvalidDescriptorChecksum`] if the descriptor string doesn't end
+/// with a checksum, or if the checksum is invalid.
+pub fn verify_checksum(desc: &str) -> Result<(), DescriptorError> {
+    if !desc.contains('#') {
+        return Err(DescriptorError::InvalidDescriptorChecksum);
+    }
+    calc_checksum_bytes_internal(desc, true).map(|_| ())
+}
+
+/// Return the descriptor string without its checksum, if it has one
+///
+/// The checksum isn't verified, see [`verify_checksum`].
+pub fn strip_checksum(desc: &str) -> &str {
+    desc.split_once('#').map_or(desc, |(desc, _)| desc)
+}
+
+/// Return the descriptor string with its checksum appended
+///
+/// If the descriptor already has a checksum it's verified and kept.
+pub fn append_checksum(desc: &str) -> Result<String, DescriptorError> {
+    let checksum = calc_checksum(desc)?;
+    Ok(format!("{}#{}", strip_checksum(desc), checksum))
+}
+
+/// Return the canonical form of a descriptor, with its checksum
+///
+/// The descriptor is parsed and serialized again, which normalizes the key expressions (e.g.
+/// hardened derivation steps are always written with `'`) and the checksum. Private keys are
+/// kept. If the descriptor has a checksum, it must be valid.
+pub fn canonicalize(desc: &str) -> Result<String, DescriptorError> {
+    let secp = Secp256k1::signing_only();
+    let (descriptor, keymap) = ExtendedDescriptor::parse_descriptor(&secp, desc)?;
+    Ok(descriptor.to_string_with_secret(&keymap))
+}
+
 // TODO in release 0.25.0, remove get_checksum_bytes and get_checksum
 // TODO in release 0.25.0, consolidate calc_checksum_bytes_internal into calc_checksum_bytes
 
@@ -169,6 +219,34 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_checksum_utils() {
+        let desc = "pkh(tpubD6NzVbkrYhZ4XHndKkuB8FifXm8r5FQHwrN6oZuWCz13qb93rtgKvD4PQsqC4HP4yhV3tA2fqr2RbY5mNXfM7RxXUoeABoDtsFUq2zJq6YK/44'/1'/0'/0/*)";
+        let with_checksum = format!("{}#lasegmfs", desc);
+
+        assert_eq!(append_checksum(desc).unwrap(), with_checksum);
+        assert_eq!(append_checksum(&with_checksum).unwrap(), with_checksum);
+        assert_eq!(strip_checksum(&with_checksum), desc);
+        assert_eq!(strip_checksum(desc), desc);
+        assert!(verify_checksum(&with_checksum).is_ok());
+        assert_matches!(
+            verify_checksum(desc),
+            Err(DescriptorError::InvalidDescriptorChecksum)
+        );
+        assert_matches!(
+            append_checksum(&format!("{}#lasegmsf", desc)),
+            Err(DescriptorError::InvalidDescriptorChecksum)
+        );
+
+        let hardened_h = desc.replace('\'', "h");
+        assert_eq!(canonicalize(&hardened_h).unwrap(), with_checksum);
+        assert_eq!(canonicalize(&with_checksum).unwrap(), with_checksum);
+        assert_matches!(
+            canonicalize(&format!("{}#lasegmsf", desc)),
+            Err(DescriptorError::Miniscript(_))
+        );
+    }
+
     #[test]
     fn test_calc_checksum_invalid_character() {
         let sparkle_heart = unsafe { core::str::from_utf8_unchecked(&[240, 159, 146, 150]) };
@@ -319,6 +319,68 @@ pub(crate) fn into_wallet_descriptor_checked<T: IntoWalletDescriptor>(
     Ok((descriptor, keymap))
 }
 
+/// Type of descriptor produced by [`policy_compile`]
+#[cfg(feature = "compiler")]
+#[cfg_attr(docsrs, doc(cfg(feature = "compiler")))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompileTarget {
+    /// Legacy P2SH
+    Sh,
+    /// Native segwit P2WSH
+    Wsh,
+    /// P2WSH nested in P2SH
+    ShWsh,
+    /// Taproot, with the internal key extracted from the policy when possible
+    Tr,
+}
+
+/// Compile a miniscript policy string into a descriptor of the `target` type
+///
+/// The keys in the policy can be anything that parses as a [`DescriptorPublicKey`], including
+/// extended keys with wildcards. The resulting descriptor can be used to create a
+/// [`Wallet`](crate::Wallet).
+///
+/// ```
+/// # use bdk::descriptor::{policy_compile, CompileTarget};
+/// let descriptor = policy_compile(
+///     "or(pk(tpubD6NzVbkrYhZ4Xferm7Pz4VnjdcDPFyjVu5K4iZXQ4pVN8Cks4pHVowTBXBKRhX64pkRyJZJN5xAKj4UDNnLPb5p2sSKXhewoYx5GbTdUFWq/*),and(pk(tpubD6NzVbkrYhZ4XHndKkuB8FifXm8r5FQHwrN6oZuWCz13qb93rtgKvD4PQsqC4HP4yhV3tA2fqr2RbY5mNXfM7RxXUoeABoDtsFUq2zJq6YK/*),older(144)))",
+///     CompileTarget::Wsh,
+/// )?;
+/// assert!(descriptor.to_string().starts_with("wsh("));
+/// # Ok::<_, bdk::descriptor::DescriptorError>(())
+/// ```
+#[cfg(feature = "compiler")]
+#[cfg_attr(docsrs, doc(cfg(feature = "compiler")))]
+pub fn policy_compile(
+    policy: &str,
+    target: CompileTarget,
+) -> Result<ExtendedDescriptor, DescriptorError> {
+    use core::str::FromStr;
+    use miniscript::policy::Concrete;
+
+    let policy = Concrete::<DescriptorPublicKey>::from_str(policy)?;
+    let descriptor = match target {
+        CompileTarget::Sh => Descriptor::new_sh(
+            policy
+                .compile::<Legacy>()
+                .map_err(miniscript::Error::from)?,
+        )?,
+        CompileTarget::Wsh => Descriptor::new_wsh(
+            policy
+                .compile::<Segwitv0>()
+                .map_err(miniscript::Error::from)?,
+        )?,
+        CompileTarget::ShWsh => Descriptor::new_sh_wsh(
+            policy
+                .compile::<Segwitv0>()
+                .map_err(miniscript::Error::from)?,
+        )?,
+        CompileTarget::Tr => policy.compile_tr(None)?,
+    };
+
+    Ok(descriptor)
+}
+
 #[doc(hidden)]
 /// Used internally mainly by the `descriptor!()` and `fragment!()` macros
 pub trait CheckMiniscript<Ctx: miniscript::ScriptContext> {
@@ -898,4 +960,27 @@ mod test {
         assert_eq!(psbt_input.redeem_script, Some(script.to_v0_p2wsh()));
         assert_eq!(psbt_input.witness_script, Some(script));
     }
+
+    #[test]
+    #[cfg(feature = "compiler")]
+    fn test_policy_compile() {
+        let policy = "or(pk(tpubD6NzVbkrYhZ4Xferm7Pz4VnjdcDPFyjVu5K4iZXQ4pVN8Cks4pHVowTBXBKRhX64pkRyJZJN5xAKj4UDNnLPb5p2sSKXhewoYx5GbTdUFWq/*),and(pk(tpubD6NzVbkrYhZ4XHndKkuB8FifXm8r5FQHwrN6oZuWCz13qb93rtgKvD4PQsqC4HP4yhV3tA2fqr2RbY5mNXfM7RxXUoeABoDtsFUq2zJq6YK/*),older(144)))";
+
+        let secp = Secp256k1::new();
+        for (target, prefix) in [
+            (CompileTarget::Sh, "sh("),
+            (CompileTarget::Wsh, "wsh("),
+            (CompileTarget::ShWsh, "sh(wsh("),
+            (CompileTarget::Tr, "tr("),
+        ] {
+            let descriptor = policy_compile(policy, target).unwrap();
+            assert!(descriptor.to_string().starts_with(prefix));
+            assert!(into_wallet_descriptor_checked(descriptor, &secp, Network::Testnet).is_ok());
+        }
+
+        assert_matches!(
+            policy_compile("or(pk(invalid)", CompileTarget::Wsh),
+            Err(DescriptorError::Miniscript(_))
+        );
+    }
 }
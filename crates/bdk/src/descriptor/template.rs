@@ -14,6 +14,7 @@
 //! This module contains the definition of various common script templates that are ready to be
 //! used. See the documentation of each template for an example.
 
+use alloc::vec::Vec;
 use bitcoin::bip32;
 use bitcoin::Network;
 
@@ -180,6 +181,96 @@ impl<K: IntoDescriptorKey<Tap>> DescriptorTemplate for P2TR<K> {
     }
 }
 
+/// Decaying multisig template. Expands to a descriptor
+/// `wsh(thresh(threshold,pk(key_1),s:pk(key_2),...,sndv:older(timelock_1),...))`
+///
+/// The coins can be spent with `threshold` signatures out of `keys`, and every relative
+/// `timelock` (in blocks) that has expired since the coins were received counts as one of the
+/// signatures. For instance, a 2-of-3 with a single timelock of 144 blocks becomes a 1-of-3 a day
+/// after the coins are confirmed, to recover the funds if two keys are lost.
+///
+/// To prevent the coins from being spent without any signature, there must be fewer timelocks
+/// than `threshold`. Reusing the same keys as different `keys` isn't allowed by miniscript.
+///
+/// Spending through a timelock requires selecting it with the
+/// [`policy_path`](crate::wallet::tx_builder::TxBuilder::policy_path) of the transaction: in the
+/// policy of the wallet, the root `thresh` has one item per key followed by one item per
+/// timelock, in order. The transaction then has the right `nSequence` to use the timelock.
+///
+/// ## Example
+///
+/// ```
+/// # use std::collections::BTreeMap;
+/// # use bdk::bitcoin::Network;
+/// # use bdk::{KeychainKind, Wallet};
+/// use bdk::template::DecayingMultisig;
+///
+/// let mut wallet = Wallet::new_no_persist(
+///     DecayingMultisig {
+///         threshold: 2,
+///         keys: vec![
+///             "cVpPVruEDdmutPzisEsYvtST1usBR3ntr8pXSyt6D2YYqXRyPcFW",
+///             "02e96fe52ef0e22d2f131dd425ce1893073a3c6ad20e8cac36726393dfb4856a4c",
+///             "03a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd",
+///         ],
+///         timelocks: vec![144],
+///     },
+///     None,
+///     Network::Testnet,
+/// )?;
+///
+/// // recovery path: the first key and the timelock
+/// let policy = wallet.policies(KeychainKind::External)?.unwrap();
+/// let path = BTreeMap::from([(policy.id, vec![0, 3])]);
+/// # let address = wallet.get_address(bdk::wallet::AddressIndex::New);
+/// let mut builder = wallet.build_tx();
+/// builder
+///     .drain_wallet()
+///     .drain_to(address.script_pubkey())
+///     .policy_path(path, KeychainKind::External);
+/// # Ok::<_, Box<dyn std::error::Error>>(())
+/// ```
+pub struct DecayingMultisig<K: IntoDescriptorKey<Segwitv0>> {
+    /// Number of signatures required before any timelock expires
+    pub threshold: usize,
+    /// The keys of the multisig
+    pub keys: Vec<K>,
+    /// Relative timelocks, in blocks, each lowering the number of required signatures by one
+    pub timelocks: Vec<u16>,
+}
+
+impl<K: IntoDescriptorKey<Segwitv0>> DescriptorTemplate for DecayingMultisig<K> {
+    fn build(self, _network: Network) -> Result<DescriptorTemplateOut, DescriptorError> {
+        if self.threshold == 0
+            || self.threshold > self.keys.len()
+            || self.timelocks.len() >= self.threshold
+            || self.timelocks.contains(&0)
+        {
+            return Err(DescriptorError::Miniscript(
+                miniscript::Error::BadDescriptor(format!(
+                    "invalid decaying multisig: {} of {} keys with {} timelocks",
+                    self.threshold,
+                    self.keys.len(),
+                    self.timelocks.len()
+                )),
+            ));
+        }
+
+        let mut items = Vec::with_capacity(self.keys.len() + self.timelocks.len());
+        for (i, key) in self.keys.into_iter().enumerate() {
+            items.push(match i {
+                0 => crate::fragment!(pk(key))?,
+                _ => crate::fragment!(s:pk(key))?,
+            });
+        }
+        for timelock in self.timelocks {
+            items.push(crate::fragment!(s:n:d:v:older(u32::from(timelock)))?);
+        }
+
+        descriptor!(wsh(thresh_vec(self.threshold, items)))
+    }
+}
+
 /// BIP44 template. Expands to `pkh(key/44'/{0,1}'/0'/{0,1}/*)`
 ///
 /// Since there are hardened derivation steps, this template requires a private derivable key (generally a `xprv`/`tprv`).
@@ -596,6 +687,43 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_decaying_multisig_template() {
+        let keys = vec![
+            "03a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd",
+            "02e96fe52ef0e22d2f131dd425ce1893073a3c6ad20e8cac36726393dfb4856a4c",
+            "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+        ];
+        let (desc, _, _) = DecayingMultisig {
+            threshold: 3,
+            keys: keys.clone(),
+            timelocks: vec![144, 1008],
+        }
+        .build(Network::Bitcoin)
+        .unwrap();
+        assert_eq!(
+            crate::descriptor::checksum::strip_checksum(&desc.to_string()),
+            format!(
+                "wsh(thresh(3,pk({}),s:pk({}),s:pk({}),sndv:older(144),sndv:older(1008)))",
+                keys[0], keys[1], keys[2]
+            )
+        );
+
+        for (threshold, timelocks) in [(0, vec![]), (4, vec![]), (2, vec![1, 2]), (2, vec![0])] {
+            assert_matches!(
+                DecayingMultisig {
+                    threshold,
+                    keys: keys.clone(),
+                    timelocks,
+                }
+                .build(Network::Bitcoin),
+                Err(DescriptorError::Miniscript(
+                    miniscript::Error::BadDescriptor(_)
+                ))
+            );
+        }
+    }
+
     // verify template descriptor generates expected address(es)
     fn check(
         desc: Result<(Descriptor<DescriptorPublicKey>, KeyMap, ValidNetworks), DescriptorError>,
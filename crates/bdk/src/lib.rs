@@ -32,6 +32,10 @@ pub mod psbt;
 pub(crate) mod types;
 pub mod wallet;
 
+#[cfg(feature = "testutils")]
+#[cfg_attr(docsrs, doc(cfg(feature = "testutils")))]
+pub mod testutils;
+
 pub use descriptor::template;
 pub use descriptor::HdKeyPaths;
 pub use types::*;
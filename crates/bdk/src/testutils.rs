@@ -0,0 +1,415 @@
+// Bitcoin Dev Kit
+//
+// Copyright (c) 2020-2021 Bitcoin Dev Kit Developers
+//
+// This file is licensed under the Apache License, Version 2.0 <LICENSE-APACHE
+// or http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your option.
+// You may not use this file except in accordance with one or both of these
+// licenses.
+
+//! Test utilities
+//!
+//! This module provides [`MockBlockchain`], an in-memory chain source with scripted blocks,
+//! confirmations, reorgs and fee estimates, to unit-test wallet logic without running `bitcoind`
+//! or an Electrum/Esplora server. It's only available with the `testutils` feature.
+//!
+//! ## Example
+//!
+//! ```
+//! # use bdk::testutils::MockBlockchain;
+//! # use bdk::wallet::AddressIndex;
+//! # use bdk::Wallet;
+//! # use bitcoin::Network;
+//! let mut wallet = Wallet::new_no_persist("wpkh(tprv8ZgxMBicQKsPd3EupYiPRhaMooHKUHJxNsTfYuScep13go8QFfHdtkG9nRkFGb7busX4isf6X9dURGCoKgitaApQ6MupRhZMcELAxTBRJgS/*)", None, Network::Regtest)?;
+//! let mut chain = MockBlockchain::new(Network::Regtest);
+//!
+//! // fund the wallet, and confirm the transaction
+//! let address = wallet.get_address(AddressIndex::New);
+//! chain.send_to(address.script_pubkey(), 50_000);
+//! chain.mine(1);
+//! wallet.apply_update(chain.update(&wallet))?;
+//! assert_eq!(wallet.get_balance().confirmed, 50_000);
+//!
+//! // the block is reorged out by a longer chain, the transaction goes back to the mempool
+//! chain.invalidate(1);
+//! chain.mine_empty(2);
+//! wallet.apply_update(chain.update(&wallet))?;
+//! assert_eq!(wallet.get_balance().confirmed, 0);
+//! assert_eq!(wallet.get_balance().untrusted_pending, 50_000);
+//! # Ok::<_, Box<dyn std::error::Error>>(())
+//! ```
+
+use crate::collections::{BTreeMap, HashSet};
+use alloc::vec::Vec;
+use core::fmt;
+
+use bdk_chain::local_chain::{self, CheckPoint};
+use bdk_chain::{BlockId, ConfirmationTimeHeightAnchor, TxGraph};
+use bitcoin::constants::genesis_block;
+use bitcoin::hashes::{sha256d, Hash};
+use bitcoin::{
+    absolute, BlockHash, Network, OutPoint, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Txid,
+    Witness,
+};
+
+use crate::types::FeeRate;
+use crate::wallet::fee_estimation::{FeeEstimates, FeeEstimationError, FeeEstimator};
+use crate::wallet::{Update, Wallet};
+
+/// Seconds between two blocks of a [`MockBlockchain`]
+pub const BLOCK_INTERVAL: u64 = 600;
+
+/// An in-memory blockchain with scripted blocks, transactions and fee estimates
+///
+/// Transactions are added to the mempool with [`send_to`](Self::send_to) or
+/// [`broadcast`](Self::broadcast), and confirmed by the next block [`mine`](Self::mine)d. Block
+/// `n` has timestamp `n * BLOCK_INTERVAL`.
+///
+/// See the [module-level documentation](self) for more.
+#[derive(Debug, Clone)]
+pub struct MockBlockchain {
+    blocks: Vec<BlockHash>,
+    txs: Vec<(Transaction, Option<u32>)>,
+    fee_estimates: Option<FeeEstimates>,
+    next_nonce: u64,
+}
+
+impl MockBlockchain {
+    /// Create a chain with only the genesis block of `network`
+    pub fn new(network: Network) -> Self {
+        MockBlockchain {
+            blocks: vec![genesis_block(network).block_hash()],
+            txs: Vec::new(),
+            fee_estimates: None,
+            next_nonce: 0,
+        }
+    }
+
+    /// Return the tip of the chain
+    pub fn tip(&self) -> BlockId {
+        let height = self.blocks.len() as u32 - 1;
+        BlockId {
+            height,
+            hash: self.blocks[height as usize],
+        }
+    }
+
+    fn nonce(&mut self) -> sha256d::Hash {
+        self.next_nonce += 1;
+        sha256d::Hash::hash(&self.next_nonce.to_le_bytes())
+    }
+
+    /// Mine `n` blocks, the first one confirming all the transactions of the mempool, and return
+    /// the new tip
+    pub fn mine(&mut self, n: u32) -> BlockId {
+        if n > 0 {
+            let height = self.blocks.len() as u32;
+            for (_, confirmation) in &mut self.txs {
+                confirmation.get_or_insert(height);
+            }
+        }
+        self.mine_empty(n)
+    }
+
+    /// Mine `n` blocks without confirming any transaction, and return the new tip
+    pub fn mine_empty(&mut self, n: u32) -> BlockId {
+        for _ in 0..n {
+            let hash = BlockHash::from_raw_hash(self.nonce());
+            self.blocks.push(hash);
+        }
+        self.tip()
+    }
+
+    /// Disconnect the last `depth` blocks, as a reorg would, and return the new tip
+    ///
+    /// The transactions they confirmed go back to the mempool. Wallets see the reorg once new
+    /// blocks, which have different hashes, are mined on top.
+    pub fn invalidate(&mut self, depth: u32) -> BlockId {
+        let new_len = self.blocks.len().saturating_sub(depth as usize).max(1);
+        self.blocks.truncate(new_len);
+        for (_, confirmation) in &mut self.txs {
+            if confirmation.map_or(false, |height| height as usize >= new_len) {
+                *confirmation = None;
+            }
+        }
+        self.tip()
+    }
+
+    /// Add a transaction paying `amount` to `script_pubkey` to the mempool, and return its txid
+    ///
+    /// The transaction spends a made-up output, so it can fund any descriptor.
+    pub fn send_to(&mut self, script_pubkey: ScriptBuf, amount: u64) -> Txid {
+        let tx = Transaction {
+            version: 1,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint::new(Txid::from_raw_hash(self.nonce()), 0),
+                script_sig: ScriptBuf::new(),
+                sequence: Sequence::MAX,
+                witness: Witness::new(),
+            }],
+            output: vec![TxOut {
+                value: amount,
+                script_pubkey,
+            }],
+        };
+        self.broadcast(tx).expect("the input is unique")
+    }
+
+    /// Add `tx` to the mempool, and return its txid
+    ///
+    /// The unconfirmed transactions spending the same outputs are replaced, while spending the
+    /// output of a confirmed transaction twice returns [`MockBlockchainError::DoubleSpend`].
+    /// Scripts and amounts aren't checked.
+    pub fn broadcast(&mut self, tx: Transaction) -> Result<Txid, MockBlockchainError> {
+        let spends = tx
+            .input
+            .iter()
+            .map(|txin| txin.previous_output)
+            .collect::<HashSet<_>>();
+        let txid = tx.txid();
+        let mut replaced = HashSet::new();
+        for (other, confirmation) in &self.txs {
+            if other.txid() == txid {
+                return Ok(txid);
+            }
+            if let Some(outpoint) = other
+                .input
+                .iter()
+                .map(|txin| txin.previous_output)
+                .find(|outpoint| spends.contains(outpoint))
+            {
+                if confirmation.is_some() {
+                    return Err(MockBlockchainError::DoubleSpend(outpoint));
+                }
+                replaced.insert(other.txid());
+            }
+        }
+
+        self.remove_with_descendants(replaced);
+        self.txs.push((tx, None));
+        Ok(txid)
+    }
+
+    fn remove_with_descendants(&mut self, mut removed: HashSet<Txid>) {
+        // transactions are stored after their parents
+        self.txs.retain(|(tx, _)| {
+            let txid = tx.txid();
+            if removed.contains(&txid)
+                || tx
+                    .input
+                    .iter()
+                    .any(|txin| removed.contains(&txin.previous_output.txid))
+            {
+                removed.insert(txid);
+                false
+            } else {
+                true
+            }
+        });
+    }
+
+    /// Remove an unconfirmed transaction, and its descendants, from the mempool
+    ///
+    /// Returns `false` if the transaction isn't in the mempool.
+    pub fn evict(&mut self, txid: Txid) -> bool {
+        match self.confirmations(txid) {
+            Some(0) => {
+                self.remove_with_descendants(HashSet::from_iter([txid]));
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Return the number of confirmations of `txid`, `0` if it's in the mempool and `None` if
+    /// it's unknown
+    pub fn confirmations(&self, txid: Txid) -> Option<u32> {
+        self.txs
+            .iter()
+            .find(|(tx, _)| tx.txid() == txid)
+            .map(|(_, confirmation)| {
+                confirmation.map_or(0, |height| self.tip().height - height + 1)
+            })
+    }
+
+    /// Set the fee estimates returned by the chain as a [`FeeEstimator`]
+    pub fn set_fee_estimates(&mut self, fee_estimates: FeeEstimates) {
+        self.fee_estimates = Some(fee_estimates);
+    }
+
+    /// Return an [`Update`] of `wallet` with the whole chain and the transactions relevant to
+    /// it, like a full scan with a real chain source would
+    ///
+    /// Unconfirmed transactions are last seen at the time of the tip.
+    pub fn update<D>(&self, wallet: &Wallet<D>) -> Update {
+        let tip = self.tip();
+        let now = tip.height as u64 * BLOCK_INTERVAL;
+        let spk_index = wallet.spk_index();
+
+        let mut graph = TxGraph::default();
+        let mut relevant = HashSet::new();
+        let mut last_active_indices = BTreeMap::new();
+        for (tx, confirmation) in &self.txs {
+            let spends_relevant = tx
+                .input
+                .iter()
+                .any(|txin| relevant.contains(&txin.previous_output.txid));
+            if !spends_relevant && !spk_index.is_relevant(tx) {
+                continue;
+            }
+            for txout in &tx.output {
+                if let Some(&(keychain, index)) = spk_index.index_of_spk(&txout.script_pubkey) {
+                    let last_active = last_active_indices.entry(keychain).or_insert(index);
+                    *last_active = index.max(*last_active);
+                }
+            }
+
+            let txid = tx.txid();
+            relevant.insert(txid);
+            let _ = graph.insert_tx(tx.clone());
+            let _ = match confirmation {
+                Some(height) => graph.insert_anchor(
+                    txid,
+                    ConfirmationTimeHeightAnchor {
+                        anchor_block: BlockId {
+                            height: *height,
+                            hash: self.blocks[*height as usize],
+                        },
+                        confirmation_height: *height,
+                        confirmation_time: *height as u64 * BLOCK_INTERVAL,
+                    },
+                ),
+                None => graph.insert_seen_at(txid, now),
+            };
+        }
+
+        let blocks = self
+            .blocks
+            .iter()
+            .enumerate()
+            .map(|(height, hash)| BlockId {
+                height: height as u32,
+                hash: *hash,
+            });
+        let chain_tip = CheckPoint::new(BlockId {
+            height: 0,
+            hash: self.blocks[0],
+        })
+        .extend(blocks.skip(1))
+        .expect("heights are increasing");
+
+        Update {
+            last_active_indices,
+            graph,
+            chain: Some(local_chain::Update {
+                tip: chain_tip,
+                introduce_older_blocks: true,
+            }),
+        }
+    }
+}
+
+impl FeeEstimator for MockBlockchain {
+    fn estimate_fee_rate(&self, target_blocks: u16) -> Result<FeeRate, FeeEstimationError> {
+        self.fee_estimates
+            .as_ref()
+            .ok_or(FeeEstimationError::NoEstimates)?
+            .estimate_fee_rate(target_blocks)
+    }
+}
+
+/// Errors returned by a [`MockBlockchain`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MockBlockchainError {
+    /// The output is already spent by a confirmed transaction
+    DoubleSpend(OutPoint),
+}
+
+impl fmt::Display for MockBlockchainError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DoubleSpend(outpoint) => write!(f, "Output already spent: {}", outpoint),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for MockBlockchainError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::wallet::AddressIndex;
+
+    const DESCRIPTOR: &str = "wpkh(tprv8ZgxMBicQKsPd3EupYiPRhaMooHKUHJxNsTfYuScep13go8QFfHdtkG9nRkFGb7busX4isf6X9dURGCoKgitaApQ6MupRhZMcELAxTBRJgS/*)";
+
+    #[test]
+    fn test_mock_blockchain() {
+        let mut wallet = Wallet::new_no_persist(DESCRIPTOR, None, Network::Regtest).unwrap();
+        let mut chain = MockBlockchain::new(Network::Regtest);
+        assert_eq!(chain.tip().height, 0);
+
+        let address = wallet.get_address(AddressIndex::New);
+        let txid = chain.send_to(address.script_pubkey(), 50_000);
+        // not relevant to the wallet
+        chain.send_to(ScriptBuf::new(), 10_000);
+        assert_eq!(chain.confirmations(txid), Some(0));
+        wallet.apply_update(chain.update(&wallet)).unwrap();
+        assert_eq!(wallet.get_balance().untrusted_pending, 50_000);
+        assert_eq!(wallet.transactions().count(), 1);
+
+        chain.mine(3);
+        assert_eq!(chain.confirmations(txid), Some(3));
+        wallet.apply_update(chain.update(&wallet)).unwrap();
+        assert_eq!(wallet.get_balance().confirmed, 50_000);
+        assert_eq!(wallet.latest_checkpoint().height(), 3);
+
+        // reorg the block confirming the transaction
+        chain.invalidate(3);
+        let tip = chain.mine(4);
+        assert_eq!(chain.confirmations(txid), Some(4));
+        wallet.apply_update(chain.update(&wallet)).unwrap();
+        assert_eq!(wallet.latest_checkpoint().block_id(), tip);
+        assert_eq!(wallet.get_balance().confirmed, 50_000);
+
+        // a confirmed output can't be spent twice
+        let spend = |value| Transaction {
+            version: 1,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint::new(txid, 0),
+                ..Default::default()
+            }],
+            output: vec![TxOut {
+                value,
+                script_pubkey: ScriptBuf::new(),
+            }],
+        };
+        let spend_a = chain.broadcast(spend(40_000)).unwrap();
+        let spend_b = chain.broadcast(spend(30_000)).unwrap();
+        assert_eq!(chain.confirmations(spend_a), None);
+        chain.mine(1);
+        assert_eq!(chain.confirmations(spend_b), Some(1));
+        assert_eq!(
+            chain.broadcast(spend(20_000)),
+            Err(MockBlockchainError::DoubleSpend(OutPoint::new(txid, 0)))
+        );
+        assert!(!chain.evict(spend_b));
+    }
+
+    #[test]
+    fn test_mock_fee_estimates() {
+        let mut chain = MockBlockchain::new(Network::Regtest);
+        assert_eq!(
+            chain.estimate_fee_rate(1),
+            Err(FeeEstimationError::NoEstimates)
+        );
+        chain.set_fee_estimates(FeeEstimates::new([(1, 10.0), (6, 2.0)]).unwrap());
+        assert_eq!(
+            chain.estimate_fee_rate(3),
+            Ok(FeeRate::from_sat_per_vb(10.0))
+        );
+    }
+}
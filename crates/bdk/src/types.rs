@@ -11,7 +11,7 @@
 
 use alloc::boxed::Box;
 use core::convert::AsRef;
-use core::ops::Sub;
+use core::ops::{Add, Sub};
 
 use bdk_chain::ConfirmationTime;
 use bitcoin::blockdata::transaction::{OutPoint, TxOut};
@@ -19,6 +19,10 @@ use bitcoin::{psbt, Weight};
 
 use serde::{Deserialize, Serialize};
 
+// Base weight of a Txin, not counting the weight needed for satisfying it.
+// prev_txid (32 bytes) + prev_vout (4 bytes) + sequence (4 bytes)
+pub(crate) const TXIN_BASE_WEIGHT: usize = (32 + 4 + 4) * 4;
+
 /// Types of keychains
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Ord, PartialOrd)]
 pub enum KeychainKind {
@@ -119,6 +123,39 @@ impl FeeRate {
         self.0 * 250.0_f32
     }
 
+    /// Return the value as satoshi/kvbytes
+    pub fn as_sat_per_kvb(&self) -> f32 {
+        self.0 * 1000.0_f32
+    }
+
+    /// Return the value as btc/kvbytes
+    pub fn as_btc_per_kvb(&self) -> f32 {
+        self.0 / 1e5
+    }
+
+    /// Return whether the fee rate is lower than the default min relay fee, in which case
+    /// transactions paying it won't be relayed by most nodes
+    pub fn is_below_min_relay_fee(&self) -> bool {
+        *self < FeeRate::default_min_relay_fee()
+    }
+
+    /// Add two fee rates, returning `None` if the result isn't a valid fee rate
+    pub fn checked_add(self, other: FeeRate) -> Option<FeeRate> {
+        let value = self.0 + other.0;
+        (value.is_finite() && value >= 0.0).then_some(FeeRate(value))
+    }
+
+    /// Subtract `other` from this fee rate, returning `None` if the result is negative
+    pub fn checked_sub(self, other: FeeRate) -> Option<FeeRate> {
+        let value = self.0 - other.0;
+        (value.is_finite() && value >= 0.0).then_some(FeeRate(value))
+    }
+
+    /// Subtract `other` from this fee rate, returning a zero fee rate if the result is negative
+    pub fn saturating_sub(self, other: FeeRate) -> FeeRate {
+        self.checked_sub(other).unwrap_or(FeeRate(0.0))
+    }
+
     /// Calculate absolute fee in Satoshis using size in weight units.
     pub fn fee_wu(&self, wu: Weight) -> u64 {
         self.fee_vb(wu.to_vbytes_ceil() as usize)
@@ -136,6 +173,14 @@ impl Default for FeeRate {
     }
 }
 
+impl Add for FeeRate {
+    type Output = Self;
+
+    fn add(self, other: FeeRate) -> Self::Output {
+        FeeRate(self.0 + other.0)
+    }
+}
+
 impl Sub for FeeRate {
     type Output = Self;
 
@@ -188,6 +233,31 @@ pub struct WeightedUtxo {
     pub utxo: Utxo,
 }
 
+impl WeightedUtxo {
+    /// Fee for spending this UTXO as an input at `fee_rate`
+    pub fn fee(&self, fee_rate: FeeRate) -> u64 {
+        fee_rate.fee_wu(Weight::from_wu(
+            (TXIN_BASE_WEIGHT + self.satisfaction_weight) as u64,
+        ))
+    }
+
+    /// Value of this UTXO minus the fee for spending it at `fee_rate`
+    pub fn effective_value(&self, fee_rate: FeeRate) -> i64 {
+        self.utxo.txout().value as i64 - self.fee(fee_rate) as i64
+    }
+
+    /// Height of the block confirming this UTXO, `None` if it's unconfirmed or foreign
+    pub fn confirmation_height(&self) -> Option<u32> {
+        match &self.utxo {
+            Utxo::Local(LocalOutput {
+                confirmation_time: ConfirmationTime::Confirmed { height, .. },
+                ..
+            }) => Some(*height),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 /// An unspent transaction output (UTXO).
 pub enum Utxo {
@@ -302,4 +372,52 @@ mod tests {
         assert!((fee.as_sat_per_vb() - 1.0).abs() < f32::EPSILON);
         assert_eq!(fee.sat_per_kwu(), 250.0);
     }
+
+    #[test]
+    fn test_feerate_conversions() {
+        let fee = FeeRate::from_sat_per_vb(2.0);
+        assert!((fee.as_sat_per_kvb() - 2000.0).abs() < f32::EPSILON);
+        assert!((fee.as_btc_per_kvb() - 2e-5).abs() < f32::EPSILON);
+        assert!(!fee.is_below_min_relay_fee());
+        assert!(FeeRate::from_sat_per_kvb(500.0).is_below_min_relay_fee());
+    }
+
+    #[test]
+    fn test_feerate_checked_math() {
+        let one = FeeRate::from_sat_per_vb(1.0);
+        let two = FeeRate::from_sat_per_vb(2.0);
+        assert_eq!(one.checked_add(one), Some(two));
+        assert_eq!(two.checked_sub(one), Some(one));
+        assert_eq!(one.checked_sub(two), None);
+        assert_eq!(one.saturating_sub(two), FeeRate::from_sat_per_vb(0.0));
+        let max = FeeRate::from_sat_per_vb(f32::MAX);
+        assert_eq!(max.checked_add(max), None);
+    }
+
+    #[test]
+    fn test_weighted_utxo_context() {
+        let weighted_utxo = WeightedUtxo {
+            // P2WPKH satisfaction weight
+            satisfaction_weight: 112,
+            utxo: Utxo::Local(LocalOutput {
+                outpoint: OutPoint::null(),
+                txout: TxOut {
+                    value: 10_000,
+                    script_pubkey: bitcoin::ScriptBuf::new(),
+                },
+                keychain: KeychainKind::External,
+                is_spent: false,
+                derivation_index: 0,
+                confirmation_time: ConfirmationTime::Confirmed {
+                    height: 42,
+                    time: 0,
+                },
+            }),
+        };
+
+        let fee_rate = FeeRate::from_sat_per_vb(2.0);
+        assert_eq!(weighted_utxo.fee(fee_rate), 136);
+        assert_eq!(weighted_utxo.effective_value(fee_rate), 10_000 - 136);
+        assert_eq!(weighted_utxo.confirmation_height(), Some(42));
+    }
 }
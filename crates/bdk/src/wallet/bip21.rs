@@ -0,0 +1,308 @@
+// Bitcoin Dev Kit
+//
+// Copyright (c) 2020-2021 Bitcoin Dev Kit Developers
+//
+// This file is licensed under the Apache License, Version 2.0 <LICENSE-APACHE
+// or http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your option.
+// You may not use this file except in accordance with one or both of these
+// licenses.
+
+//! BIP21 payment URIs
+//!
+//! This module implements parsing and serialization of `bitcoin:` URIs as described in
+//! [BIP21](https://github.com/bitcoin/bips/blob/master/bip-0021.mediawiki), including the `pj`
+//! parameter of [BIP78](https://github.com/bitcoin/bips/blob/master/bip-0078.mediawiki).
+//!
+//! ## Example
+//!
+//! ```
+//! # use bdk::wallet::bip21::Uri;
+//! # use bitcoin::Network;
+//! let uri = Uri::parse(
+//!     "bitcoin:tb1q4525hmgw265tl3drrl8jjta7ayffu6jf68ltjd?amount=0.0005&label=Coffee",
+//!     Network::Testnet,
+//! )?;
+//! assert_eq!(uri.amount, Some(50_000));
+//! assert_eq!(uri.label.as_deref(), Some("Coffee"));
+//! # Ok::<_, bdk::wallet::bip21::Bip21Error>(())
+//! ```
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use core::str::FromStr;
+
+use bitcoin::amount::{Denomination, ParseAmountError};
+use bitcoin::{Address, Amount, Network};
+
+const SCHEME: &str = "bitcoin:";
+
+/// A BIP21 payment URI
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Uri {
+    /// The address to pay
+    pub address: Address,
+    /// The amount requested, in satoshis
+    pub amount: Option<u64>,
+    /// A label for the address, e.g. the name of the receiver
+    pub label: Option<String>,
+    /// A message describing the payment
+    pub message: Option<String>,
+    /// The payjoin endpoint of the receiver (BIP78)
+    pub pj: Option<String>,
+    /// Any other parameter, in the order it appears in the URI
+    pub extras: Vec<(String, String)>,
+}
+
+impl Uri {
+    /// Create a URI paying `address`, without any parameter
+    pub fn new(address: Address) -> Self {
+        Uri {
+            address,
+            amount: None,
+            label: None,
+            message: None,
+            pj: None,
+            extras: vec![],
+        }
+    }
+
+    /// Parse a `bitcoin:` URI, checking that the address is valid for `network`
+    pub fn parse(uri: &str, network: Network) -> Result<Self, Bip21Error> {
+        let uri = match uri.get(..SCHEME.len()) {
+            Some(scheme) if scheme.eq_ignore_ascii_case(SCHEME) => &uri[SCHEME.len()..],
+            _ => return Err(Bip21Error::MissingScheme),
+        };
+        let (address, query) = match uri.split_once('?') {
+            Some((address, query)) => (address, Some(query)),
+            None => (uri, None),
+        };
+
+        let address = Address::from_str(address)
+            .and_then(|address| address.require_network(network))
+            .map_err(Bip21Error::Address)?;
+        let mut result = Uri::new(address);
+
+        for param in query.into_iter().flat_map(|q| q.split('&')) {
+            if param.is_empty() {
+                continue;
+            }
+            let (key, value) = param.split_once('=').unwrap_or((param, ""));
+            let value = percent_decode(value)?;
+            let slot = match key {
+                "amount" => {
+                    if result.amount.is_some() {
+                        return Err(Bip21Error::DuplicateParameter(key.to_string()));
+                    }
+                    let amount = Amount::from_str_in(&value, Denomination::Bitcoin)
+                        .map_err(Bip21Error::Amount)?;
+                    result.amount = Some(amount.to_sat());
+                    continue;
+                }
+                "label" => &mut result.label,
+                "message" => &mut result.message,
+                "pj" => &mut result.pj,
+                key if key.starts_with("req-") => {
+                    return Err(Bip21Error::UnknownRequiredParameter(key.to_string()))
+                }
+                key => {
+                    result.extras.push((key.to_string(), value));
+                    continue;
+                }
+            };
+            if slot.is_some() {
+                return Err(Bip21Error::DuplicateParameter(key.to_string()));
+            }
+            *slot = Some(value);
+        }
+
+        Ok(result)
+    }
+}
+
+impl fmt::Display for Uri {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", SCHEME, self.address)?;
+
+        let mut separator = '?';
+        let mut write_param = |f: &mut fmt::Formatter<'_>, key: &str, value: &str| {
+            write!(f, "{}{}={}", separator, key, percent_encode(value))?;
+            separator = '&';
+            Ok::<_, fmt::Error>(())
+        };
+
+        if let Some(amount) = self.amount {
+            write_param(
+                f,
+                "amount",
+                &Amount::from_sat(amount).to_string_in(Denomination::Bitcoin),
+            )?;
+        }
+        if let Some(label) = &self.label {
+            write_param(f, "label", label)?;
+        }
+        if let Some(message) = &self.message {
+            write_param(f, "message", message)?;
+        }
+        if let Some(pj) = &self.pj {
+            write_param(f, "pj", pj)?;
+        }
+        for (key, value) in &self.extras {
+            write_param(f, key, value)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn percent_encode(s: &str) -> String {
+    let mut encoded = String::with_capacity(s.len());
+    for byte in s.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+fn percent_decode(s: &str) -> Result<String, Bip21Error> {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let byte = bytes
+                .get(i + 1..i + 3)
+                .and_then(|hex| core::str::from_utf8(hex).ok())
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .ok_or(Bip21Error::InvalidEncoding)?;
+            decoded.push(byte);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).map_err(|_| Bip21Error::InvalidEncoding)
+}
+
+/// Errors returned when parsing a BIP21 [`Uri`]
+#[derive(Debug)]
+pub enum Bip21Error {
+    /// The URI doesn't start with `bitcoin:`
+    MissingScheme,
+    /// The address is invalid, or for a different network
+    Address(bitcoin::address::Error),
+    /// The amount is invalid
+    Amount(ParseAmountError),
+    /// A parameter isn't correctly percent-encoded UTF-8
+    InvalidEncoding,
+    /// A parameter appears more than once
+    DuplicateParameter(String),
+    /// A `req-` parameter that isn't supported
+    UnknownRequiredParameter(String),
+}
+
+impl fmt::Display for Bip21Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingScheme => write!(f, "URI doesn't start with `bitcoin:`"),
+            Self::Address(e) => write!(f, "Invalid address: {}", e),
+            Self::Amount(e) => write!(f, "Invalid amount: {}", e),
+            Self::InvalidEncoding => write!(f, "Invalid percent-encoding"),
+            Self::DuplicateParameter(key) => write!(f, "Duplicate parameter: {}", key),
+            Self::UnknownRequiredParameter(key) => {
+                write!(f, "Unknown required parameter: {}", key)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Bip21Error {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const ADDRESS: &str = "tb1q4525hmgw265tl3drrl8jjta7ayffu6jf68ltjd";
+
+    #[test]
+    fn test_parse_uri() {
+        let uri = Uri::parse(
+            &format!(
+                "BITCOIN:{}?amount=1.5&label=Luke-Jr&message=Donation%20for%20project%20xyz&pj=https://example.com/pj&foo=bar",
+                ADDRESS
+            ),
+            Network::Testnet,
+        )
+        .unwrap();
+
+        assert_eq!(uri.address.to_string(), ADDRESS);
+        assert_eq!(uri.amount, Some(150_000_000));
+        assert_eq!(uri.label.as_deref(), Some("Luke-Jr"));
+        assert_eq!(uri.message.as_deref(), Some("Donation for project xyz"));
+        assert_eq!(uri.pj.as_deref(), Some("https://example.com/pj"));
+        assert_eq!(uri.extras, vec![("foo".to_string(), "bar".to_string())]);
+    }
+
+    #[test]
+    fn test_parse_uri_errors() {
+        assert!(matches!(
+            Uri::parse(ADDRESS, Network::Testnet),
+            Err(Bip21Error::MissingScheme)
+        ));
+        assert!(matches!(
+            Uri::parse(&format!("bitcoin:{}", ADDRESS), Network::Bitcoin),
+            Err(Bip21Error::Address(_))
+        ));
+        assert!(matches!(
+            Uri::parse(&format!("bitcoin:{}?amount=1,5", ADDRESS), Network::Testnet),
+            Err(Bip21Error::Amount(_))
+        ));
+        assert!(matches!(
+            Uri::parse(&format!("bitcoin:{}?label=%ZZ", ADDRESS), Network::Testnet),
+            Err(Bip21Error::InvalidEncoding)
+        ));
+        assert!(matches!(
+            Uri::parse(
+                &format!("bitcoin:{}?label=a&label=b", ADDRESS),
+                Network::Testnet
+            ),
+            Err(Bip21Error::DuplicateParameter(_))
+        ));
+        assert!(matches!(
+            Uri::parse(
+                &format!("bitcoin:{}?req-somethingyoudontunderstand=50", ADDRESS),
+                Network::Testnet
+            ),
+            Err(Bip21Error::UnknownRequiredParameter(_))
+        ));
+    }
+
+    #[test]
+    fn test_uri_roundtrip() {
+        let mut uri = Uri::new(
+            Address::from_str(ADDRESS)
+                .unwrap()
+                .require_network(Network::Testnet)
+                .unwrap(),
+        );
+        uri.amount = Some(50_000);
+        uri.label = Some("Coffee & cake".to_string());
+
+        let serialized = uri.to_string();
+        assert_eq!(
+            serialized,
+            format!(
+                "bitcoin:{}?amount=0.0005&label=Coffee%20%26%20cake",
+                ADDRESS
+            )
+        );
+        assert_eq!(Uri::parse(&serialized, Network::Testnet).unwrap(), uri);
+    }
+}
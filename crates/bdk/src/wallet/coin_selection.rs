@@ -32,7 +32,6 @@
 //! # use bdk::*;
 //! # use bdk::wallet::coin_selection::decide_change;
 //! # use anyhow::Error;
-//! # const TXIN_BASE_WEIGHT: usize = (32 + 4 + 4) * 4;
 //! #[derive(Debug)]
 //! struct AlwaysSpendEverything;
 //!
@@ -46,22 +45,19 @@
 //!         drain_script: &Script,
 //!     ) -> Result<CoinSelectionResult, coin_selection::Error> {
 //!         let mut selected_amount = 0;
-//!         let mut additional_weight = Weight::ZERO;
+//!         let mut additional_fees = 0;
 //!         let all_utxos_selected = required_utxos
 //!             .into_iter()
 //!             .chain(optional_utxos)
 //!             .scan(
-//!                 (&mut selected_amount, &mut additional_weight),
-//!                 |(selected_amount, additional_weight), weighted_utxo| {
+//!                 (&mut selected_amount, &mut additional_fees),
+//!                 |(selected_amount, additional_fees), weighted_utxo| {
 //!                     **selected_amount += weighted_utxo.utxo.txout().value;
-//!                     **additional_weight += Weight::from_wu(
-//!                         (TXIN_BASE_WEIGHT + weighted_utxo.satisfaction_weight) as u64,
-//!                     );
+//!                     **additional_fees += weighted_utxo.fee(fee_rate);
 //!                     Some(weighted_utxo.utxo)
 //!                 },
 //!             )
 //!             .collect::<Vec<_>>();
-//!         let additional_fees = fee_rate.fee_wu(additional_weight);
 //!         let amount_needed_with_fees = additional_fees + target_amount;
 //!         if selected_amount < amount_needed_with_fees {
 //!             return Err(coin_selection::Error::InsufficientFunds {
@@ -100,15 +96,19 @@
 //! # Ok::<(), anyhow::Error>(())
 //! ```
 
+use crate::collections::{BTreeMap, BTreeSet};
 use crate::types::FeeRate;
 use crate::wallet::utils::IsDust;
 use crate::Utxo;
 use crate::WeightedUtxo;
 
+use alloc::boxed::Box;
+use alloc::sync::Arc;
 use alloc::vec::Vec;
 use bitcoin::consensus::encode::serialize;
-use bitcoin::{Script, Weight};
+use bitcoin::{Script, ScriptBuf};
 
+use core::cmp::Reverse;
 use core::convert::TryInto;
 use core::fmt::{self, Formatter};
 use rand::seq::SliceRandom;
@@ -117,10 +117,6 @@ use rand::seq::SliceRandom;
 /// overridden
 pub type DefaultCoinSelectionAlgorithm = BranchAndBoundCoinSelection;
 
-// Base weight of a Txin, not counting the weight needed for satisfying it.
-// prev_txid (32 bytes) + prev_vout (4 bytes) + sequence (4 bytes)
-pub(crate) const TXIN_BASE_WEIGHT: usize = (32 + 4 + 4) * 4;
-
 /// Errors that can be thrown by the [`coin_selection`](crate::wallet::coin_selection) module
 #[derive(Debug)]
 pub enum Error {
@@ -158,7 +154,7 @@ impl fmt::Display for Error {
 #[cfg(feature = "std")]
 impl std::error::Error for Error {}
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 /// Remaining amount after performing coin selection
 pub enum Excess {
     /// It's not possible to create spendable output from excess using the current drain output
@@ -238,6 +234,34 @@ pub trait CoinSelectionAlgorithm: core::fmt::Debug {
     ) -> Result<CoinSelectionResult, Error>;
 }
 
+macro_rules! impl_coin_selection_for_pointer {
+    ( $( $pointer:ty ),* ) => {
+        $(
+            impl<T: CoinSelectionAlgorithm + ?Sized> CoinSelectionAlgorithm for $pointer {
+                fn coin_select(
+                    &self,
+                    required_utxos: Vec<WeightedUtxo>,
+                    optional_utxos: Vec<WeightedUtxo>,
+                    fee_rate: FeeRate,
+                    target_amount: u64,
+                    drain_script: &Script,
+                ) -> Result<CoinSelectionResult, Error> {
+                    (**self).coin_select(
+                        required_utxos,
+                        optional_utxos,
+                        fee_rate,
+                        target_amount,
+                        drain_script,
+                    )
+                }
+            }
+        )*
+    };
+}
+
+// Allows picking the coin selection algorithm at runtime with `Box<dyn CoinSelectionAlgorithm>`
+impl_coin_selection_for_pointer!(&T, Box<T>, Arc<T>);
+
 /// Simple and dumb coin selection
 ///
 /// This coin selection algorithm sorts the available UTXOs by value and then picks them starting
@@ -303,6 +327,114 @@ impl CoinSelectionAlgorithm for OldestFirstCoinSelection {
     }
 }
 
+/// Coin selection that avoids spending only some of the UTXOs sent to the same address
+///
+/// Spending a subset of the UTXOs of an address links them together while leaving the others
+/// behind, which can later be linked again. This coin selection algorithm groups the available
+/// UTXOs by script pubkey and always spends a group as a whole, picking the groups with the
+/// largest value first. Groups sharing their script pubkey with a required UTXO are always spent.
+///
+/// Spending whole groups can require more inputs than necessary. If the grouped selection costs
+/// more than `max_extra_fee` satoshis of additional input fees compared to
+/// [`LargestFirstCoinSelection`], the latter is used instead.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct AvoidPartialSpendsCoinSelection {
+    max_extra_fee: u64,
+}
+
+impl AvoidPartialSpendsCoinSelection {
+    /// Create new instance accepting up to `max_extra_fee` satoshis of additional fees to avoid
+    /// partial spends
+    pub fn new(max_extra_fee: u64) -> Self {
+        Self { max_extra_fee }
+    }
+
+    fn grouped_coin_select(
+        required_utxos: Vec<WeightedUtxo>,
+        optional_utxos: Vec<WeightedUtxo>,
+        fee_rate: FeeRate,
+        target_amount: u64,
+        drain_script: &Script,
+    ) -> Result<CoinSelectionResult, Error> {
+        let required_spks = required_utxos
+            .iter()
+            .map(|u| u.utxo.txout().script_pubkey.clone())
+            .collect::<BTreeSet<_>>();
+
+        let mut groups: BTreeMap<ScriptBuf, Vec<WeightedUtxo>> = BTreeMap::new();
+        for utxo in optional_utxos {
+            groups
+                .entry(utxo.utxo.txout().script_pubkey.clone())
+                .or_default()
+                .push(utxo);
+        }
+
+        let mut selected = required_utxos;
+        let mut optional_groups = Vec::new();
+        for (spk, group) in groups {
+            if required_spks.contains(&spk) {
+                selected.extend(group);
+            } else {
+                optional_groups.push(group);
+            }
+        }
+        optional_groups.sort_unstable_by_key(|group| {
+            Reverse(group.iter().map(|u| u.utxo.txout().value).sum::<u64>())
+        });
+
+        let input_fee = |u: &WeightedUtxo| u.fee(fee_rate);
+        let mut selected_amount = selected.iter().map(|u| u.utxo.txout().value).sum::<u64>();
+        let mut fee_amount = selected.iter().map(input_fee).sum::<u64>();
+        for group in optional_groups {
+            if selected_amount >= target_amount + fee_amount {
+                break;
+            }
+            selected_amount += group.iter().map(|u| u.utxo.txout().value).sum::<u64>();
+            fee_amount += group.iter().map(input_fee).sum::<u64>();
+            selected.extend(group);
+        }
+
+        select_sorted_utxos(
+            selected.into_iter().map(|utxo| (true, utxo)),
+            fee_rate,
+            target_amount,
+            drain_script,
+        )
+    }
+}
+
+impl CoinSelectionAlgorithm for AvoidPartialSpendsCoinSelection {
+    fn coin_select(
+        &self,
+        required_utxos: Vec<WeightedUtxo>,
+        optional_utxos: Vec<WeightedUtxo>,
+        fee_rate: FeeRate,
+        target_amount: u64,
+        drain_script: &Script,
+    ) -> Result<CoinSelectionResult, Error> {
+        let grouped = Self::grouped_coin_select(
+            required_utxos.clone(),
+            optional_utxos.clone(),
+            fee_rate,
+            target_amount,
+            drain_script,
+        )?;
+
+        match LargestFirstCoinSelection.coin_select(
+            required_utxos,
+            optional_utxos,
+            fee_rate,
+            target_amount,
+            drain_script,
+        ) {
+            Ok(ungrouped) if grouped.fee_amount > ungrouped.fee_amount + self.max_extra_fee => {
+                Ok(ungrouped)
+            }
+            _ => Ok(grouped),
+        }
+    }
+}
+
 /// Decide if change can be created
 ///
 /// - `remaining_amount`: the amount in which the selected coins exceed the target amount
@@ -329,6 +461,44 @@ pub fn decide_change(remaining_amount: u64, fee_rate: FeeRate, drain_script: &Sc
     }
 }
 
+/// Calculate the waste metric of a coin selection
+///
+/// The waste is the extra fee paid for spending the `selected` inputs at `fee_rate` instead of at
+/// `long_term_fee_rate`, plus the cost of the `excess`: the amount dropped to fees when no change is
+/// created, or the fee of the change output otherwise. When comparing candidate selections, the
+/// one with the lowest waste is preferred.
+///
+/// The waste can be negative when `fee_rate` is lower than `long_term_fee_rate`, meaning that
+/// spending the inputs now is cheaper than spending them later.
+pub fn waste(
+    selected: &[WeightedUtxo],
+    excess: &Excess,
+    fee_rate: FeeRate,
+    long_term_fee_rate: FeeRate,
+) -> i64 {
+    let inputs_waste = selected
+        .iter()
+        .map(|u| input_waste(u, fee_rate, long_term_fee_rate))
+        .sum::<i64>();
+    let excess_waste = match excess {
+        Excess::NoChange {
+            remaining_amount, ..
+        } => *remaining_amount,
+        Excess::Change { fee, .. } => *fee,
+    };
+
+    inputs_waste + excess_waste as i64
+}
+
+// Difference between the fee for spending `weighted_utxo` at `fee_rate` and at `long_term_fee_rate`
+fn input_waste(
+    weighted_utxo: &WeightedUtxo,
+    fee_rate: FeeRate,
+    long_term_fee_rate: FeeRate,
+) -> i64 {
+    weighted_utxo.fee(fee_rate) as i64 - weighted_utxo.fee(long_term_fee_rate) as i64
+}
+
 fn select_sorted_utxos(
     utxos: impl Iterator<Item = (bool, WeightedUtxo)>,
     fee_rate: FeeRate,
@@ -342,9 +512,7 @@ fn select_sorted_utxos(
             (&mut selected_amount, &mut fee_amount),
             |(selected_amount, fee_amount), (must_use, weighted_utxo)| {
                 if must_use || **selected_amount < target_amount + **fee_amount {
-                    **fee_amount += fee_rate.fee_wu(Weight::from_wu(
-                        (TXIN_BASE_WEIGHT + weighted_utxo.satisfaction_weight) as u64,
-                    ));
+                    **fee_amount += weighted_utxo.fee(fee_rate);
                     **selected_amount += weighted_utxo.utxo.txout().value;
                     Some(weighted_utxo.utxo)
                 } else {
@@ -385,10 +553,8 @@ struct OutputGroup {
 
 impl OutputGroup {
     fn new(weighted_utxo: WeightedUtxo, fee_rate: FeeRate) -> Self {
-        let fee = fee_rate.fee_wu(Weight::from_wu(
-            (TXIN_BASE_WEIGHT + weighted_utxo.satisfaction_weight) as u64,
-        ));
-        let effective_value = weighted_utxo.utxo.txout().value as i64 - fee as i64;
+        let fee = weighted_utxo.fee(fee_rate);
+        let effective_value = weighted_utxo.effective_value(fee_rate);
         OutputGroup {
             weighted_utxo,
             fee,
@@ -400,9 +566,16 @@ impl OutputGroup {
 /// Branch and bound coin selection
 ///
 /// Code adapted from Bitcoin Core's implementation and from Mark Erhardt Master's Thesis: <http://murch.one/wp-content/uploads/2016/11/erhardt2016coinselection.pdf>
+///
+/// Among the changeless solutions found, the one with the lowest [`waste`] is picked. By default
+/// the long term fee rate used to compute the waste is the fee rate of the transaction, so the
+/// solution with the smallest excess wins. Set it with
+/// [`with_long_term_fee_rate`](Self::with_long_term_fee_rate) to also account for the cost of the
+/// inputs.
 #[derive(Debug, Clone)]
 pub struct BranchAndBoundCoinSelection {
     size_of_change: u64,
+    long_term_fee_rate: Option<FeeRate>,
 }
 
 impl Default for BranchAndBoundCoinSelection {
@@ -410,6 +583,7 @@ impl Default for BranchAndBoundCoinSelection {
         Self {
             // P2WPKH cost of change -> value (8 bytes) + script len (1 bytes) + script (22 bytes)
             size_of_change: 8 + 1 + 22,
+            long_term_fee_rate: None,
         }
     }
 }
@@ -417,7 +591,17 @@ impl Default for BranchAndBoundCoinSelection {
 impl BranchAndBoundCoinSelection {
     /// Create new instance with target size for change output
     pub fn new(size_of_change: u64) -> Self {
-        Self { size_of_change }
+        Self {
+            size_of_change,
+            long_term_fee_rate: None,
+        }
+    }
+
+    /// Set the fee rate expected to be paid in the long term, used to compute the [`waste`] of
+    /// the candidate selections
+    pub fn with_long_term_fee_rate(mut self, long_term_fee_rate: FeeRate) -> Self {
+        self.long_term_fee_rate = Some(long_term_fee_rate);
+        self
     }
 }
 
@@ -559,9 +743,11 @@ impl BranchAndBoundCoinSelection {
         optional_utxos.sort_unstable_by_key(|a| a.effective_value);
         optional_utxos.reverse();
 
+        let long_term_fee_rate = self.long_term_fee_rate.unwrap_or(fee_rate);
+
         // Contains the best selection we found
         let mut best_selection = Vec::new();
-        let mut best_selection_value = None;
+        let mut best_selection_waste = None;
 
         // Depth First search loop for choosing the UTXOs
         for _ in 0..BNB_TOTAL_TRIES {
@@ -579,16 +765,21 @@ impl BranchAndBoundCoinSelection {
                 // backtracking
                 backtrack = true;
 
+                // The waste of the required utxos is the same for every solution, so it's not
+                // taken into account when comparing them
+                let curr_waste = current_selection
+                    .iter()
+                    .zip(optional_utxos.iter())
+                    .filter(|(is_selected, _)| **is_selected)
+                    .map(|(_, u)| input_waste(&u.weighted_utxo, fee_rate, long_term_fee_rate))
+                    .sum::<i64>()
+                    + (curr_value - target_amount);
+
                 // If we found a solution better than the previous one, or if there wasn't previous
                 // solution, update the best solution
-                if best_selection_value.is_none() || curr_value < best_selection_value.unwrap() {
+                if best_selection_waste.map_or(true, |best_waste| curr_waste < best_waste) {
                     best_selection = current_selection.clone();
-                    best_selection_value = Some(curr_value);
-                }
-
-                // If we found a perfect match, break here
-                if curr_value == target_amount {
-                    break;
+                    best_selection_waste = Some(curr_waste);
                 }
             }
 
@@ -641,7 +832,11 @@ impl BranchAndBoundCoinSelection {
             .filter_map(|(optional, is_in_best)| if is_in_best { Some(optional) } else { None })
             .collect::<Vec<OutputGroup>>();
 
-        let selected_amount = best_selection_value.unwrap();
+        let selected_amount = required_utxos
+            .iter()
+            .chain(selected_utxos.iter())
+            .map(|u| u.effective_value)
+            .sum::<i64>();
 
         // remaining_amount can't be negative as that would mean the
         // selection wasn't successful
@@ -1381,6 +1576,152 @@ mod test {
         }
     }
 
+    fn utxo_with_spk(value: u64, index: u32, script_pubkey: ScriptBuf) -> WeightedUtxo {
+        let mut weighted_utxo = utxo(value, index, ConfirmationTime::Unconfirmed { last_seen: 0 });
+        if let Utxo::Local(local) = &mut weighted_utxo.utxo {
+            local.txout.script_pubkey = script_pubkey;
+        }
+        weighted_utxo
+    }
+
+    #[test]
+    fn test_avoid_partial_spends_coin_selection() {
+        let spk_a = ScriptBuf::from(vec![0xaa]);
+        let spk_b = ScriptBuf::from(vec![0xbb]);
+        let utxos = vec![
+            utxo_with_spk(50_000, 0, spk_a.clone()),
+            utxo_with_spk(50_000, 1, spk_a.clone()),
+            utxo_with_spk(50_000, 2, spk_a.clone()),
+            utxo_with_spk(120_000, 3, spk_b),
+        ];
+        let fee_rate = FeeRate::from_sat_per_vb(1.0);
+        let drain_script = ScriptBuf::default();
+        let target_amount = 130_000;
+
+        // Spending the whole group of `spk_a` costs one more input (68 sats) than spending the
+        // largest utxo and one of the utxos of `spk_a`
+        let result = AvoidPartialSpendsCoinSelection::new(100)
+            .coin_select(
+                vec![],
+                utxos.clone(),
+                fee_rate,
+                target_amount,
+                &drain_script,
+            )
+            .unwrap();
+        assert_eq!(result.selected.len(), 3);
+        assert!(result
+            .selected
+            .iter()
+            .all(|u| u.txout().script_pubkey == spk_a));
+        assert_eq!(result.fee_amount, 204);
+
+        let result = AvoidPartialSpendsCoinSelection::new(50)
+            .coin_select(
+                vec![],
+                utxos.clone(),
+                fee_rate,
+                target_amount,
+                &drain_script,
+            )
+            .unwrap();
+        assert_eq!(result.selected.len(), 2);
+        assert_eq!(result.selected_amount(), 170_000);
+
+        // utxos sharing the script pubkey of a required utxo are always spent
+        let mut optional = utxos;
+        let required = vec![optional.remove(0)];
+        let result = AvoidPartialSpendsCoinSelection::new(1_000)
+            .coin_select(required, optional, fee_rate, 10_000, &drain_script)
+            .unwrap();
+        assert_eq!(result.selected.len(), 3);
+        assert_eq!(result.selected_amount(), 150_000);
+    }
+
+    #[test]
+    fn test_bnb_prefers_lowest_waste() {
+        let fee_rate = FeeRate::from_sat_per_vb(10.0);
+        // Spending each utxo costs 680 sats at 10 sat/vB
+        let utxos = vec![
+            utxo(10_780, 0, ConfirmationTime::Unconfirmed { last_seen: 0 }),
+            utxo(5_680, 1, ConfirmationTime::Unconfirmed { last_seen: 0 }),
+            utxo(5_680, 2, ConfirmationTime::Unconfirmed { last_seen: 0 }),
+        ];
+        let drain_script = ScriptBuf::default();
+        let target_amount = 10_000;
+
+        // Without a long term fee rate the exact match of the two smaller utxos is preferred
+        let result = BranchAndBoundCoinSelection::default()
+            .coin_select(
+                vec![],
+                utxos.clone(),
+                fee_rate,
+                target_amount,
+                &drain_script,
+            )
+            .unwrap();
+        assert_eq!(result.selected.len(), 2);
+        assert_eq!(result.selected_amount(), 11_360);
+
+        // With a low long term fee rate spending one input and dropping 100 sats is cheaper
+        let result = BranchAndBoundCoinSelection::default()
+            .with_long_term_fee_rate(FeeRate::from_sat_per_vb(1.0))
+            .coin_select(vec![], utxos, fee_rate, target_amount, &drain_script)
+            .unwrap();
+        assert_eq!(result.selected.len(), 1);
+        assert_eq!(result.selected_amount(), 10_780);
+    }
+
+    #[test]
+    fn test_bnb_keeps_searching_after_exact_match() {
+        let fee_rate = FeeRate::from_sat_per_vb(1.0);
+        // Spending each utxo costs 68 sats at 1 sat/vB
+        let utxos = vec![
+            utxo(5_068, 0, ConfirmationTime::Unconfirmed { last_seen: 0 }),
+            utxo(5_068, 1, ConfirmationTime::Unconfirmed { last_seen: 0 }),
+            utxo(3_408, 2, ConfirmationTime::Unconfirmed { last_seen: 0 }),
+            utxo(3_408, 3, ConfirmationTime::Unconfirmed { last_seen: 0 }),
+            utxo(3_408, 4, ConfirmationTime::Unconfirmed { last_seen: 0 }),
+        ];
+        let drain_script = ScriptBuf::default();
+        let target_amount = 10_000;
+
+        // The two larger utxos are an exact match and are found first, but with a high long term
+        // fee rate consolidating one more utxo while dropping 20 sats wastes less
+        let result = BranchAndBoundCoinSelection::default()
+            .with_long_term_fee_rate(FeeRate::from_sat_per_vb(10.0))
+            .coin_select(vec![], utxos, fee_rate, target_amount, &drain_script)
+            .unwrap();
+        assert_eq!(result.selected.len(), 3);
+        assert_eq!(result.selected_amount(), 10_224);
+    }
+
+    #[test]
+    fn test_waste() {
+        let fee_rate = FeeRate::from_sat_per_vb(10.0);
+        let long_term_fee_rate = FeeRate::from_sat_per_vb(1.0);
+        let utxos = get_test_utxos();
+
+        let excess = Excess::NoChange {
+            dust_threshold: 294,
+            remaining_amount: 100,
+            change_fee: 310,
+        };
+        assert_eq!(
+            waste(&utxos, &excess, fee_rate, long_term_fee_rate),
+            3 * (680 - 68) + 100
+        );
+
+        let excess = Excess::Change {
+            amount: 10_000,
+            fee: 310,
+        };
+        assert_eq!(
+            waste(&utxos, &excess, long_term_fee_rate, fee_rate),
+            3 * (68 - 680) + 310
+        );
+    }
+
     #[test]
     fn test_single_random_draw_function_success() {
         let seed = [0; 32];
@@ -90,6 +90,25 @@ pub enum CreateTxError<P> {
     NoUtxosSelected,
     /// Output created is under the dust limit, 546 satoshis
     OutputBelowDustLimit(usize),
+    /// The OP_RETURN output at this index is larger than [`MAX_OP_RETURN_RELAY`] bytes, and
+    /// wouldn't be relayed by the network
+    ///
+    /// [`MAX_OP_RETURN_RELAY`]: crate::wallet::tx_builder::MAX_OP_RETURN_RELAY
+    OpReturnTooLarge {
+        /// Index of the output
+        index: usize,
+        /// Size of the output script, in bytes
+        size: usize,
+    },
+    /// An option refers to the recipient at this index, but there's no such recipient
+    InvalidRecipientIndex(usize),
+    /// The recipient at this index pays the same script as a previous one
+    DuplicateRecipient(usize),
+    /// The transaction would be larger than the maximum standard weight
+    TransactionTooLarge {
+        /// Estimated weight of the signed transaction
+        weight: u64,
+    },
     /// The `change_policy` was set but the wallet does not have a change_descriptor
     ChangePolicyDescriptor,
     /// There was an error with coin selection
@@ -115,6 +134,9 @@ pub enum CreateTxError<P> {
     UnknownUtxo,
     /// Missing non_witness_utxo on foreign utxo for given `OutPoint`
     MissingNonWitnessUtxo(OutPoint),
+    /// The input spending this `OutPoint` uses `SIGHASH_SINGLE`, but there's no output at the
+    /// same index
+    SighashSingleWithoutOutput(OutPoint),
     /// Miniscript PSBT error
     MiniscriptPsbt(MiniscriptPsbtError),
 }
@@ -178,6 +200,29 @@ where
             CreateTxError::OutputBelowDustLimit(limit) => {
                 write!(f, "Output below the dust limit: {}", limit)
             }
+            CreateTxError::OpReturnTooLarge { index, size } => {
+                write!(
+                    f,
+                    "OP_RETURN output {} is too large: {} bytes (max {})",
+                    index,
+                    size,
+                    crate::wallet::tx_builder::MAX_OP_RETURN_RELAY
+                )
+            }
+            CreateTxError::InvalidRecipientIndex(index) => {
+                write!(f, "Invalid recipient index: {}", index)
+            }
+            CreateTxError::DuplicateRecipient(index) => {
+                write!(f, "Duplicate recipient at index: {}", index)
+            }
+            CreateTxError::TransactionTooLarge { weight } => {
+                write!(
+                    f,
+                    "Transaction too large: {} wu (max {})",
+                    weight,
+                    bitcoin::policy::MAX_STANDARD_TX_WEIGHT
+                )
+            }
             CreateTxError::ChangePolicyDescriptor => {
                 write!(
                     f,
@@ -205,6 +250,13 @@ where
             CreateTxError::MissingNonWitnessUtxo(outpoint) => {
                 write!(f, "Missing non_witness_utxo on foreign utxo {}", outpoint)
             }
+            CreateTxError::SighashSingleWithoutOutput(outpoint) => {
+                write!(
+                    f,
+                    "Input {} uses SIGHASH_SINGLE without a corresponding output",
+                    outpoint
+                )
+            }
             CreateTxError::MiniscriptPsbt(err) => {
                 write!(f, "Miniscript PSBT error: {}", err)
             }
@@ -290,3 +342,90 @@ impl fmt::Display for BuildFeeBumpError {
 
 #[cfg(feature = "std")]
 impl std::error::Error for BuildFeeBumpError {}
+
+#[derive(Debug)]
+/// Error returned from [`Wallet::build_cpfp`]
+///
+/// [`Wallet::build_cpfp`]: super::Wallet::build_cpfp
+pub enum BuildCpfpError<P> {
+    /// Thrown when a tx is not found in the internal database
+    TransactionNotFound(Txid),
+    /// Happens when trying to accelerate a transaction that is already confirmed
+    TransactionConfirmed(Txid),
+    /// The transaction doesn't have any unspent output owned by the wallet
+    NoSpendableOutputs(Txid),
+    /// The fee of the transaction can't be calculated because some of its previous outputs are
+    /// missing
+    FeeRateUnavailable,
+    /// Error while estimating the size of the child transaction
+    CreateTx(CreateTxError<P>),
+}
+
+impl<P> fmt::Display for BuildCpfpError<P>
+where
+    P: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TransactionNotFound(txid) => {
+                write!(
+                    f,
+                    "Transaction not found in the internal database with txid: {}",
+                    txid
+                )
+            }
+            Self::TransactionConfirmed(txid) => {
+                write!(f, "Transaction already confirmed with txid: {}", txid)
+            }
+            Self::NoSpendableOutputs(txid) => {
+                write!(
+                    f,
+                    "Transaction has no spendable outputs with txid: {}",
+                    txid
+                )
+            }
+            Self::FeeRateUnavailable => write!(f, "Fee rate unavailable"),
+            Self::CreateTx(e) => e.fmt(f),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<P: fmt::Display + fmt::Debug> std::error::Error for BuildCpfpError<P> {}
+
+#[derive(Debug)]
+/// Error returned from [`Wallet::combine_psbts`]
+///
+/// [`Wallet::combine_psbts`]: super::Wallet::combine_psbts
+pub enum CombinePsbtError {
+    /// No PSBT was given
+    NoPsbts,
+    /// The PSBTs can't be combined, for instance because they are for different transactions
+    Psbt(psbt::Error),
+    /// The input spending this outpoint has a signature made by a key that isn't part of the
+    /// wallet's descriptor at the index of the spent script
+    UnexpectedSignature(OutPoint),
+    /// The input spending this outpoint has a signature that isn't valid for the transaction
+    InvalidSignature(OutPoint),
+    /// The hash signed by a signature couldn't be computed
+    Signer(crate::signer::SignerError),
+}
+
+impl fmt::Display for CombinePsbtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoPsbts => write!(f, "No PSBT to combine"),
+            Self::Psbt(e) => write!(f, "Failed to combine PSBTs: {}", e),
+            Self::UnexpectedSignature(outpoint) => {
+                write!(f, "Unexpected signature for input {}", outpoint)
+            }
+            Self::InvalidSignature(outpoint) => {
+                write!(f, "Invalid signature for input {}", outpoint)
+            }
+            Self::Signer(err) => write!(f, "Failed to verify the signatures: {}", err),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for CombinePsbtError {}
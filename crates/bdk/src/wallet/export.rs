@@ -52,10 +52,32 @@
 //! println!("Exported: {}", export.to_string());
 //! # Ok::<_, Box<dyn std::error::Error>>(())
 //! ```
+//!
+//! ### Export the public descriptors for Bitcoin Core
+//!
+//! [`CoreImportDescriptor`] exports the watch-only descriptors of a wallet in the format of the
+//! `importdescriptors` RPC of Bitcoin Core.
+//!
+//! ```
+//! # use bitcoin::*;
+//! # use bdk::wallet::export::*;
+//! # use bdk::*;
+//! # let wallet = Wallet::new_no_persist(
+//! #     "wpkh([c258d2e4/84h/1h/0h]tpubDD3ynpHgJQW8VvWRzQ5WFDCrs4jqVFGHB3vLC3r49XHJSqP8bHKdK4AriuUKLccK68zfzowx7YhmDN8SiSkgCDENUFx9qVw65YyqM78vyVe/0/*)",
+//! #     Some("wpkh([c258d2e4/84h/1h/0h]tpubDD3ynpHgJQW8VvWRzQ5WFDCrs4jqVFGHB3vLC3r49XHJSqP8bHKdK4AriuUKLccK68zfzowx7YhmDN8SiSkgCDENUFx9qVw65YyqM78vyVe/1/*)"),
+//! #     Network::Testnet,
+//! # )?;
+//! let descriptors = CoreImportDescriptor::export_wallet(&wallet, true);
+//!
+//! // bitcoin-cli importdescriptors '<json>'
+//! println!("{}", serde_json::to_string(&descriptors)?);
+//! # Ok::<_, Box<dyn std::error::Error>>(())
+//! ```
 
 use core::str::FromStr;
 
 use alloc::string::{String, ToString};
+use alloc::vec::Vec;
 use serde::{Deserialize, Serialize};
 
 use miniscript::descriptor::{ShInner, WshInner};
@@ -210,6 +232,66 @@ impl FullyNodedExport {
     }
 }
 
+/// A public descriptor of a wallet, in the format of the `importdescriptors` RPC of Bitcoin Core
+///
+/// For a usage example see [this module](crate::wallet::export)'s documentation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoreImportDescriptor {
+    /// The descriptor, without private keys but with key origins and checksum
+    pub desc: String,
+    /// Whether the descriptor is used to receive (`false`) or for change (`true`)
+    pub internal: bool,
+    /// Whether Bitcoin Core should derive new addresses from the descriptor
+    pub active: bool,
+    /// The first and last derivation index to import, if the descriptor has a wildcard
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub range: Option<[u32; 2]>,
+    /// Unix timestamp of the earliest block to rescan when looking for the wallet's transactions
+    pub timestamp: u64,
+}
+
+impl CoreImportDescriptor {
+    /// Export the public descriptors of a wallet
+    ///
+    /// Every descriptor is imported up to the last derivation index revealed by the wallet. A
+    /// wallet without a change descriptor exports only its external one.
+    ///
+    /// If `include_timestamp` is `true`, this function will look into the `wallet`'s database
+    /// for the oldest confirmed transaction it knows and use its block time as the `timestamp`
+    /// to rescan from. Otherwise, or if there isn't any, the `timestamp` is `0`.
+    pub fn export_wallet<D>(wallet: &Wallet<D>, include_timestamp: bool) -> Vec<Self> {
+        let timestamp = if include_timestamp {
+            wallet
+                .transactions()
+                .filter_map(|canonical_tx| match canonical_tx.chain_position {
+                    bdk_chain::ChainPosition::Confirmed(a) => Some(a.confirmation_time),
+                    bdk_chain::ChainPosition::Unconfirmed(_) => None,
+                })
+                .min()
+                .unwrap_or(0)
+        } else {
+            0
+        };
+
+        [KeychainKind::External, KeychainKind::Internal]
+            .into_iter()
+            .filter_map(|keychain| {
+                let descriptor = wallet.public_descriptor(keychain)?;
+                let range = descriptor
+                    .has_wildcard()
+                    .then(|| [0, wallet.derivation_index(keychain).unwrap_or(0)]);
+                Some(CoreImportDescriptor {
+                    desc: descriptor.to_string(),
+                    internal: keychain == KeychainKind::Internal,
+                    active: true,
+                    range,
+                    timestamp,
+                })
+            })
+            .collect()
+    }
+}
+
 #[cfg(test)]
 mod test {
     use core::str::FromStr;
@@ -219,6 +301,7 @@ mod test {
     use bitcoin::{BlockHash, Network, Transaction};
 
     use super::*;
+    use crate::descriptor::calc_checksum;
     use crate::wallet::Wallet;
 
     fn get_test_wallet(
@@ -324,6 +407,44 @@ mod test {
         assert_eq!(export.to_string(), "{\"descriptor\":\"wpkh(xprv9s21ZrQH143K4CTb63EaMxja1YiTnSEWKMbn23uoEnAzxjdUJRQkazCAtzxGm4LSoTSVTptoV9RbchnKPW9HxKtZumdyxyikZFDLhogJ5Uj/44\'/0\'/0\'/0/*)\",\"blockheight\":5000,\"label\":\"Test Label\"}");
     }
 
+    #[test]
+    fn test_export_core_descriptors() {
+        let descriptor = "wpkh([73756c7f/84'/0'/0']tpubDCKxNyM3bLgbEX13Mcd8mYxbVg9ajDkWXMh29hMWBurKfVmBfWAM96QVP3zaUcN51HvkZ3ar4VwP82kC8JZhhux8vFQoJintSpVBwpFvyU3/0/*)";
+        let change_descriptor = "wpkh([73756c7f/84'/0'/0']tpubDCKxNyM3bLgbEX13Mcd8mYxbVg9ajDkWXMh29hMWBurKfVmBfWAM96QVP3zaUcN51HvkZ3ar4VwP82kC8JZhhux8vFQoJintSpVBwpFvyU3/1/*)";
+
+        let mut wallet = get_test_wallet(descriptor, Some(change_descriptor), Network::Testnet);
+        wallet.get_address(crate::wallet::AddressIndex::New);
+        wallet.get_address(crate::wallet::AddressIndex::New);
+        let export = CoreImportDescriptor::export_wallet(&wallet, true);
+
+        assert_eq!(export.len(), 2);
+        assert_eq!(
+            export[0].desc,
+            format!("{}#{}", descriptor, calc_checksum(descriptor).unwrap())
+        );
+        assert!(!export[0].internal);
+        assert_eq!(export[0].range, Some([0, 1]));
+        assert_eq!(
+            export[1].desc,
+            format!(
+                "{}#{}",
+                change_descriptor,
+                calc_checksum(change_descriptor).unwrap()
+            )
+        );
+        assert!(export[1].internal);
+        assert_eq!(export[1].range, Some([0, 0]));
+
+        // private keys are never exported
+        let descriptor = "wpkh(xprv9s21ZrQH143K4CTb63EaMxja1YiTnSEWKMbn23uoEnAzxjdUJRQkazCAtzxGm4LSoTSVTptoV9RbchnKPW9HxKtZumdyxyikZFDLhogJ5Uj/44'/0'/0'/0/*)";
+        let wallet = get_test_wallet(descriptor, None, Network::Bitcoin);
+        let export = CoreImportDescriptor::export_wallet(&wallet, false);
+        assert_eq!(export.len(), 1);
+        assert!(!export[0].desc.contains("xprv"));
+        assert!(export[0].desc.contains("xpub"));
+        assert_eq!(export[0].timestamp, 0);
+    }
+
     #[test]
     fn test_export_from_json() {
         let descriptor = "wpkh(xprv9s21ZrQH143K4CTb63EaMxja1YiTnSEWKMbn23uoEnAzxjdUJRQkazCAtzxGm4LSoTSVTptoV9RbchnKPW9HxKtZumdyxyikZFDLhogJ5Uj/44'/0'/0'/0/*)";
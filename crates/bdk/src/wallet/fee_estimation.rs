@@ -0,0 +1,460 @@
+// Bitcoin Dev Kit
+//
+// Copyright (c) 2020-2021 Bitcoin Dev Kit Developers
+//
+// This file is licensed under the Apache License, Version 2.0 <LICENSE-APACHE
+// or http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your option.
+// You may not use this file except in accordance with one or both of these
+// licenses.
+
+//! Fee estimation
+//!
+//! This module defines the [`FeeEstimator`] trait, used by
+//! [`TxBuilder::fee_estimator`](crate::wallet::tx_builder::TxBuilder::fee_estimator) to pick the
+//! fee rate of a transaction from a confirmation target.
+//!
+//! [`FeeEstimates`] validates and stores the estimates returned by the `/fee-estimates` endpoint
+//! of Esplora servers, including mempool.space, and [`CachedFeeEstimator`] fetches them again
+//! once they're too old.
+//! [`MedianFeeEstimator`] combines multiple sources, so that a single provider can't make the
+//! wallet overpay.
+//!
+//! ## Example
+//!
+//! ```
+//! # use bdk::wallet::fee_estimation::{FeeEstimates, MedianFeeEstimator};
+//! # use std::collections::HashMap;
+//! # let mut wallet = bdk::doctest_wallet!();
+//! # let to_address = wallet.get_address(bdk::wallet::AddressIndex::New).address;
+//! // e.g. `esplora_client.get_fee_estimates()?`
+//! let mempool_space = HashMap::from([("1".to_string(), 20.0), ("6".to_string(), 10.0)]);
+//! let blockstream = HashMap::from([("1".to_string(), 24.0), ("6".to_string(), 12.0)]);
+//!
+//! let estimator = MedianFeeEstimator::new(vec![
+//!     Box::new(FeeEstimates::from_esplora(&mempool_space)?),
+//!     Box::new(FeeEstimates::from_esplora(&blockstream)?),
+//! ]);
+//!
+//! let mut builder = wallet.build_tx();
+//! builder
+//!     .add_recipient(to_address.script_pubkey(), 50_000)
+//!     .fee_estimator(&estimator, 6)?;
+//! # Ok::<_, Box<dyn std::error::Error>>(())
+//! ```
+
+use crate::collections::{BTreeMap, HashMap};
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::types::FeeRate;
+
+/// Highest fee rate accepted from a fee source, in sat/vbyte
+///
+/// Anything higher is considered a bogus estimate rather than a real fee market.
+pub const MAX_FEE_RATE_ESTIMATE: f32 = 10_000.0;
+
+/// A source of fee rate estimates
+pub trait FeeEstimator {
+    /// Return the fee rate needed for a transaction to confirm within `target_blocks` blocks
+    fn estimate_fee_rate(&self, target_blocks: u16) -> Result<FeeRate, FeeEstimationError>;
+}
+
+impl<T: FeeEstimator + ?Sized> FeeEstimator for &T {
+    fn estimate_fee_rate(&self, target_blocks: u16) -> Result<FeeRate, FeeEstimationError> {
+        (**self).estimate_fee_rate(target_blocks)
+    }
+}
+
+impl<T: FeeEstimator + ?Sized> FeeEstimator for Box<T> {
+    fn estimate_fee_rate(&self, target_blocks: u16) -> Result<FeeRate, FeeEstimationError> {
+        (**self).estimate_fee_rate(target_blocks)
+    }
+}
+
+impl FeeEstimator for FeeRate {
+    fn estimate_fee_rate(&self, _target_blocks: u16) -> Result<FeeRate, FeeEstimationError> {
+        Ok(*self)
+    }
+}
+
+/// A snapshot of fee rate estimates, by confirmation target
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeeEstimates {
+    estimates: BTreeMap<u16, FeeRate>,
+}
+
+impl FeeEstimates {
+    /// Create the estimates from fee rates in sat/vbyte, by confirmation target in blocks
+    ///
+    /// Returns an error if there are no estimates, if a fee rate is negative, not finite or
+    /// higher than [`MAX_FEE_RATE_ESTIMATE`], or if a fee rate is lower than the one of a
+    /// longer target.
+    pub fn new(
+        estimates: impl IntoIterator<Item = (u16, f32)>,
+    ) -> Result<Self, FeeEstimationError> {
+        let estimates = estimates
+            .into_iter()
+            .map(|(target, rate)| {
+                if rate.is_finite() && (0.0..=MAX_FEE_RATE_ESTIMATE).contains(&rate) {
+                    Ok((target, FeeRate::from_sat_per_vb(rate)))
+                } else {
+                    Err(FeeEstimationError::InvalidEstimate { target, rate })
+                }
+            })
+            .collect::<Result<BTreeMap<_, _>, _>>()?;
+        if estimates.is_empty() {
+            return Err(FeeEstimationError::NoEstimates);
+        }
+        let mut previous: Option<(u16, FeeRate)> = None;
+        for (target, rate) in &estimates {
+            if let Some((_, previous_rate)) = previous {
+                if *rate > previous_rate {
+                    return Err(FeeEstimationError::InvalidEstimate {
+                        target: *target,
+                        rate: rate.as_sat_per_vb(),
+                    });
+                }
+            }
+            previous = Some((*target, *rate));
+        }
+
+        Ok(FeeEstimates { estimates })
+    }
+
+    /// Create the estimates from the response of the `/fee-estimates` endpoint of an Esplora
+    /// server, like mempool.space or blockstream.info
+    pub fn from_esplora(estimates: &HashMap<String, f64>) -> Result<Self, FeeEstimationError> {
+        let estimates = estimates
+            .iter()
+            .map(|(target, rate)| {
+                target
+                    .parse::<u16>()
+                    .map(|target| (target, *rate as f32))
+                    .map_err(|_| FeeEstimationError::InvalidTarget(target.clone()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::new(estimates)
+    }
+
+    /// Return the estimates, by confirmation target
+    pub fn estimates(&self) -> &BTreeMap<u16, FeeRate> {
+        &self.estimates
+    }
+}
+
+impl FeeEstimator for FeeEstimates {
+    /// Return the estimate of the longest target that is not longer than `target_blocks`, or
+    /// the estimate of the shortest target if they're all longer
+    fn estimate_fee_rate(&self, target_blocks: u16) -> Result<FeeRate, FeeEstimationError> {
+        self.estimates
+            .range(..=target_blocks)
+            .next_back()
+            .or_else(|| self.estimates.iter().next())
+            .map(|(_, rate)| *rate)
+            .ok_or(FeeEstimationError::NoEstimates)
+    }
+}
+
+/// Fetches [`FeeEstimates`] from a fee API and caches them for a while
+///
+/// `fetch` is called to refresh the estimates the first time they are needed, and then every
+/// time they are older than `max_age`. It usually queries the `/fee-estimates` endpoint of an
+/// Esplora server:
+///
+/// ```no_run
+/// # use bdk::wallet::fee_estimation::{CachedFeeEstimator, FeeEstimationError};
+/// # use std::collections::HashMap;
+/// # fn get_fee_estimates() -> Result<HashMap<String, f64>, String> { unimplemented!() }
+/// let estimator = CachedFeeEstimator::new(std::time::Duration::from_secs(60), || {
+///     // e.g. `esplora_client.get_fee_estimates()`
+///     get_fee_estimates().map_err(FeeEstimationError::Source)
+/// });
+/// ```
+///
+/// It's not available on `wasm32`, where [`std::time::Instant`] isn't supported.
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+pub struct CachedFeeEstimator<F> {
+    max_age: std::time::Duration,
+    fetch: F,
+    cache: core::cell::RefCell<Option<(std::time::Instant, FeeEstimates)>>,
+}
+
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+impl<F> CachedFeeEstimator<F>
+where
+    F: Fn() -> Result<HashMap<String, f64>, FeeEstimationError>,
+{
+    /// Create a new estimator refreshing the estimates with `fetch` once they are older than
+    /// `max_age`
+    pub fn new(max_age: std::time::Duration, fetch: F) -> Self {
+        CachedFeeEstimator {
+            max_age,
+            fetch,
+            cache: core::cell::RefCell::new(None),
+        }
+    }
+
+    /// Return the cached estimates, if any, even if they're expired
+    pub fn cached(&self) -> Option<FeeEstimates> {
+        self.cache
+            .borrow()
+            .as_ref()
+            .map(|(_, estimates)| estimates.clone())
+    }
+
+    /// Discard the cached estimates, so that they are fetched again on the next estimate
+    pub fn invalidate(&self) {
+        self.cache.replace(None);
+    }
+}
+
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+impl<F> fmt::Debug for CachedFeeEstimator<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CachedFeeEstimator")
+            .field("max_age", &self.max_age)
+            .field("cache", &self.cache)
+            .finish()
+    }
+}
+
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+impl<F> FeeEstimator for CachedFeeEstimator<F>
+where
+    F: Fn() -> Result<HashMap<String, f64>, FeeEstimationError>,
+{
+    /// Return the estimate from the cached estimates, refreshing them first if they're expired
+    ///
+    /// If the estimates can't be refreshed, the error is returned and the expired estimates are
+    /// kept, to be used by [`cached`](Self::cached).
+    fn estimate_fee_rate(&self, target_blocks: u16) -> Result<FeeRate, FeeEstimationError> {
+        let now = std::time::Instant::now();
+        let expired = match &*self.cache.borrow() {
+            Some((fetched_at, _)) => now.duration_since(*fetched_at) >= self.max_age,
+            None => true,
+        };
+        if expired {
+            let estimates = FeeEstimates::from_esplora(&(self.fetch)()?)?;
+            self.cache.replace(Some((now, estimates)));
+        }
+
+        self.cache
+            .borrow()
+            .as_ref()
+            .expect("the estimates were just fetched")
+            .1
+            .estimate_fee_rate(target_blocks)
+    }
+}
+
+/// Combines multiple [`FeeEstimator`]s, returning the median of their estimates
+///
+/// Sources failing to give an estimate, or giving a negative or non-finite one, are ignored, as
+/// long as at least one succeeds. With an even number of estimates, the lower of the two middle
+/// ones is returned.
+pub struct MedianFeeEstimator {
+    estimators: Vec<Box<dyn FeeEstimator>>,
+}
+
+impl MedianFeeEstimator {
+    /// Create a new estimator combining `estimators`
+    pub fn new(estimators: Vec<Box<dyn FeeEstimator>>) -> Self {
+        MedianFeeEstimator { estimators }
+    }
+}
+
+impl fmt::Debug for MedianFeeEstimator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MedianFeeEstimator")
+            .field("estimators", &self.estimators.len())
+            .finish()
+    }
+}
+
+impl FeeEstimator for MedianFeeEstimator {
+    fn estimate_fee_rate(&self, target_blocks: u16) -> Result<FeeRate, FeeEstimationError> {
+        let mut last_error = FeeEstimationError::NoEstimates;
+        let mut rates = Vec::with_capacity(self.estimators.len());
+        for estimator in &self.estimators {
+            match estimator.estimate_fee_rate(target_blocks) {
+                Ok(rate) if rate.as_sat_per_vb().is_finite() && rate.as_sat_per_vb() >= 0.0 => {
+                    rates.push(rate)
+                }
+                Ok(rate) => {
+                    last_error = FeeEstimationError::InvalidEstimate {
+                        target: target_blocks,
+                        rate: rate.as_sat_per_vb(),
+                    }
+                }
+                Err(e) => last_error = e,
+            }
+        }
+        if rates.is_empty() {
+            return Err(last_error);
+        }
+        rates.sort_by(|a, b| a.as_sat_per_vb().total_cmp(&b.as_sat_per_vb()));
+
+        Ok(rates[(rates.len() - 1) / 2])
+    }
+}
+
+/// Errors returned when estimating a fee rate
+#[derive(Debug, Clone, PartialEq)]
+pub enum FeeEstimationError {
+    /// The source doesn't have any estimate
+    NoEstimates,
+    /// A confirmation target couldn't be parsed
+    InvalidTarget(String),
+    /// The fee rate for a target is invalid or inconsistent with the other targets
+    InvalidEstimate {
+        /// The confirmation target, in blocks
+        target: u16,
+        /// The fee rate, in sat/vbyte
+        rate: f32,
+    },
+    /// The source failed to give an estimate
+    Source(String),
+}
+
+impl fmt::Display for FeeEstimationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoEstimates => write!(f, "No fee estimates available"),
+            Self::InvalidTarget(target) => write!(f, "Invalid confirmation target: {}", target),
+            Self::InvalidEstimate { target, rate } => write!(
+                f,
+                "Invalid fee estimate for {} blocks: {} sat/vB",
+                target, rate
+            ),
+            Self::Source(e) => write!(f, "Fee estimation failed: {}", e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for FeeEstimationError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_fee_estimates() {
+        let estimates = FeeEstimates::new([(1, 20.0), (3, 15.0), (6, 10.0)]).unwrap();
+        assert_eq!(
+            estimates.estimate_fee_rate(3),
+            Ok(FeeRate::from_sat_per_vb(15.0))
+        );
+        assert_eq!(
+            estimates.estimate_fee_rate(5),
+            Ok(FeeRate::from_sat_per_vb(15.0))
+        );
+        assert_eq!(
+            estimates.estimate_fee_rate(144),
+            Ok(FeeRate::from_sat_per_vb(10.0))
+        );
+        assert_eq!(
+            estimates.estimate_fee_rate(0),
+            Ok(FeeRate::from_sat_per_vb(20.0))
+        );
+    }
+
+    #[test]
+    fn test_invalid_fee_estimates() {
+        assert_eq!(FeeEstimates::new([]), Err(FeeEstimationError::NoEstimates));
+        assert!(matches!(
+            FeeEstimates::new([(1, f32::NAN)]),
+            Err(FeeEstimationError::InvalidEstimate { target: 1, .. })
+        ));
+        assert!(matches!(
+            FeeEstimates::new([(1, 1e6)]),
+            Err(FeeEstimationError::InvalidEstimate { target: 1, .. })
+        ));
+        // a longer target can't require a higher fee rate
+        assert!(matches!(
+            FeeEstimates::new([(1, 10.0), (6, 20.0)]),
+            Err(FeeEstimationError::InvalidEstimate { target: 6, .. })
+        ));
+
+        let response = HashMap::from([("soon".to_string(), 1.0)]);
+        assert_eq!(
+            FeeEstimates::from_esplora(&response),
+            Err(FeeEstimationError::InvalidTarget("soon".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_median_fee_estimator() {
+        let estimator = MedianFeeEstimator::new(vec![
+            Box::new(FeeRate::from_sat_per_vb(10.0)),
+            Box::new(FeeRate::from_sat_per_vb(100.0)),
+            Box::new(FeeRate::from_sat_per_vb(12.0)),
+            Box::new(FeeEstimates {
+                estimates: BTreeMap::new(),
+            }),
+        ]);
+        assert_eq!(
+            estimator.estimate_fee_rate(1),
+            Ok(FeeRate::from_sat_per_vb(12.0))
+        );
+
+        let estimator = MedianFeeEstimator::new(vec![]);
+        assert_eq!(
+            estimator.estimate_fee_rate(1),
+            Err(FeeEstimationError::NoEstimates)
+        );
+
+        // invalid estimates are ignored instead of breaking the ordering
+        let max = FeeRate::from_sat_per_vb(f32::MAX);
+        let infinity = max + max;
+        let nan = infinity - infinity;
+        let estimator = MedianFeeEstimator::new(vec![
+            Box::new(nan),
+            Box::new(FeeRate::from_sat_per_vb(10.0)),
+            Box::new(infinity),
+            Box::new(FeeRate::from_sat_per_vb(12.0)),
+            Box::new(FeeRate::from_sat_per_vb(100.0)),
+        ]);
+        assert_eq!(
+            estimator.estimate_fee_rate(1),
+            Ok(FeeRate::from_sat_per_vb(12.0))
+        );
+
+        let negative = FeeRate::from_sat_per_vb(1.0) - FeeRate::from_sat_per_vb(2.0);
+        let estimator = MedianFeeEstimator::new(vec![Box::new(negative)]);
+        assert_eq!(
+            estimator.estimate_fee_rate(1),
+            Err(FeeEstimationError::InvalidEstimate {
+                target: 1,
+                rate: -1.0
+            })
+        );
+    }
+
+    #[test]
+    #[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+    fn test_cached_fee_estimator() {
+        let fetches = core::cell::Cell::new(0);
+        let estimator = CachedFeeEstimator::new(std::time::Duration::from_secs(3600), || {
+            fetches.set(fetches.get() + 1);
+            Ok(HashMap::from([("1".to_string(), 5.0)]))
+        });
+        assert_eq!(estimator.cached(), None);
+        assert_eq!(
+            estimator.estimate_fee_rate(6),
+            Ok(FeeRate::from_sat_per_vb(5.0))
+        );
+        assert_eq!(
+            estimator.estimate_fee_rate(1),
+            Ok(FeeRate::from_sat_per_vb(5.0))
+        );
+        assert_eq!(fetches.get(), 1);
+
+        estimator.invalidate();
+        estimator.estimate_fee_rate(1).unwrap();
+        assert_eq!(fetches.get(), 2);
+    }
+}
@@ -0,0 +1,384 @@
+// Bitcoin Dev Kit
+//
+// Copyright (c) 2020-2021 Bitcoin Dev Kit Developers
+//
+// This file is licensed under the Apache License, Version 2.0 <LICENSE-APACHE
+// or http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your option.
+// You may not use this file except in accordance with one or both of these
+// licenses.
+
+//! Message signing
+//!
+//! This module implements the two common ways of proving the ownership of an address by signing
+//! a message with its key:
+//!
+//! - the legacy `signmessage` format of Bitcoin Core, extended by
+//!   [BIP137](https://github.com/bitcoin/bips/blob/master/bip-0137.mediawiki) to p2wpkh and
+//!   p2sh-p2wpkh addresses;
+//! - the "simple" format of [BIP322](https://github.com/bitcoin/bips/blob/master/bip-0322.mediawiki),
+//!   which works with any segwit address.
+//!
+//! Messages are signed with [`Wallet::sign_message`](crate::Wallet::sign_message) and verified
+//! with [`verify_message`], which doesn't require a wallet.
+//!
+//! ## Example
+//!
+//! ```
+//! # use bdk::wallet::message::{verify_message, MessageSignatureFormat};
+//! # use bdk::wallet::AddressIndex;
+//! # use bdk::Wallet;
+//! # use bitcoin::Network;
+//! let mut wallet = Wallet::new_no_persist(
+//!     "wpkh(tprv8ZgxMBicQKsPd3EupYiPRhaMooHKUHJxNsTfYuScep13go8QFfHdtkG9nRkFGb7busX4isf6X9dURGCoKgitaApQ6MupRhZMcELAxTBRJgS/*)",
+//!     None,
+//!     Network::Testnet,
+//! )?;
+//! let address = wallet.get_address(AddressIndex::New).address;
+//!
+//! let signature =
+//!     wallet.sign_message(&address, "Hello World", MessageSignatureFormat::Bip322Simple)?;
+//! assert!(verify_message(&address, "Hello World", &signature)?);
+//! # Ok::<_, Box<dyn std::error::Error>>(())
+//! ```
+
+use core::fmt;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use bitcoin::base64::engine::general_purpose::STANDARD as BASE64;
+use bitcoin::base64::Engine;
+use bitcoin::blockdata::opcodes::all::OP_RETURN;
+use bitcoin::blockdata::script::Builder;
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+use bitcoin::key::XOnlyPublicKey;
+use bitcoin::secp256k1::ecdsa::{RecoverableSignature, RecoveryId};
+use bitcoin::secp256k1::{Message, Secp256k1};
+use bitcoin::sighash::{Prevouts, SighashCache};
+use bitcoin::sign_message::signed_msg_hash;
+use bitcoin::{
+    absolute, consensus, ecdsa, taproot, Address, Network, OutPoint, PrivateKey, PublicKey,
+    ScriptBuf, Sequence, Transaction, TxIn, TxOut, Txid, Witness,
+};
+
+use super::utils::SecpCtx;
+use crate::signer::SignerError;
+
+const BIP322_TAG: &[u8] = b"BIP0322-signed-message";
+
+/// The format of a message signature
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MessageSignatureFormat {
+    /// The legacy `signmessage` format, only available for p2pkh, p2wpkh and p2sh-p2wpkh
+    /// addresses
+    Legacy,
+    /// The "simple" format of BIP322, only available for segwit addresses
+    Bip322Simple,
+}
+
+/// Verify that `signature` is a valid signature of `message` by the owner of `address`
+///
+/// Both [`MessageSignatureFormat`]s are accepted. BIP322 signatures can only be verified for
+/// p2wpkh and p2tr (key path) addresses, as other scripts would require a full script
+/// interpreter.
+pub fn verify_message(
+    address: &Address,
+    message: &str,
+    signature: &str,
+) -> Result<bool, MessageSigningError> {
+    let signature = BASE64
+        .decode(signature)
+        .map_err(|_| MessageSigningError::InvalidSignature)?;
+    let secp = Secp256k1::verification_only();
+
+    // A legacy signature is a one-byte header followed by a compact signature, which can't be
+    // mistaken for a serialized witness as it would start with a stack of 27 to 42 elements
+    if signature.len() == 65 && (27..=42).contains(&signature[0]) {
+        // headers 27 to 30 are only used by p2pkh addresses of uncompressed keys
+        let compressed = signature[0] >= 31;
+        let recovery_id = RecoveryId::from_i32(((signature[0] - 27) & 0x03) as i32)
+            .map_err(|_| MessageSigningError::InvalidSignature)?;
+        let signature = RecoverableSignature::from_compact(&signature[1..], recovery_id)
+            .map_err(|_| MessageSigningError::InvalidSignature)?;
+        let msg = Message::from_slice(&signed_msg_hash(message)[..]).expect("32 bytes");
+        let pubkey = match secp.recover_ecdsa(&msg, &signature) {
+            Ok(pubkey) => pubkey,
+            Err(_) => return Ok(false),
+        };
+        let pubkey = PublicKey {
+            compressed,
+            inner: pubkey,
+        };
+        return Ok(legacy_scripts(&pubkey)
+            .iter()
+            .any(|script| *script == address.script_pubkey()));
+    }
+
+    let witness: Witness =
+        consensus::deserialize(&signature).map_err(|_| MessageSigningError::InvalidSignature)?;
+    let script_pubkey = address.script_pubkey();
+    let to_spend = bip322_to_spend(&script_pubkey, message);
+    let to_sign = bip322_to_sign(to_spend.txid());
+    let mut sighash_cache = SighashCache::new(&to_sign);
+
+    if script_pubkey.is_v0_p2wpkh() {
+        if witness.len() != 2 {
+            return Ok(false);
+        }
+        let signature = ecdsa::Signature::from_slice(&witness[0])
+            .map_err(|_| MessageSigningError::InvalidSignature)?;
+        let pubkey = PublicKey::from_slice(&witness[1])
+            .map_err(|_| MessageSigningError::InvalidSignature)?;
+        let wpubkey_hash = match pubkey.wpubkey_hash() {
+            Some(hash) if ScriptBuf::new_v0_p2wpkh(&hash) == script_pubkey => hash,
+            _ => return Ok(false),
+        };
+        let sighash = sighash_cache
+            .segwit_signature_hash(
+                0,
+                &ScriptBuf::new_v0_p2wpkh(&wpubkey_hash)
+                    .p2wpkh_script_code()
+                    .expect("it's a p2wpkh script"),
+                0,
+                signature.hash_ty,
+            )
+            .map_err(|_| MessageSigningError::InvalidSignature)?;
+        Ok(secp
+            .verify_ecdsa(&Message::from(sighash), &signature.sig, &pubkey.inner)
+            .is_ok())
+    } else if script_pubkey.is_v1_p2tr() {
+        if witness.len() != 1 {
+            return Ok(false);
+        }
+        let signature = taproot::Signature::from_slice(&witness[0])
+            .map_err(|_| MessageSigningError::InvalidSignature)?;
+        let output_key = XOnlyPublicKey::from_slice(&script_pubkey.as_bytes()[2..])
+            .map_err(|_| MessageSigningError::InvalidSignature)?;
+        let sighash = sighash_cache
+            .taproot_signature_hash(
+                0,
+                &Prevouts::All(&to_spend.output),
+                None,
+                None,
+                signature.hash_ty,
+            )
+            .map_err(|_| MessageSigningError::InvalidSignature)?;
+        Ok(secp
+            .verify_schnorr(&signature.sig, &Message::from(sighash), &output_key)
+            .is_ok())
+    } else {
+        Err(MessageSigningError::UnsupportedAddress)
+    }
+}
+
+/// Sign `message` with `key` in the legacy format, for an address of type `script_pubkey`
+pub(crate) fn sign_legacy(
+    secp: &SecpCtx,
+    key: &PrivateKey,
+    script_pubkey: &ScriptBuf,
+    message: &str,
+) -> Option<String> {
+    let pubkey = key.public_key(secp);
+    // BIP137 header offsets for p2pkh, p2sh-p2wpkh and p2wpkh
+    let header_offset = match legacy_scripts(&pubkey)
+        .iter()
+        .position(|script| script == script_pubkey)?
+    {
+        0 if pubkey.compressed => 4,
+        0 => 0,
+        1 => 8,
+        _ => 12,
+    };
+
+    let msg = Message::from_slice(&signed_msg_hash(message)[..]).expect("32 bytes");
+    let (recovery_id, signature) = secp
+        .sign_ecdsa_recoverable(&msg, &key.inner)
+        .serialize_compact();
+    let mut serialized = Vec::with_capacity(65);
+    serialized.push(27 + header_offset + recovery_id.to_i32() as u8);
+    serialized.extend_from_slice(&signature);
+
+    Some(BASE64.encode(serialized))
+}
+
+/// Serialize the witness of a BIP322 "simple" signature
+pub(crate) fn encode_bip322_witness(witness: &Witness) -> String {
+    BASE64.encode(consensus::serialize(witness))
+}
+
+/// Build the virtual `to_spend` transaction of BIP322
+pub(crate) fn bip322_to_spend(script_pubkey: &ScriptBuf, message: &str) -> Transaction {
+    let tag_hash = sha256::Hash::hash(BIP322_TAG);
+    let mut engine = sha256::Hash::engine();
+    engine.input(&tag_hash[..]);
+    engine.input(&tag_hash[..]);
+    engine.input(message.as_bytes());
+    let message_hash = sha256::Hash::from_engine(engine);
+
+    Transaction {
+        version: 0,
+        lock_time: absolute::LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint::new(Txid::all_zeros(), 0xFFFFFFFF),
+            script_sig: Builder::new()
+                .push_int(0)
+                .push_slice(message_hash.to_byte_array())
+                .into_script(),
+            sequence: Sequence(0),
+            witness: Witness::new(),
+        }],
+        output: vec![TxOut {
+            value: 0,
+            script_pubkey: script_pubkey.clone(),
+        }],
+    }
+}
+
+/// Build the virtual `to_sign` transaction of BIP322, without its witness
+pub(crate) fn bip322_to_sign(to_spend: Txid) -> Transaction {
+    Transaction {
+        version: 0,
+        lock_time: absolute::LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint::new(to_spend, 0),
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence(0),
+            witness: Witness::new(),
+        }],
+        output: vec![TxOut {
+            value: 0,
+            script_pubkey: Builder::new().push_opcode(OP_RETURN).into_script(),
+        }],
+    }
+}
+
+/// The p2pkh, p2sh-p2wpkh and p2wpkh scripts of `pubkey`, in this order
+fn legacy_scripts(pubkey: &PublicKey) -> Vec<ScriptBuf> {
+    let mut scripts = vec![Address::p2pkh(pubkey, Network::Bitcoin).script_pubkey()];
+    if let (Ok(nested), Ok(native)) = (
+        Address::p2shwpkh(pubkey, Network::Bitcoin),
+        Address::p2wpkh(pubkey, Network::Bitcoin),
+    ) {
+        scripts.push(nested.script_pubkey());
+        scripts.push(native.script_pubkey());
+    }
+    scripts
+}
+
+/// Errors returned when signing or verifying a message
+#[derive(Debug)]
+pub enum MessageSigningError {
+    /// The address doesn't belong to the wallet
+    UnknownAddress,
+    /// The address type isn't supported by the signature format
+    UnsupportedAddress,
+    /// The wallet doesn't have the private keys required to sign for the address
+    MissingKey,
+    /// The signature couldn't be decoded
+    InvalidSignature,
+    /// Signing error
+    Signer(SignerError),
+}
+
+impl fmt::Display for MessageSigningError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownAddress => write!(f, "The address doesn't belong to the wallet"),
+            Self::UnsupportedAddress => {
+                write!(
+                    f,
+                    "The address type isn't supported by the signature format"
+                )
+            }
+            Self::MissingKey => write!(f, "Missing private key to sign for the address"),
+            Self::InvalidSignature => write!(f, "Invalid signature encoding"),
+            Self::Signer(err) => write!(f, "Signer error: {}", err),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for MessageSigningError {}
+
+impl From<SignerError> for MessageSigningError {
+    fn from(err: SignerError) -> Self {
+        MessageSigningError::Signer(err)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use core::str::FromStr;
+
+    #[test]
+    fn test_bip322_to_spend() {
+        // Test vectors from BIP322
+        let script_pubkey = Address::from_str("bc1q9vza2e8x573nczrlzms0wvx3gsqjx7vavgkx0l")
+            .unwrap()
+            .assume_checked()
+            .script_pubkey();
+
+        let to_spend = bip322_to_spend(&script_pubkey, "");
+        assert_eq!(
+            to_spend.txid().to_string(),
+            "c5680aa69bb8d860bf82d4e9cd3504b55dde018de765a91bb566283c545a99a7"
+        );
+        assert_eq!(
+            bip322_to_sign(to_spend.txid()).txid().to_string(),
+            "1e9654e951a5ba44c8604c4de6c67fd78a27e81dcadcfe1edf638ba3aaebaed6"
+        );
+
+        let to_spend = bip322_to_spend(&script_pubkey, "Hello World");
+        assert_eq!(
+            to_spend.txid().to_string(),
+            "b79d196740ad5217771c1098fc4a4b51e0535c32236c71f1ea4d61a2d603352b"
+        );
+    }
+
+    #[test]
+    fn test_verify_bip322_simple() {
+        // Test vectors from BIP322
+        let address = Address::from_str("bc1q9vza2e8x573nczrlzms0wvx3gsqjx7vavgkx0l")
+            .unwrap()
+            .assume_checked();
+        let empty = "AkcwRAIgM2gBAQqvZX15ZiysmKmQpDrG83avLIT492QBzLnQIxYCIBaTpOaD20qRlEylyxFSeEA2ba9YOixpX8z46TSDtS40ASECx/EgAxlkQpQ9hYjgGu6EBCPMVPwVIVJqO4XCsMvViHI=";
+        let hello_world = "AkcwRAIgZRfIY3p7/DoVTty6YZbWS71bc5Vct9p9Fia83eRmw2QCICK/ENGfwLtptFluMGs2KsqoNSk89pO7F29zJLUx9a/sASECx/EgAxlkQpQ9hYjgGu6EBCPMVPwVIVJqO4XCsMvViHI=";
+
+        assert!(verify_message(&address, "", empty).unwrap());
+        assert!(verify_message(&address, "Hello World", hello_world).unwrap());
+        assert!(!verify_message(&address, "Hello World", empty).unwrap());
+    }
+
+    #[test]
+    fn test_legacy_signature_roundtrip() {
+        let secp = Secp256k1::new();
+        let key =
+            PrivateKey::from_wif("cVpPVruEDdmutPzisEsYvtST1usBR3ntr8pXSyt6D2YYqXRyPcFW").unwrap();
+        let address = Address::p2wpkh(&key.public_key(&secp), Network::Testnet).unwrap();
+
+        let signature = sign_legacy(&secp, &key, &address.script_pubkey(), "message").unwrap();
+        assert!(verify_message(&address, "message", &signature).unwrap());
+        assert!(!verify_message(&address, "other message", &signature).unwrap());
+
+        let other = Address::p2pkh(&key.public_key(&secp), Network::Testnet);
+        assert!(!verify_message(&other, "message", &signature).unwrap());
+    }
+
+    #[test]
+    fn test_legacy_signature_uncompressed() {
+        let secp = Secp256k1::new();
+        let mut key =
+            PrivateKey::from_wif("cVpPVruEDdmutPzisEsYvtST1usBR3ntr8pXSyt6D2YYqXRyPcFW").unwrap();
+        key.compressed = false;
+        let address = Address::p2pkh(&key.public_key(&secp), Network::Testnet);
+
+        let signature = sign_legacy(&secp, &key, &address.script_pubkey(), "message").unwrap();
+        assert!(verify_message(&address, "message", &signature).unwrap());
+
+        key.compressed = true;
+        let compressed = Address::p2pkh(&key.public_key(&secp), Network::Testnet);
+        assert!(!verify_message(&compressed, "message", &signature).unwrap());
+    }
+}
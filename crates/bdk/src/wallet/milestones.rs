@@ -0,0 +1,141 @@
+// Bitcoin Dev Kit
+//
+// Copyright (c) 2020-2021 Bitcoin Dev Kit Developers
+//
+// This file is licensed under the Apache License, Version 2.0 <LICENSE-APACHE
+// or http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your option.
+// You may not use this file except in accordance with one or both of these
+// licenses.
+
+//! Confirmation milestones
+//!
+//! This module provides [`MilestoneTracker`], which watches the transactions of a [`Wallet`] and
+//! reports when they reach a given number of confirmations. Every milestone is reported only once
+//! per transaction, and the milestones already reported are persisted, so that notification
+//! systems built on top of it don't fire twice, even across restarts.
+//!
+//! ## Example
+//!
+//! ```
+//! # use bdk::wallet::milestones::MilestoneTracker;
+//! # let wallet = bdk::doctest_wallet!();
+//! let mut tracker = MilestoneTracker::new([1, 3, 6], ())?;
+//!
+//! // call this after every sync
+//! for event in tracker.update(&wallet)? {
+//!     println!("{} reached {} confirmations", event.txid, event.milestone);
+//! }
+//! # Ok::<_, core::convert::Infallible>(())
+//! ```
+
+use crate::collections::{BTreeMap, BTreeSet};
+use alloc::vec::Vec;
+
+use bdk_chain::{ChainPosition, Persist, PersistBackend};
+use bitcoin::Txid;
+
+use crate::wallet::Wallet;
+
+/// The changeset produced by a [`MilestoneTracker`].
+///
+/// Maps every transaction to the highest milestone already emitted for it.
+pub type ChangeSet = BTreeMap<Txid, u32>;
+
+/// A confirmation milestone reached by a transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MilestoneEvent {
+    /// The transaction that reached the milestone
+    pub txid: Txid,
+    /// The milestone reached
+    pub milestone: u32,
+    /// The number of confirmations of the transaction at the time of the update
+    pub confirmations: u32,
+    /// The height of the block confirming the transaction
+    pub confirmation_height: u32,
+}
+
+/// Tracks the confirmation milestones of the transactions of a [`Wallet`].
+///
+/// See the [module-level documentation](self) for more.
+#[derive(Debug)]
+pub struct MilestoneTracker<D = ()> {
+    milestones: BTreeSet<u32>,
+    emitted: ChangeSet,
+    persist: Persist<D, ChangeSet>,
+}
+
+impl<D> MilestoneTracker<D>
+where
+    D: PersistBackend<ChangeSet>,
+{
+    /// Create a tracker for the given `milestones`, loading the milestones already emitted
+    /// from `db`.
+    ///
+    /// Milestones of zero confirmations are ignored.
+    pub fn new(milestones: impl IntoIterator<Item = u32>, mut db: D) -> Result<Self, D::LoadError> {
+        let emitted = db.load_from_persistence()?.unwrap_or_default();
+        Ok(Self {
+            milestones: milestones.into_iter().filter(|m| *m > 0).collect(),
+            emitted,
+            persist: Persist::new(db),
+        })
+    }
+
+    /// Returns the milestones being tracked
+    pub fn milestones(&self) -> &BTreeSet<u32> {
+        &self.milestones
+    }
+
+    /// Returns the highest milestone already emitted for `txid`, if any
+    pub fn last_emitted(&self, txid: &Txid) -> Option<u32> {
+        self.emitted.get(txid).copied()
+    }
+
+    /// Check the transactions of `wallet` and return the milestones they reached since the
+    /// previous update.
+    ///
+    /// The new milestones are persisted before being returned, so they won't be emitted again
+    /// by this tracker or by one loaded from the same database. Transactions that go back to
+    /// unconfirmed because of a reorg don't emit their milestones a second time. If persisting
+    /// fails nothing is recorded as emitted, and the next update returns the same milestones.
+    pub fn update<W>(&mut self, wallet: &Wallet<W>) -> Result<Vec<MilestoneEvent>, D::WriteError> {
+        let tip_height = wallet.latest_checkpoint().height();
+        let mut events = Vec::new();
+        let mut changeset = ChangeSet::new();
+
+        for canonical_tx in wallet.transactions() {
+            let confirmation_height = match canonical_tx.chain_position {
+                ChainPosition::Confirmed(anchor) => anchor.confirmation_height,
+                ChainPosition::Unconfirmed(_) => continue,
+            };
+            let confirmations = tip_height.saturating_sub(confirmation_height) + 1;
+            let txid = canonical_tx.tx_node.txid;
+            let last_emitted = self.last_emitted(&txid).unwrap_or(0);
+            if last_emitted >= confirmations {
+                continue;
+            }
+
+            for milestone in self
+                .milestones
+                .range(last_emitted + 1..=confirmations)
+                .copied()
+            {
+                events.push(MilestoneEvent {
+                    txid,
+                    milestone,
+                    confirmations,
+                    confirmation_height,
+                });
+                changeset.insert(txid, milestone);
+            }
+        }
+
+        self.persist.stage(changeset);
+        if let Some(committed) = self.persist.commit()? {
+            self.emitted.extend(committed);
+        }
+
+        Ok(events)
+    }
+}
@@ -12,7 +12,7 @@
 //! Wallet
 //!
 //! This module defines the [`Wallet`] structure.
-use crate::collections::{BTreeMap, HashMap, HashSet};
+use crate::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use alloc::{
     boxed::Box,
     string::{String, ToString},
@@ -28,23 +28,34 @@ use bdk_chain::{
     Append, BlockId, ChainPosition, ConfirmationTime, ConfirmationTimeHeightAnchor, FullTxOut,
     IndexedTxGraph, Persist, PersistBackend,
 };
+use bitcoin::bip32::{ChildNumber, DerivationPath, Fingerprint};
+use bitcoin::policy::MAX_STANDARD_TX_WEIGHT;
 use bitcoin::secp256k1::{All, Secp256k1};
 use bitcoin::sighash::{EcdsaSighashType, TapSighashType};
 use bitcoin::{
-    absolute, Address, Network, OutPoint, Script, ScriptBuf, Sequence, Transaction, TxOut, Txid,
-    Weight, Witness,
+    absolute, Address, AddressType, Network, OutPoint, Script, ScriptBuf, Sequence, Transaction,
+    TxOut, Txid, Weight, Witness,
 };
 use bitcoin::{consensus::encode::serialize, BlockHash};
 use bitcoin::{constants::genesis_block, psbt};
 use core::fmt;
 use core::ops::Deref;
 use descriptor::error::Error as DescriptorError;
+use miniscript::descriptor::{DescriptorSecretKey, DescriptorType, KeyMap, Wildcard};
 use miniscript::psbt::{PsbtExt, PsbtInputExt, PsbtInputSatisfier};
+use miniscript::ForEachKey;
 
 use bdk_chain::tx_graph::CalculateFeeError;
 
+pub mod bip21;
 pub mod coin_selection;
 pub mod export;
+pub mod fee_estimation;
+pub mod message;
+pub mod milestones;
+pub mod multisig;
+pub mod rebroadcast;
+pub mod reserves;
 pub mod signer;
 pub mod tx_builder;
 pub(crate) mod utils;
@@ -58,11 +69,13 @@ pub use utils::IsDust;
 
 #[allow(deprecated)]
 use coin_selection::DefaultCoinSelectionAlgorithm;
+use message::{MessageSignatureFormat, MessageSigningError};
+use reserves::ProofError;
 use signer::{SignOptions, SignerOrdering, SignersContainer, TransactionSigner};
 use tx_builder::{BumpFee, CreateTx, FeePolicy, TxBuilder, TxParams};
 use utils::{check_nsequence_rbf, After, Older, SecpCtx};
 
-use crate::descriptor::policy::BuildSatisfaction;
+use crate::descriptor::policy::{BuildSatisfaction, Satisfaction};
 use crate::descriptor::{
     self, calc_checksum, into_wallet_descriptor_checked, DerivedDescriptor, DescriptorMeta,
     ExtendedDescriptor, ExtractPolicy, IntoWalletDescriptor, Policy, XKeyUtils,
@@ -71,7 +84,9 @@ use crate::psbt::PsbtUtils;
 use crate::signer::SignerError;
 use crate::types::*;
 use crate::wallet::coin_selection::Excess::{Change, NoChange};
-use crate::wallet::error::{BuildFeeBumpError, CreateTxError, MiniscriptPsbtError};
+use crate::wallet::error::{
+    BuildCpfpError, BuildFeeBumpError, CombinePsbtError, CreateTxError, MiniscriptPsbtError,
+};
 
 const COINBASE_MATURITY: u32 = 100;
 
@@ -93,6 +108,7 @@ pub struct Wallet<D = ()> {
     persist: Persist<D, ChangeSet>,
     network: Network,
     secp: SecpCtx,
+    gap_limit: Option<u32>,
 }
 
 /// An update to [`Wallet`].
@@ -131,6 +147,14 @@ pub struct ChangeSet {
 
     /// Stores the network type of the wallet.
     pub network: Option<Network>,
+
+    /// Stores the checksum of the descriptor of each keychain.
+    #[serde(default)]
+    pub descriptor_checksums: BTreeMap<KeychainKind, String>,
+
+    /// Stores the gap limit set with [`Wallet::set_gap_limit`].
+    #[serde(default)]
+    pub gap_limit: Option<u32>,
 }
 
 impl Append for ChangeSet {
@@ -144,6 +168,53 @@ impl Append for ChangeSet {
             );
             self.network = other.network;
         }
+        // a different checksum is caught by `Wallet::load`, as it won't match the descriptor
+        self.descriptor_checksums.extend(other.descriptor_checksums);
+        if other.gap_limit.is_some() {
+            self.gap_limit = other.gap_limit;
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.chain.is_empty()
+            && self.indexed_tx_graph.is_empty()
+            && self.descriptor_checksums.is_empty()
+            && self.gap_limit.is_none()
+    }
+}
+
+/// The [`ChangeSet`] persisted by wallets before the descriptor checksums and the gap limit were
+/// stored.
+///
+/// Self-describing formats read it as a [`ChangeSet`] directly, but the bincode entries of
+/// `bdk_file_store` can't be: open such a store as a store of `LegacyChangeSet` and migrate it
+/// to a new one with `Store::migrate_from`.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, Default)]
+pub struct LegacyChangeSet {
+    /// Changes to the [`LocalChain`].
+    ///
+    /// [`LocalChain`]: local_chain::LocalChain
+    pub chain: local_chain::ChangeSet,
+
+    /// Changes to [`IndexedTxGraph`].
+    ///
+    /// [`IndexedTxGraph`]: bdk_chain::indexed_tx_graph::IndexedTxGraph
+    pub indexed_tx_graph: indexed_tx_graph::ChangeSet<
+        ConfirmationTimeHeightAnchor,
+        keychain::ChangeSet<KeychainKind>,
+    >,
+
+    /// Stores the network type of the wallet.
+    pub network: Option<Network>,
+}
+
+impl Append for LegacyChangeSet {
+    fn append(&mut self, other: Self) {
+        Append::append(&mut self.chain, other.chain);
+        Append::append(&mut self.indexed_tx_graph, other.indexed_tx_graph);
+        if other.network.is_some() {
+            self.network = other.network;
+        }
     }
 
     fn is_empty(&self) -> bool {
@@ -151,6 +222,17 @@ impl Append for ChangeSet {
     }
 }
 
+impl From<LegacyChangeSet> for ChangeSet {
+    fn from(legacy: LegacyChangeSet) -> Self {
+        Self {
+            chain: legacy.chain,
+            indexed_tx_graph: legacy.indexed_tx_graph,
+            network: legacy.network,
+            ..Default::default()
+        }
+    }
+}
+
 impl From<local_chain::ChangeSet> for ChangeSet {
     fn from(chain: local_chain::ChangeSet) -> Self {
         Self {
@@ -181,12 +263,53 @@ impl
     }
 }
 
+/// Who an output of a PSBT pays, as returned by [`Wallet::classify_psbt`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputOwnership {
+    /// The output pays a script that doesn't belong to the wallet
+    External,
+    /// The output pays an address of the wallet's external keychain
+    Receive {
+        /// Derivation index of the address
+        index: u32,
+    },
+    /// The output pays an address of the wallet's internal (change) keychain
+    Change {
+        /// Derivation index of the address
+        index: u32,
+    },
+}
+
+/// The changes that applying an [`Update`] would make to a [`Wallet`], as computed by
+/// [`Wallet::preview_update`].
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct UpdatePreview {
+    /// Transactions that would be added to the wallet
+    pub new_txs: Vec<Txid>,
+    /// Outputs that would become unspent outputs of the wallet
+    pub new_utxos: Vec<OutPoint>,
+    /// Unspent outputs of the wallet that would be spent or dropped from the best chain
+    pub removed_utxos: Vec<OutPoint>,
+    /// The keychains whose last revealed derivation index would advance, with the new index
+    pub index_advances: BTreeMap<KeychainKind, u32>,
+    /// The changes that [`Wallet::apply_update`] would stage
+    pub changeset: ChangeSet,
+}
+
 /// The address index selection strategy to use to derived an address from the wallet's external
 /// descriptor. See [`Wallet::get_address`]. If you're unsure which one to use use `WalletIndex::New`.
 #[derive(Debug)]
 pub enum AddressIndex {
     /// Return a new address after incrementing the current descriptor index.
     New,
+    /// Return a new address as with [`AddressIndex::New`], unless the gap limit set with
+    /// [`Wallet::set_gap_limit`] would be exceeded.
+    ///
+    /// Once there are as many unused addresses after the last used one as the gap limit, the
+    /// first of them is returned again instead of revealing a new one, so that a scan with the gap
+    /// limit as `stop_gap` finds every address given out. The returned address may then have
+    /// already been given out.
+    NewWithinGapLimit,
     /// Return the address for the current descriptor index if it has not been used in a received
     /// transaction. Otherwise return a new address as with [`AddressIndex::New`].
     ///
@@ -229,6 +352,110 @@ impl fmt::Display for AddressInfo {
     }
 }
 
+/// Usage details of a script pubkey revealed by the wallet, see [`Wallet::get_address_details`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddressDetails {
+    /// Child index of this address
+    pub index: u32,
+    /// Type of keychain
+    pub keychain: KeychainKind,
+    /// Number of transactions in the best chain paying to this address
+    pub tx_count: usize,
+    /// Total amount received by this address, in satoshis
+    pub total_received: u64,
+    /// Height of the first confirmed transaction paying to this address
+    pub first_used_height: Option<u32>,
+    /// Height of the last confirmed transaction paying to this address
+    pub last_used_height: Option<u32>,
+}
+
+/// The derivation of an address checked with [`Wallet::verify_address`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddressOrigin {
+    /// Type of keychain
+    pub keychain: KeychainKind,
+    /// Child index of this address
+    pub index: u32,
+    /// Full derivation path of every key in the script, by master fingerprint
+    pub derivation_paths: BTreeMap<Fingerprint, DerivationPath>,
+}
+
+/// An address with the data needed to verify it on a hardware device, returned by
+/// [`Wallet::get_address_with_proof`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddressProof {
+    /// The address
+    pub address: Address,
+    /// Type of keychain
+    pub keychain: KeychainKind,
+    /// Child index of this address
+    pub index: u32,
+    /// Every key in the script, in the order of the descriptor, with its master fingerprint and
+    /// full derivation path
+    pub key_origins: Vec<(bitcoin::PublicKey, bitcoin::bip32::KeySource)>,
+    /// Every key in the script, sorted as in a `sortedmulti` script (BIP67)
+    pub sorted_pubkeys: Vec<bitcoin::PublicKey>,
+}
+
+/// Direction of a transaction, relative to the wallet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TxDirection {
+    /// The wallet receives more than it sends
+    Incoming,
+    /// The wallet sends at least as much as it receives
+    Outgoing,
+}
+
+/// Why a transaction known by the wallet is not part of the best chain, see
+/// [`Wallet::get_conflict_status`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictStatus {
+    /// The transaction was replaced (or double-spent) by this transaction of the best chain
+    Replaced(Txid),
+    /// The transaction conflicts with another one, or spends from a transaction that was
+    /// replaced
+    Conflicting,
+}
+
+/// Order of the transactions returned by [`Wallet::list_transactions`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TxSortOrder {
+    /// Unconfirmed transactions first, then confirmed ones from the highest block
+    NewestFirst,
+    /// Confirmed transactions from the lowest block first, then unconfirmed ones
+    OldestFirst,
+}
+
+impl Default for TxSortOrder {
+    fn default() -> Self {
+        TxSortOrder::NewestFirst
+    }
+}
+
+/// Filters, ordering and pagination of [`Wallet::list_transactions`]
+///
+/// The default value returns every transaction, newest first.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TxFilter {
+    /// Only return confirmed transactions
+    pub confirmed_only: bool,
+    /// Only return transactions confirmed in this range of heights
+    ///
+    /// Unconfirmed transactions are excluded when set.
+    pub height_range: Option<core::ops::RangeInclusive<u32>>,
+    /// Only return transactions moving at least this amount in or out of the wallet, in
+    /// satoshis
+    pub min_amount: Option<u64>,
+    /// Only return transactions in this direction
+    pub direction: Option<TxDirection>,
+    /// Order of the transactions
+    pub ordering: TxSortOrder,
+    /// Number of matching transactions to skip
+    pub offset: usize,
+    /// Maximum number of transactions to return
+    pub limit: Option<usize>,
+}
+
 impl Wallet {
     /// Creates a wallet that does not persist data.
     pub fn new_no_persist<E: IntoWalletDescriptor>(
@@ -319,6 +546,9 @@ pub enum LoadError<L> {
     /// There was a problem with the passed-in descriptor(s).
     Descriptor(crate::descriptor::DescriptorError),
     /// Loading data from the persistence backend failed.
+    ///
+    /// This is also the error returned for `bdk_file_store` stores written by older versions of
+    /// the wallet, which must be migrated with [`LegacyChangeSet`].
     Load(L),
     /// Wallet not initialized, persistence backend is empty.
     NotInitialized,
@@ -326,6 +556,15 @@ pub enum LoadError<L> {
     MissingNetwork,
     /// Data loaded from persistence is missing genesis hash.
     MissingGenesis,
+    /// The descriptor of a keychain doesn't match the one the persisted data was created with.
+    DescriptorMismatch {
+        /// The keychain whose descriptor doesn't match
+        keychain: KeychainKind,
+        /// The checksum of the provided descriptor, if any
+        expected: Option<String>,
+        /// The checksum loaded from persistence
+        got: Option<String>,
+    },
 }
 
 impl<L> fmt::Display for LoadError<L>
@@ -335,12 +574,25 @@ where
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             LoadError::Descriptor(e) => e.fmt(f),
-            LoadError::Load(e) => e.fmt(f),
+            LoadError::Load(e) => write!(
+                f,
+                "failed to load the wallet data: {} (data written by older versions of the wallet must be migrated, see `LegacyChangeSet`)",
+                e
+            ),
             LoadError::NotInitialized => {
                 write!(f, "wallet is not initialized, persistence backend is empty")
             }
             LoadError::MissingNetwork => write!(f, "loaded data is missing network type"),
             LoadError::MissingGenesis => write!(f, "loaded data is missing genesis hash"),
+            LoadError::DescriptorMismatch {
+                keychain,
+                expected,
+                got,
+            } => write!(
+                f,
+                "loaded {:?} descriptor checksum is not {:?}, got {:?}",
+                keychain, expected, got
+            ),
         }
     }
 }
@@ -378,6 +630,15 @@ pub enum NewOrLoadError<W, L> {
         /// The network type loaded from persistence.
         got: Option<Network>,
     },
+    /// The descriptor of a keychain doesn't match the one the persisted data was created with.
+    LoadedDescriptorDoesNotMatch {
+        /// The keychain whose descriptor doesn't match
+        keychain: KeychainKind,
+        /// The checksum of the provided descriptor, if any
+        expected: Option<String>,
+        /// The checksum loaded from persistence
+        got: Option<String>,
+    },
 }
 
 impl<W, L> fmt::Display for NewOrLoadError<W, L>
@@ -399,6 +660,15 @@ where
             NewOrLoadError::LoadedNetworkDoesNotMatch { expected, got } => {
                 write!(f, "loaded network type is not {}, got {:?}", expected, got)
             }
+            NewOrLoadError::LoadedDescriptorDoesNotMatch {
+                keychain,
+                expected,
+                got,
+            } => write!(
+                f,
+                "loaded {:?} descriptor checksum is not {:?}, got {:?}",
+                keychain, expected, got
+            ),
         }
     }
 }
@@ -462,12 +732,15 @@ impl<D> Wallet<D> {
                 .map_err(NewError::Descriptor)?;
 
         let indexed_graph = IndexedTxGraph::new(index);
+        let descriptor_checksums = descriptor_checksums(indexed_graph.index.keychains());
 
         let mut persist = Persist::new(db);
         persist.stage(ChangeSet {
             chain: chain_changeset,
             indexed_tx_graph: indexed_graph.initial_changeset(),
             network: Some(network),
+            descriptor_checksums,
+            gap_limit: None,
         });
         persist.commit().map_err(NewError::Write)?;
 
@@ -479,10 +752,14 @@ impl<D> Wallet<D> {
             indexed_graph,
             persist,
             secp,
+            gap_limit: None,
         })
     }
 
     /// Load [`Wallet`] from the given persistence backend.
+    ///
+    /// `bdk_file_store` stores written by older versions of the wallet must be migrated first,
+    /// see [`LegacyChangeSet`].
     pub fn load<E: IntoWalletDescriptor>(
         descriptor: E,
         change_descriptor: Option<E>,
@@ -517,8 +794,32 @@ impl<D> Wallet<D> {
             create_signers(&mut index, &secp, descriptor, change_descriptor, network)
                 .map_err(LoadError::Descriptor)?;
 
+        // Data persisted before the checksums were stored doesn't have them: store them now
+        let descriptor_checksums = descriptor_checksums(index.keychains());
+        let mut persist = Persist::new(db);
+        if changeset.descriptor_checksums.is_empty() {
+            persist.stage(ChangeSet {
+                descriptor_checksums,
+                ..Default::default()
+            });
+        } else {
+            for keychain in [KeychainKind::External, KeychainKind::Internal] {
+                let expected = descriptor_checksums.get(&keychain);
+                let got = changeset.descriptor_checksums.get(&keychain);
+                if expected != got {
+                    return Err(LoadError::DescriptorMismatch {
+                        keychain,
+                        expected: expected.cloned(),
+                        got: got.cloned(),
+                    });
+                }
+            }
+        }
+
+        if let Some(gap_limit) = changeset.gap_limit {
+            index.set_lookahead_for_all(gap_limit);
+        }
         let indexed_graph = IndexedTxGraph::new(index);
-        let persist = Persist::new(db);
 
         Ok(Wallet {
             signers,
@@ -528,6 +829,7 @@ impl<D> Wallet<D> {
             persist,
             network,
             secp,
+            gap_limit: changeset.gap_limit,
         })
     }
 
@@ -590,6 +892,15 @@ impl<D> Wallet<D> {
                                     got: None,
                                 }
                             }
+                            LoadError::DescriptorMismatch {
+                                keychain,
+                                expected,
+                                got,
+                            } => NewOrLoadError::LoadedDescriptorDoesNotMatch {
+                                keychain,
+                                expected,
+                                got,
+                            },
                         })?;
                 if wallet.network != network {
                     return Err(NewOrLoadError::LoadedNetworkDoesNotMatch {
@@ -686,12 +997,27 @@ impl<D> Wallet<D> {
         D: PersistBackend<ChangeSet>,
     {
         let keychain = self.map_keychain(keychain);
+        let gap_limit = self.gap_limit;
         let txout_index = &mut self.indexed_graph.index;
         let (index, spk, changeset) = match address_index {
             AddressIndex::New => {
                 let ((index, spk), index_changeset) = txout_index.reveal_next_spk(&keychain);
                 (index, spk.into(), Some(index_changeset))
             }
+            AddressIndex::NewWithinGapLimit => {
+                let last_used = txout_index.last_used_index(&keychain);
+                let gap = txout_index
+                    .unused_spks_of_keychain(&keychain)
+                    .filter(|(index, _)| last_used.map_or(true, |last_used| *index > last_used))
+                    .count();
+                let ((index, spk), index_changeset) = match gap_limit {
+                    Some(gap_limit) if gap >= gap_limit as usize => {
+                        txout_index.next_unused_spk(&keychain)
+                    }
+                    _ => txout_index.reveal_next_spk(&keychain),
+                };
+                (index, spk.into(), Some(index_changeset))
+            }
             AddressIndex::LastUnused => {
                 let ((index, spk), index_changeset) = txout_index.next_unused_spk(&keychain);
                 (index, spk.into(), Some(index_changeset))
@@ -722,6 +1048,154 @@ impl<D> Wallet<D> {
         })
     }
 
+    /// Reveal the next `n` external addresses and return them, persisting the new derivation
+    /// index with a single commit.
+    ///
+    /// Like [`AddressIndex::New`], and unlike [`AddressIndex::NewWithinGapLimit`], unused
+    /// addresses are never handed out again, and the gap limit is ignored. As the wallet is
+    /// borrowed mutably, concurrent callers (e.g. sharing the wallet through a `Mutex`) always
+    /// receive distinct addresses. Fewer than `n` addresses are returned if the descriptor can't
+    /// derive that many new ones, e.g. if it doesn't have a wildcard.
+    pub fn reserve_addresses(&mut self, n: u32) -> Result<Vec<AddressInfo>, D::WriteError>
+    where
+        D: PersistBackend<ChangeSet>,
+    {
+        let keychain = KeychainKind::External;
+        let txout_index = &mut self.indexed_graph.index;
+        let (first, is_new) = txout_index.next_index(&keychain);
+        if n == 0 || !is_new {
+            return Ok(Vec::new());
+        }
+        let (_, changeset) = txout_index.reveal_to_target(&keychain, first.saturating_add(n - 1));
+        let last = txout_index
+            .last_revealed_index(&keychain)
+            .expect("addresses were just revealed");
+
+        self.persist
+            .stage(ChangeSet::from(indexed_tx_graph::ChangeSet::from(
+                changeset,
+            )));
+        self.persist.commit()?;
+
+        Ok((first..=last)
+            .map(|index| {
+                let spk = self
+                    .indexed_graph
+                    .index
+                    .inner()
+                    .spk_at_index(&(keychain, index))
+                    .expect("address was just revealed");
+                AddressInfo {
+                    index,
+                    address: Address::from_script(spk, self.network)
+                        .expect("descriptor must have address form"),
+                    keychain,
+                }
+            })
+            .collect())
+    }
+
+    /// Return the types of the addresses the wallet can receive to.
+    ///
+    /// The wallet has a single external descriptor, so this contains at most the type of its
+    /// addresses. It's empty if the descriptor doesn't have an address form (e.g. a bare
+    /// multisig).
+    pub fn supported_address_types(&self) -> Vec<AddressType> {
+        self.indexed_graph
+            .index
+            .spks_of_keychain(&KeychainKind::External)
+            .next()
+            .and_then(|(_, spk)| Address::from_script(&spk, self.network).ok())
+            .and_then(|address| address.address_type())
+            .into_iter()
+            .collect()
+    }
+
+    /// Return a new external address of type `address_type`, revealed as with
+    /// [`AddressIndex::New`].
+    ///
+    /// Returns `Ok(None)` without revealing any address if the wallet can't receive to
+    /// `address_type`, see [`Wallet::supported_address_types`].
+    pub fn get_new_address_of_type(
+        &mut self,
+        address_type: AddressType,
+    ) -> Result<Option<AddressInfo>, D::WriteError>
+    where
+        D: PersistBackend<ChangeSet>,
+    {
+        if !self.supported_address_types().contains(&address_type) {
+            return Ok(None);
+        }
+        self._get_address(KeychainKind::External, AddressIndex::New)
+            .map(Some)
+    }
+
+    /// Return a BIP21 payment URI for a new address of the wallet, requesting `amount` satoshis
+    /// if given.
+    ///
+    /// The address is revealed as with [`AddressIndex::New`]. A
+    /// `PersistBackend<ChangeSet>::WriteError` will result if unable to persist the new address
+    /// to the `PersistBackend`.
+    pub fn get_new_address_uri(
+        &mut self,
+        amount: Option<u64>,
+        label: Option<&str>,
+    ) -> Result<bip21::Uri, D::WriteError>
+    where
+        D: PersistBackend<ChangeSet>,
+    {
+        let address_info = self.try_get_address(AddressIndex::New)?;
+        let mut uri = bip21::Uri::new(address_info.address);
+        uri.amount = amount;
+        uri.label = label.map(ToString::to_string);
+        Ok(uri)
+    }
+
+    /// List the addresses of `keychain` that have been revealed but haven't been used yet, from
+    /// the lowest derivation index.
+    ///
+    /// An address is used once a transaction paying it is indexed, or while it's reserved as the
+    /// change address of a transaction created by the wallet.
+    pub fn list_unused_addresses(
+        &self,
+        keychain: KeychainKind,
+    ) -> impl Iterator<Item = AddressInfo> + '_ {
+        let keychain = self.map_keychain(keychain);
+        self.indexed_graph
+            .index
+            .unused_spks_of_keychain(&keychain)
+            .map(move |(index, spk)| AddressInfo {
+                index,
+                address: Address::from_script(spk, self.network)
+                    .expect("descriptor must have address form"),
+                keychain,
+            })
+    }
+
+    /// Set the gap limit of the wallet.
+    ///
+    /// The wallet stores `gap_limit` scripts past the last revealed one for every keychain, so
+    /// that the transactions paying them are found when applying updates (see
+    /// [`KeychainTxOutIndex::set_lookahead`]), and [`AddressIndex::NewWithinGapLimit`] won't
+    /// reveal more than `gap_limit` consecutive unused addresses. When scanning [`Wallet::spks_of_all_keychains`]
+    /// with a chain source, the same value should be used as `stop_gap` so that a recovery
+    /// doesn't stop before the last address given out.
+    ///
+    /// The gap limit is staged, and restored by [`Wallet::load`] once committed.
+    pub fn set_gap_limit(&mut self, gap_limit: u32) {
+        self.indexed_graph.index.set_lookahead_for_all(gap_limit);
+        self.gap_limit = Some(gap_limit);
+        self.persist.stage(ChangeSet {
+            gap_limit: Some(gap_limit),
+            ..Default::default()
+        });
+    }
+
+    /// Return the gap limit set with [`Wallet::set_gap_limit`], if any
+    pub fn gap_limit(&self) -> Option<u32> {
+        self.gap_limit
+    }
+
     /// Return whether or not a `script` is part of this wallet (either internal or external)
     pub fn is_mine(&self, script: &Script) -> bool {
         self.indexed_graph.index.index_of_spk(script).is_some()
@@ -734,6 +1208,168 @@ impl<D> Wallet<D> {
         self.indexed_graph.index.index_of_spk(spk).copied()
     }
 
+    /// Classify every output of `psbt`, in order, by whether it pays the wallet and from which
+    /// keychain.
+    ///
+    /// This also works for PSBTs created elsewhere: outputs paying a script the wallet hasn't
+    /// revealed yet are recognized through their key origins (`bip32_derivation` or
+    /// `tap_key_origins`), if the PSBT has them.
+    pub fn classify_psbt(&self, psbt: &psbt::PartiallySignedTransaction) -> Vec<OutputOwnership> {
+        psbt.unsigned_tx
+            .output
+            .iter()
+            .enumerate()
+            .map(|(n, txout)| {
+                let derivation = self.derivation_of_spk(&txout.script_pubkey).or_else(|| {
+                    psbt.outputs
+                        .get(n)
+                        .and_then(|output| self.derivation_from_key_origins(output, txout))
+                });
+                match derivation {
+                    Some((KeychainKind::External, index)) => OutputOwnership::Receive { index },
+                    Some((KeychainKind::Internal, index)) => OutputOwnership::Change { index },
+                    None => OutputOwnership::External,
+                }
+            })
+            .collect()
+    }
+
+    /// Look for a derivation index in the key origins of a PSBT output that derives its script
+    /// pubkey from one of the wallet's descriptors
+    fn derivation_from_key_origins(
+        &self,
+        output: &psbt::Output,
+        txout: &TxOut,
+    ) -> Option<(KeychainKind, u32)> {
+        self.indexed_graph
+            .index
+            .keychains()
+            .iter()
+            .find_map(|(keychain, descriptor)| {
+                utils::derivation_from_key_origins(descriptor, output, &txout.script_pubkey)
+                    .map(|index| (*keychain, index))
+            })
+    }
+
+    /// Returns the derivation and usage details of `script`, computed from the outputs the wallet
+    /// has indexed for it.
+    ///
+    /// Returns `None` if `script` is not part of this wallet.
+    pub fn get_address_details(&self, script: &Script) -> Option<AddressDetails> {
+        let spk_i = *self.indexed_graph.index.index_of_spk(script)?;
+        let (keychain, index) = spk_i;
+        let outpoints = self
+            .indexed_graph
+            .index
+            .inner()
+            .outputs_in_range(spk_i..=spk_i)
+            .map(|(&spk_i, op)| (spk_i, op));
+
+        let mut txids = HashSet::new();
+        let mut details = AddressDetails {
+            index,
+            keychain,
+            tx_count: 0,
+            total_received: 0,
+            first_used_height: None,
+            last_used_height: None,
+        };
+        for (_, full_txo) in self.indexed_graph.graph().filter_chain_txouts(
+            &self.chain,
+            self.chain.tip().block_id(),
+            outpoints,
+        ) {
+            txids.insert(full_txo.outpoint.txid);
+            details.total_received += full_txo.txout.value;
+            if let ConfirmationTime::Confirmed { height, .. } =
+                ConfirmationTime::from(full_txo.chain_position)
+            {
+                details.first_used_height =
+                    Some(details.first_used_height.map_or(height, |h| h.min(height)));
+                details.last_used_height =
+                    Some(details.last_used_height.map_or(height, |h| h.max(height)));
+            }
+        }
+        details.tx_count = txids.len();
+
+        Some(details)
+    }
+
+    /// Return the external address at `index`, without revealing it, with the origin of every
+    /// key in its script.
+    ///
+    /// Hardware devices like Coldcard or BitBox derive the address again from the key origins
+    /// (and from the registered multisig setup, if any) before showing it, so that the user can
+    /// check that it wasn't replaced. Returns `None` if `index` is hardened or if the
+    /// descriptor doesn't have an address form.
+    pub fn get_address_with_proof(&self, index: u32) -> Option<AddressProof> {
+        let keychain = KeychainKind::External;
+        let derived = self
+            .get_descriptor_for_keychain(keychain)
+            .at_derivation_index(index)
+            .ok()?;
+        let address = derived.address(self.network).ok()?;
+
+        let mut key_origins = Vec::new();
+        derived.for_each_key(|key| {
+            if let Ok(pk) = key.derive_public_key(&self.secp) {
+                let path = key.full_derivation_path().unwrap_or_default();
+                key_origins.push((pk, (key.master_fingerprint(), path)));
+            }
+            true
+        });
+        let mut sorted_pubkeys = key_origins.iter().map(|(pk, _)| *pk).collect::<Vec<_>>();
+        sorted_pubkeys.sort_by_key(|pk| pk.to_bytes());
+
+        Some(AddressProof {
+            address,
+            keychain,
+            index,
+            key_origins,
+            sorted_pubkeys,
+        })
+    }
+
+    /// Check that `address` derives from one of the wallet's descriptors at an index in
+    /// `index_range`, and return its derivation.
+    ///
+    /// The scripts are derived again from the descriptors rather than looked up in the wallet's
+    /// index, so this can be used to confirm that an address displayed by another application or
+    /// device really belongs to the wallet before sharing it. Returns `None` if no index in the
+    /// range matches.
+    pub fn verify_address(
+        &self,
+        address: &Address,
+        index_range: core::ops::Range<u32>,
+    ) -> Option<AddressOrigin> {
+        let script_pubkey = address.script_pubkey();
+        self.indexed_graph
+            .index
+            .keychains()
+            .iter()
+            .find_map(|(keychain, descriptor)| {
+                index_range.clone().find_map(|index| {
+                    let derived = descriptor.at_derivation_index(index).ok()?;
+                    if derived.script_pubkey() != script_pubkey {
+                        return None;
+                    }
+
+                    let mut derivation_paths = BTreeMap::new();
+                    derived.for_each_key(|key| {
+                        if let Some(path) = key.full_derivation_path() {
+                            derivation_paths.insert(key.master_fingerprint(), path);
+                        }
+                        true
+                    });
+                    Some(AddressOrigin {
+                        keychain: *keychain,
+                        index,
+                        derivation_paths,
+                    })
+                })
+            })
+    }
+
     /// Return the list of unspent outputs of this wallet
     pub fn list_unspent(&self) -> impl Iterator<Item = LocalOutput> + '_ {
         self.indexed_graph
@@ -746,6 +1382,49 @@ impl<D> Wallet<D> {
             .map(|((k, i), full_txo)| new_local_utxo(k, i, full_txo))
     }
 
+    /// Return the unspent outputs of `keychain` that the spending path `policy_path` could spend
+    /// in a transaction confirmed after the block at `at_height`.
+    ///
+    /// The path has the same format as the one passed to [`TxBuilder::policy_path`]. An output is
+    /// considered spendable when the wallet holds keys that contribute to the keychain's policy,
+    /// and the timelocks required by the path are met at `at_height`. Time-based timelocks are
+    /// never considered met. This is useful for vault-like wallets where different branches of
+    /// the policy unlock at different times.
+    pub fn list_spendable(
+        &self,
+        keychain: KeychainKind,
+        policy_path: &BTreeMap<String, Vec<usize>>,
+        at_height: u32,
+    ) -> Result<Vec<LocalOutput>, DescriptorError> {
+        let keychain = self.map_keychain(keychain);
+        let policy = match self.policies(keychain)? {
+            Some(policy) => policy,
+            None => return Ok(vec![]),
+        };
+        if policy.contribution == Satisfaction::None {
+            return Ok(vec![]);
+        }
+        let condition = policy.get_condition(policy_path)?;
+
+        if let Some(timelock) = condition.timelock {
+            if at_height < timelock.to_consensus_u32() {
+                return Ok(vec![]);
+            }
+        }
+
+        Ok(self
+            .list_unspent()
+            .filter(|utxo| utxo.keychain == keychain)
+            .filter(|utxo| match (condition.csv, utxo.confirmation_time) {
+                (None, _) => true,
+                (Some(csv), ConfirmationTime::Confirmed { height, .. }) => {
+                    at_height >= height.saturating_add(csv.to_consensus_u32())
+                }
+                (Some(_), ConfirmationTime::Unconfirmed { .. }) => false,
+            })
+            .collect())
+    }
+
     /// List all relevant outputs (includes both spent and unspent, confirmed and unconfirmed).
     ///
     /// To list only unspent outputs (UTXOs), use [`Wallet::list_unspent`] instead.
@@ -1084,6 +1763,203 @@ impl<D> Wallet<D> {
             .list_chain_txs(&self.chain, self.chain.tip().block_id())
     }
 
+    /// List the transactions of the wallet matching `filter`.
+    ///
+    /// The filters are applied before the pagination, so `offset` and `limit` count matching
+    /// transactions only.
+    ///
+    /// ```rust, no_run
+    /// # use bdk::wallet::{TxDirection, TxFilter};
+    /// # use bdk::Wallet;
+    /// # let wallet: Wallet<()> = todo!();
+    /// // second page of the incoming transactions, 10 per page
+    /// let page = wallet.list_transactions(&TxFilter {
+    ///     direction: Some(TxDirection::Incoming),
+    ///     offset: 10,
+    ///     limit: Some(10),
+    ///     ..Default::default()
+    /// });
+    /// ```
+    pub fn list_transactions(
+        &self,
+        filter: &TxFilter,
+    ) -> Vec<CanonicalTx<'_, Transaction, ConfirmationTimeHeightAnchor>> {
+        let mut txs = self
+            .transactions()
+            .filter(|canonical_tx| {
+                let confirmation_height = match &canonical_tx.chain_position {
+                    ChainPosition::Confirmed(anchor) => Some(anchor.confirmation_height),
+                    ChainPosition::Unconfirmed(_) => None,
+                };
+                if (filter.confirmed_only || filter.height_range.is_some())
+                    && confirmation_height.is_none()
+                {
+                    return false;
+                }
+                if let (Some(range), Some(height)) = (&filter.height_range, confirmation_height) {
+                    if !range.contains(&height) {
+                        return false;
+                    }
+                }
+
+                if filter.min_amount.is_none() && filter.direction.is_none() {
+                    return true;
+                }
+                let (sent, received) = self.sent_and_received(&canonical_tx.tx_node.tx);
+                let (direction, amount) = if received > sent {
+                    (TxDirection::Incoming, received - sent)
+                } else {
+                    (TxDirection::Outgoing, sent - received)
+                };
+                filter.min_amount.map_or(true, |min| amount >= min)
+                    && filter.direction.map_or(true, |d| d == direction)
+            })
+            .collect::<Vec<_>>();
+
+        txs.sort_by_key(|canonical_tx| {
+            let position = match &canonical_tx.chain_position {
+                ChainPosition::Confirmed(anchor) => (
+                    0,
+                    anchor.confirmation_height as u64,
+                    anchor.confirmation_time,
+                ),
+                ChainPosition::Unconfirmed(last_seen) => (1, *last_seen, 0),
+            };
+            (position, canonical_tx.tx_node.txid)
+        });
+        if filter.ordering == TxSortOrder::NewestFirst {
+            txs.reverse();
+        }
+
+        txs.into_iter()
+            .skip(filter.offset)
+            .take(filter.limit.unwrap_or(usize::MAX))
+            .collect()
+    }
+
+    /// Return whether the transaction `txid` was replaced or double-spent.
+    ///
+    /// Returns `None` if the transaction is unknown, part of the best chain, or if it simply
+    /// isn't in the best chain anymore without conflicting with any known transaction (e.g. its
+    /// block was reorganized away). The outputs of conflicted transactions are never returned
+    /// by [`Wallet::list_unspent`] and can't be selected when building transactions.
+    pub fn get_conflict_status(&self, txid: Txid) -> Option<ConflictStatus> {
+        let graph = self.indexed_graph.graph();
+        let chain_tip = self.chain.tip().block_id();
+        let tx = graph.get_tx(txid)?;
+        if graph
+            .get_chain_position(&self.chain, chain_tip, txid)
+            .is_some()
+        {
+            return None;
+        }
+
+        let mut direct_conflicts = graph.direct_conflitcs(tx).map(|(_, txid)| txid).peekable();
+        if direct_conflicts.peek().is_some() {
+            let replacement = direct_conflicts.find(|txid| {
+                graph
+                    .get_chain_position(&self.chain, chain_tip, *txid)
+                    .is_some()
+            });
+            return Some(replacement.map_or(ConflictStatus::Conflicting, ConflictStatus::Replaced));
+        }
+
+        let mut ancestors_conflicts = graph.walk_ancestors(tx, |_, ancestor| {
+            Some(graph.direct_conflitcs(ancestor).next().is_some())
+        });
+        if ancestors_conflicts.any(|has_conflicts| has_conflicts) {
+            Some(ConflictStatus::Conflicting)
+        } else {
+            None
+        }
+    }
+
+    /// List the transactions of the wallet that were replaced or double-spent, see
+    /// [`Wallet::get_conflict_status`].
+    pub fn list_conflicted_transactions(&self) -> Vec<(Txid, ConflictStatus)> {
+        self.indexed_graph
+            .graph()
+            .full_txs()
+            .filter_map(|tx_node| {
+                self.get_conflict_status(tx_node.txid)
+                    .map(|status| (tx_node.txid, status))
+            })
+            .collect()
+    }
+
+    /// Return the unconfirmed ancestors of the transaction `txid`, i.e. the unconfirmed
+    /// transactions it spends from, directly or not.
+    ///
+    /// Only the transactions known by the wallet are returned, starting with the direct parents.
+    pub fn unconfirmed_ancestors(&self, txid: Txid) -> Vec<Txid> {
+        let graph = self.indexed_graph.graph();
+        let tx = match graph.get_tx(txid) {
+            Some(tx) => tx,
+            None => return vec![],
+        };
+        graph
+            .walk_ancestors(tx, |_, ancestor| {
+                let txid = ancestor.txid();
+                match graph.get_chain_position(&self.chain, self.chain.tip().block_id(), txid)? {
+                    ChainPosition::Unconfirmed(_) => Some(txid),
+                    ChainPosition::Confirmed(_) => None,
+                }
+            })
+            .collect()
+    }
+
+    /// Return the unconfirmed descendants of the transaction `txid`, i.e. the unconfirmed
+    /// transactions spending from it, directly or not.
+    ///
+    /// Only the transactions known by the wallet are returned, starting with the direct children.
+    pub fn unconfirmed_descendants(&self, txid: Txid) -> Vec<Txid> {
+        let graph = self.indexed_graph.graph();
+        graph
+            .walk_descendants(txid, |_, descendant| {
+                match graph.get_chain_position(
+                    &self.chain,
+                    self.chain.tip().block_id(),
+                    descendant,
+                )? {
+                    ChainPosition::Unconfirmed(_) => Some(descendant),
+                    ChainPosition::Confirmed(_) => None,
+                }
+            })
+            .collect()
+    }
+
+    /// Return the balance that can be spent without depending on unconfirmed transactions
+    /// created by someone else.
+    ///
+    /// This is the confirmed balance plus the unconfirmed outputs of transactions sent by the
+    /// wallet, as long as all their unconfirmed ancestors were sent by the wallet too. Spending
+    /// other unconfirmed outputs builds on transactions the wallet can't replace, and which can
+    /// grow beyond the mempool package limits without the wallet noticing.
+    pub fn effective_spendable_balance(&self) -> u64 {
+        let sent_by_wallet = |txid: Txid| {
+            self.indexed_graph
+                .graph()
+                .get_tx(txid)
+                .map_or(false, |tx| self.sent_and_received(tx).0 > 0)
+        };
+
+        let unconfirmed = self
+            .list_unspent()
+            .filter(|utxo| !utxo.confirmation_time.is_confirmed())
+            .filter(|utxo| {
+                let txid = utxo.outpoint.txid;
+                sent_by_wallet(txid)
+                    && self
+                        .unconfirmed_ancestors(txid)
+                        .into_iter()
+                        .all(&sent_by_wallet)
+            })
+            .map(|utxo| utxo.txout.value)
+            .sum::<u64>();
+
+        self.get_balance().confirmed + unconfirmed
+    }
+
     /// Return the balance, separated into available, trusted-pending, untrusted-pending and immature
     /// values.
     pub fn get_balance(&self) -> Balance {
@@ -1112,6 +1988,36 @@ impl<D> Wallet<D> {
         signers.add_external(signer.id(&self.secp), ordering, signer);
     }
 
+    /// Add signers for the private keys in `keymap`
+    ///
+    /// This turns a watch-only wallet into a signing one without rebuilding it, for example
+    /// with the [`KeyMap`] returned when parsing the private version of its descriptor. Keys that
+    /// aren't part of the descriptor of `keychain` are never used to sign.
+    pub fn add_signers_from_keymap(&mut self, keychain: KeychainKind, keymap: KeyMap) {
+        let container = SignersContainer::build(
+            keymap,
+            self.get_descriptor_for_keychain(keychain),
+            &self.secp,
+        );
+        let signers = match keychain {
+            KeychainKind::External => Arc::make_mut(&mut self.signers),
+            KeychainKind::Internal => Arc::make_mut(&mut self.change_signers),
+        };
+
+        for signer in container.signers() {
+            signers.add_external(
+                signer.id(&self.secp),
+                SignerOrdering::default(),
+                Arc::clone(signer),
+            );
+        }
+    }
+
+    /// Return whether the wallet doesn't have any signer, and can only watch its descriptors
+    pub fn is_watch_only(&self) -> bool {
+        self.signers.ids().is_empty() && self.change_signers.ids().is_empty()
+    }
+
     /// Get the signers
     ///
     /// ## Example
@@ -1172,11 +2078,115 @@ impl<D> Wallet<D> {
         }
     }
 
-    pub(crate) fn create_tx<Cs: coin_selection::CoinSelectionAlgorithm>(
+    pub(crate) fn create_tx<Cs: coin_selection::CoinSelectionAlgorithm>(
+        &mut self,
+        coin_selection: Cs,
+        params: TxParams,
+    ) -> Result<psbt::PartiallySignedTransaction, CreateTxError<D::WriteError>>
+    where
+        D: PersistBackend<ChangeSet>,
+    {
+        let (tx, selected, _, _) = self.prepare_tx(&coin_selection, &params, true)?;
+        let psbt = self.complete_transaction(tx, selected, params)?;
+        Ok(psbt)
+    }
+
+    pub(crate) fn create_tx_with_details<Cs: coin_selection::CoinSelectionAlgorithm>(
         &mut self,
         coin_selection: Cs,
         params: TxParams,
-    ) -> Result<psbt::PartiallySignedTransaction, CreateTxError<D::WriteError>>
+    ) -> Result<
+        (psbt::PartiallySignedTransaction, tx_builder::TxDetails),
+        CreateTxError<D::WriteError>,
+    >
+    where
+        D: PersistBackend<ChangeSet>,
+    {
+        let (tx, selected, weight, excess) = self.prepare_tx(&coin_selection, &params, true)?;
+
+        let input_value = selected.iter().map(|u| u.txout().value).sum::<u64>();
+        let output_value = tx.output.iter().map(|txout| txout.value).sum::<u64>();
+        let fee = input_value.saturating_sub(output_value);
+        let selected_utxos = selected
+            .iter()
+            .map(|utxo| {
+                let outpoint = utxo.outpoint();
+                let reason = if params.utxos.iter().any(|u| u.utxo.outpoint() == outpoint) {
+                    tx_builder::SelectionReason::Manual
+                } else if params.drain_wallet {
+                    tx_builder::SelectionReason::DrainWallet
+                } else {
+                    tx_builder::SelectionReason::CoinSelection
+                };
+                tx_builder::SelectedUtxo {
+                    outpoint,
+                    value: utxo.txout().value,
+                    reason,
+                }
+            })
+            .collect();
+        let change = match excess {
+            Change { amount, .. } => Some(amount),
+            NoChange { .. } => None,
+        };
+        // the waste is computed with the fee rate of the transaction as long term fee rate, so
+        // only the excess is wasted
+        let waste = match excess {
+            Change { fee, .. } => fee,
+            NoChange {
+                remaining_amount, ..
+            } => remaining_amount,
+        };
+        let details = tx_builder::TxDetails {
+            selected_utxos,
+            change,
+            fee,
+            fee_rate: FeeRate::from_wu(fee, weight),
+            weight,
+            excess,
+            waste,
+            external_policy_path: params.external_policy_path.clone(),
+            internal_policy_path: params.internal_policy_path.clone(),
+        };
+
+        let psbt = self.complete_transaction(tx, selected, params)?;
+        Ok((psbt, details))
+    }
+
+    pub(crate) fn estimate_tx<Cs: coin_selection::CoinSelectionAlgorithm>(
+        &mut self,
+        coin_selection: &Cs,
+        params: &TxParams,
+    ) -> Result<tx_builder::TxEstimate, CreateTxError<D::WriteError>>
+    where
+        D: PersistBackend<ChangeSet>,
+    {
+        let (tx, selected, weight, _) = self.prepare_tx(coin_selection, params, false)?;
+        let input_value = selected.iter().map(|u| u.txout().value).sum::<u64>();
+        let output_value = tx.output.iter().map(|txout| txout.value).sum::<u64>();
+        let fee = input_value.saturating_sub(output_value);
+        Ok(tx_builder::TxEstimate {
+            weight,
+            fee,
+            fee_rate: FeeRate::from_wu(fee, weight),
+        })
+    }
+
+    /// Select the coins and build the unsigned transaction described by `params`, returning it
+    /// with the selected UTXOs, its estimated weight once signed and the excess of the coin
+    /// selection.
+    ///
+    /// A new change address is revealed and persisted only if `reveal_change` is set, otherwise
+    /// the next one is used without revealing it.
+    fn prepare_tx<Cs: coin_selection::CoinSelectionAlgorithm>(
+        &mut self,
+        coin_selection: &Cs,
+        params: &TxParams,
+        reveal_change: bool,
+    ) -> Result<
+        (Transaction, Vec<Utxo>, Weight, coin_selection::Excess),
+        CreateTxError<D::WriteError>,
+    >
     where
         D: PersistBackend<ChangeSet>,
     {
@@ -1358,7 +2368,8 @@ impl<D> Wallet<D> {
             }
             FeePolicy::FeeRate(rate) => {
                 if let Some(previous_fee) = params.bumping_fee {
-                    let required_feerate = FeeRate::from_sat_per_vb(previous_fee.rate + 1.0);
+                    let required_feerate = FeeRate::from_sat_per_vb(previous_fee.rate)
+                        + FeeRate::default_min_relay_fee();
                     if *rate < required_feerate {
                         return Err(CreateTxError::FeeRateTooLow {
                             required: required_feerate,
@@ -1387,6 +2398,14 @@ impl<D> Wallet<D> {
         let recipients = params.recipients.iter().map(|(r, v)| (r, *v));
 
         for (index, (script_pubkey, value)) in recipients.enumerate() {
+            if script_pubkey.is_op_return() && script_pubkey.len() > tx_builder::MAX_OP_RETURN_RELAY
+            {
+                return Err(CreateTxError::OpReturnTooLarge {
+                    index,
+                    size: script_pubkey.len(),
+                });
+            }
+
             if !params.allow_dust
                 && value.is_dust(script_pubkey)
                 && !script_pubkey.is_provably_unspendable()
@@ -1408,6 +2427,26 @@ impl<D> Wallet<D> {
             outgoing += value;
         }
 
+        if let Some(index) = params
+            .subtract_fee_from
+            .iter()
+            .chain(params.first_output.iter())
+            .find(|i| **i >= tx.output.len())
+        {
+            return Err(CreateTxError::InvalidRecipientIndex(*index));
+        }
+        if params.reject_duplicate_recipients {
+            let mut seen = HashSet::new();
+            if let Some(index) = tx
+                .output
+                .iter()
+                .position(|txout| !seen.insert(txout.script_pubkey.clone()))
+            {
+                return Err(CreateTxError::DuplicateRecipient(index));
+            }
+        }
+        let subtract_fee = !params.subtract_fee_from.is_empty();
+
         fee_amount += fee_rate.fee_wu(tx.weight());
 
         // Segwit transactions' header is 2WU larger than legacy txs' header,
@@ -1440,6 +2479,28 @@ impl<D> Wallet<D> {
         // get drain script
         let drain_script = match params.drain_to {
             Some(ref drain_recipient) => drain_recipient.clone(),
+            None if !reveal_change => {
+                let change_keychain = self.map_keychain(KeychainKind::Internal);
+                match self
+                    .indexed_graph
+                    .index
+                    .unused_spks_of_keychain(&change_keychain)
+                    .next()
+                {
+                    Some((_, spk)) => spk.into(),
+                    None => {
+                        let index = self
+                            .indexed_graph
+                            .index
+                            .last_revealed_index(&change_keychain)
+                            .map_or(0, |index| index + 1);
+                        self.indexed_graph.index.keychains()[&change_keychain]
+                            .at_derivation_index(index)
+                            .expect("must be a valid derivation index")
+                            .script_pubkey()
+                    }
+                }
+            }
             None => {
                 let change_keychain = self.map_keychain(KeychainKind::Internal);
                 let ((index, spk), index_changeset) =
@@ -1455,15 +2516,50 @@ impl<D> Wallet<D> {
             }
         };
 
-        let coin_selection = coin_selection.coin_select(
-            required_utxos,
-            optional_utxos,
-            fee_rate,
-            outgoing + fee_amount,
-            &drain_script,
-        )?;
-        fee_amount += coin_selection.fee_amount;
-        let excess = &coin_selection.excess;
+        // When the fee is paid by the recipients the coin selection only has to cover the
+        // outgoing amount, the fee for the inputs is added afterwards
+        let input_fees = required_utxos
+            .iter()
+            .chain(optional_utxos.iter())
+            .map(|u| (u.utxo.outpoint(), u.fee(fee_rate)))
+            .collect::<HashMap<_, _>>();
+        let coin_selection = if subtract_fee {
+            let coin_selection = coin_selection.coin_select(
+                required_utxos,
+                optional_utxos,
+                FeeRate::from_sat_per_vb(0.0),
+                outgoing,
+                &drain_script,
+            )?;
+            fee_amount += coin_selection
+                .selected
+                .iter()
+                .map(|u| input_fees.get(&u.outpoint()).copied().unwrap_or(0))
+                .sum::<u64>();
+            coin_selection
+        } else {
+            let coin_selection = coin_selection.coin_select(
+                required_utxos,
+                optional_utxos,
+                fee_rate,
+                outgoing + fee_amount,
+                &drain_script,
+            )?;
+            fee_amount += coin_selection.fee_amount;
+            coin_selection
+        };
+        let mut excess = coin_selection.excess;
+        // a change below the requested minimum is left to the fees, like dust change
+        if let Change { amount, fee } = excess {
+            if amount < params.min_change_value {
+                excess = NoChange {
+                    dust_threshold: params.min_change_value,
+                    remaining_amount: amount + fee,
+                    change_fee: fee,
+                };
+            }
+        }
+        let excess = &excess;
 
         tx.input = coin_selection
             .selected
@@ -1482,9 +2578,12 @@ impl<D> Wallet<D> {
             // - We have a drain_to address and the utxos we must spend (this happens,
             // for example, when we RBF)
             // - We have a drain_to address and drain_wallet set
+            // - We drain the utxos we must spend to the change (this happens for CPFP)
             // Otherwise, we don't know who we should send the funds to, and how much
             // we should send!
-            if params.drain_to.is_some() && (params.drain_wallet || !params.utxos.is_empty()) {
+            if (params.drain_to.is_some() || params.drain_to_change)
+                && (params.drain_wallet || !params.utxos.is_empty())
+            {
                 if let NoChange {
                     dust_threshold,
                     remaining_amount,
@@ -1501,6 +2600,23 @@ impl<D> Wallet<D> {
             }
         }
 
+        if subtract_fee {
+            let fee_to_subtract = match excess {
+                // anything left over is already going to the miners
+                NoChange {
+                    remaining_amount, ..
+                } => fee_amount.saturating_sub(*remaining_amount),
+                Change { .. } => fee_amount + fee_rate.fee_vb(serialize(&drain_script).len() + 8),
+            };
+            subtract_fee_from_outputs(
+                &mut tx,
+                &params.subtract_fee_from,
+                fee_to_subtract,
+                params.allow_dust,
+            )
+            .map_err(CreateTxError::OutputBelowDustLimit)?;
+        }
+
         match excess {
             NoChange {
                 remaining_amount, ..
@@ -1524,11 +2640,38 @@ impl<D> Wallet<D> {
             }
         };
 
-        // sort input/outputs according to the chosen algorithm
+        // the final weight is only known after signing, estimate it from the satisfaction weights
+        let estimated_weight = tx.weight().to_wu()
+            + 2
+            + coin_selection
+                .selected
+                .iter()
+                .map(|u| {
+                    satisfaction_weights
+                        .get(&u.outpoint())
+                        .copied()
+                        .unwrap_or(0) as u64
+                })
+                .sum::<u64>();
+        if estimated_weight > MAX_STANDARD_TX_WEIGHT as u64 {
+            return Err(CreateTxError::TransactionTooLarge {
+                weight: estimated_weight,
+            });
+        }
+
+        // sort input/outputs according to the chosen algorithm, keeping the pinned output first
+        let first_output = params.first_output.map(|index| tx.output.remove(index));
         params.ordering.sort_tx(&mut tx);
+        if let Some(output) = first_output {
+            tx.output.insert(0, output);
+        }
 
-        let psbt = self.complete_transaction(tx, coin_selection.selected, params)?;
-        Ok(psbt)
+        Ok((
+            tx,
+            coin_selection.selected,
+            Weight::from_wu(estimated_weight),
+            *excess,
+        ))
     }
 
     /// Bump the fee of a transaction previously created with this wallet.
@@ -1704,6 +2847,132 @@ impl<D> Wallet<D> {
         })
     }
 
+    /// Accelerate an unconfirmed transaction using *child pays for parent* (CPFP).
+    ///
+    /// Returns a [`TxBuilder`] that spends all the outputs of `txid` owned by the wallet to a new
+    /// change address, paying a fee so that the child, the parent and the unconfirmed ancestors of
+    /// the parent together reach `package_fee_rate`. If they already pay more than that, the child
+    /// pays `package_fee_rate` for its own size. Like for [`build_tx`](Self::build_tx), the change
+    /// address is only revealed and persisted when the builder is finished.
+    ///
+    /// The fee of the child is computed from the estimated size of the transaction as returned by
+    /// the builder. Adding inputs or outputs to the builder makes the package fee rate lower than
+    /// requested.
+    ///
+    /// Returns an error if the transaction is confirmed, if it doesn't have any unspent output
+    /// owned by the wallet or if its fee can't be calculated.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// # use bitcoin::Txid;
+    /// # use bdk::*;
+    /// # let mut wallet: Wallet<()> = todo!();
+    /// # let txid: Txid = todo!();
+    /// let mut psbt = {
+    ///     let mut builder = wallet.build_cpfp(txid, FeeRate::from_sat_per_vb(20.0))?;
+    ///     builder.enable_rbf();
+    ///     builder.finish()?
+    /// };
+    /// let _ = wallet.sign(&mut psbt, SignOptions::default())?;
+    /// let child_tx = psbt.extract_tx();
+    /// // broadcast child_tx along with the parent
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn build_cpfp(
+        &mut self,
+        txid: Txid,
+        package_fee_rate: FeeRate,
+    ) -> Result<
+        TxBuilder<'_, D, DefaultCoinSelectionAlgorithm, CreateTx>,
+        BuildCpfpError<D::WriteError>,
+    >
+    where
+        D: PersistBackend<ChangeSet>,
+    {
+        let graph = self.indexed_graph.graph();
+        let chain_tip = self.chain.tip().block_id();
+
+        let parent = graph
+            .get_tx(txid)
+            .ok_or(BuildCpfpError::TransactionNotFound(txid))?;
+        let pos = graph
+            .get_chain_position(&self.chain, chain_tip, txid)
+            .ok_or(BuildCpfpError::TransactionNotFound(txid))?;
+        if let ChainPosition::Confirmed(_) = pos {
+            return Err(BuildCpfpError::TransactionConfirmed(txid));
+        }
+
+        // the parent is mined along with its unconfirmed ancestors, so they are part of the package
+        let mut package_fee = 0;
+        let mut package_weight = Weight::ZERO;
+        let mut to_visit = vec![parent];
+        let mut visited = HashSet::from([txid]);
+        while let Some(tx) = to_visit.pop() {
+            package_fee += self
+                .calculate_fee(tx)
+                .map_err(|_| BuildCpfpError::FeeRateUnavailable)?;
+            package_weight += tx.weight();
+            for txin in &tx.input {
+                let prev_txid = txin.previous_output.txid;
+                if !visited.insert(prev_txid) {
+                    continue;
+                }
+                if let (Some(prev_tx), Some(ChainPosition::Unconfirmed(_))) = (
+                    graph.get_tx(prev_txid),
+                    graph.get_chain_position(&self.chain, chain_tip, prev_txid),
+                ) {
+                    to_visit.push(prev_tx);
+                }
+            }
+        }
+
+        let utxos = self
+            .list_unspent()
+            .filter(|utxo| utxo.outpoint.txid == txid)
+            .map(|utxo| {
+                #[allow(deprecated)]
+                let satisfaction_weight = self
+                    .get_descriptor_for_keychain(utxo.keychain)
+                    .max_satisfaction_weight()
+                    .unwrap();
+                WeightedUtxo {
+                    satisfaction_weight,
+                    utxo: Utxo::Local(utxo),
+                }
+            })
+            .collect::<Vec<_>>();
+        if utxos.is_empty() {
+            return Err(BuildCpfpError::NoSpendableOutputs(txid));
+        }
+
+        let mut params = TxParams {
+            utxos,
+            manually_selected_only: true,
+            drain_to_change: true,
+            fee_policy: Some(FeePolicy::FeeAmount(0)),
+            ..Default::default()
+        };
+        // the fee doesn't change the weight of the child, which only has the drain output
+        let child_weight = self
+            .estimate_tx(&DefaultCoinSelectionAlgorithm::default(), &params)
+            .map_err(BuildCpfpError::CreateTx)?
+            .weight;
+
+        let child_fee = package_fee_rate
+            .fee_wu(package_weight + child_weight)
+            .saturating_sub(package_fee)
+            .max(package_fee_rate.fee_wu(child_weight));
+        params.fee_policy = Some(FeePolicy::FeeAmount(child_fee));
+
+        Ok(TxBuilder {
+            wallet: alloc::rc::Rc::new(core::cell::RefCell::new(self)),
+            params,
+            coin_selection: DefaultCoinSelectionAlgorithm::default(),
+            phantom: core::marker::PhantomData,
+        })
+    }
+
     /// Sign a transaction with all the wallet's signers, in the order specified by every signer's
     /// [`SignerOrdering`]. This function returns the `Result` type with an encapsulated `bool` that has the value true if the PSBT was finalized, or false otherwise.
     ///
@@ -1712,6 +2981,9 @@ impl<D> Wallet<D> {
     /// signers will follow the options, but the "software signers" (WIF keys and `xprv`) defined
     /// in this library will.
     ///
+    /// A watch-only wallet can't sign, but still finalizes the inputs signed elsewhere. It returns
+    /// [`SignerError::NoSigners`] if it couldn't finalize any of them.
+    ///
     /// ## Example
     ///
     /// ```
@@ -1737,6 +3009,14 @@ impl<D> Wallet<D> {
         psbt: &mut psbt::PartiallySignedTransaction,
         sign_options: SignOptions,
     ) -> Result<bool, SignerError> {
+        let count_finalized = |psbt: &psbt::PartiallySignedTransaction| {
+            psbt.inputs
+                .iter()
+                .filter(|i| i.final_script_sig.is_some() || i.final_script_witness.is_some())
+                .count()
+        };
+        let finalized_before = count_finalized(psbt);
+
         // This adds all the PSBT metadata for the inputs, which will help us later figure out how
         // to derive our keys
         self.update_psbt_with_descriptor(psbt)
@@ -1778,11 +3058,18 @@ impl<D> Wallet<D> {
         }
 
         // attempt to finalize
-        if sign_options.try_finalize {
-            self.finalize_psbt(psbt, sign_options)
+        let finalized = if sign_options.try_finalize {
+            self.finalize_psbt(psbt, sign_options)?
         } else {
-            Ok(false)
+            false
+        };
+
+        // without signers, finalizing the signatures made elsewhere is all we can do
+        if self.is_watch_only() && !finalized && count_finalized(psbt) == finalized_before {
+            return Err(SignerError::NoSigners(self.master_fingerprints()));
         }
+
+        Ok(finalized)
     }
 
     /// Return the spending policies for the wallet's descriptor
@@ -1806,6 +3093,112 @@ impl<D> Wallet<D> {
         self.indexed_graph.index.keychains().get(&keychain)
     }
 
+    /// Combine PSBTs of the same transaction, for instance signed out-of-band by different
+    /// cosigners, into a single PSBT.
+    ///
+    /// On every input spending an output of this wallet, each partial signature must be made by
+    /// one of the keys of the wallet's descriptor derived at the index of the spent script,
+    /// otherwise [`CombinePsbtError::UnexpectedSignature`] is returned, and must be valid for the
+    /// output known to the wallet, otherwise [`CombinePsbtError::InvalidSignature`] is returned.
+    /// Inputs spending outputs that don't belong to the wallet, like the ones of a payjoin, aren't
+    /// checked. The
+    /// combined PSBT can then be finalized with [`Wallet::finalize_psbt`] and extracted to be
+    /// broadcast.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// # use bdk::*;
+    /// # use bitcoin::psbt::PartiallySignedTransaction as Psbt;
+    /// # let wallet = doctest_wallet!();
+    /// # let psbt_a: Psbt = unimplemented!();
+    /// # let psbt_b: Psbt = unimplemented!();
+    /// let mut psbt = wallet.combine_psbts(&[psbt_a, psbt_b])?;
+    /// let finalized = wallet.finalize_psbt(&mut psbt, SignOptions::default())?;
+    /// assert!(finalized, "not enough signatures yet");
+    /// let tx = psbt.extract_tx();
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn combine_psbts(
+        &self,
+        psbts: &[psbt::PartiallySignedTransaction],
+    ) -> Result<psbt::PartiallySignedTransaction, CombinePsbtError> {
+        let (first, others) = psbts.split_first().ok_or(CombinePsbtError::NoPsbts)?;
+        let mut combined = first.clone();
+        for psbt in others {
+            combined
+                .combine(psbt.clone())
+                .map_err(CombinePsbtError::Psbt)?;
+        }
+
+        // The signatures are verified against the spent outputs known to the wallet, not the
+        // ones provided in the PSBTs
+        let mut verified = combined.clone();
+        let mut descriptors = Vec::new();
+        for (n, txin) in combined.unsigned_tx.input.iter().enumerate() {
+            // Prefer the spent output known to the wallet over the one provided in the PSBT.
+            // Inputs that don't belong to the wallet, for instance added with
+            // `add_foreign_utxo` or by a payjoin receiver, are left to their owners
+            let graph = self.indexed_graph.graph();
+            let txout = graph
+                .get_txout(txin.previous_output)
+                .cloned()
+                .or_else(|| combined.get_utxo_for(n));
+            let descriptor = match txout
+                .as_ref()
+                .and_then(|txout| self.get_descriptor_for_txout(txout))
+            {
+                Some(descriptor) => descriptor,
+                None => continue,
+            };
+            if let Some(prev_tx) = graph.get_tx(txin.previous_output.txid) {
+                verified.inputs[n].non_witness_utxo = Some(prev_tx.clone());
+            }
+            verified.inputs[n].witness_utxo = txout;
+            descriptors.push((n, txin.previous_output, descriptor));
+        }
+
+        for (n, outpoint, descriptor) in descriptors {
+            // Every signature must be made by one of the keys of the descriptor, derived at the
+            // index of the spent script, so that nothing else can be smuggled in the PSBT
+            let mut keys = Vec::new();
+            descriptor.for_each_key(|key| {
+                if let Ok(pk) = key.derive_public_key(&self.secp) {
+                    keys.push(pk);
+                }
+                true
+            });
+            let psbt_input = &combined.inputs[n];
+            let desc_type = descriptor.desc_type();
+            let unexpected_ecdsa = psbt_input.partial_sigs.keys().any(|pk| !keys.contains(pk));
+            let unexpected_schnorr = psbt_input.tap_script_sigs.keys().any(|(xonly, _)| {
+                !keys
+                    .iter()
+                    .any(|pk| pk.inner.x_only_public_key().0 == *xonly)
+            });
+            let unexpected_key_spend =
+                psbt_input.tap_key_sig.is_some() && desc_type != DescriptorType::Tr;
+            if unexpected_ecdsa || unexpected_schnorr || unexpected_key_spend {
+                return Err(CombinePsbtError::UnexpectedSignature(outpoint));
+            }
+
+            let is_witness = !matches!(
+                desc_type,
+                DescriptorType::Bare
+                    | DescriptorType::Pkh
+                    | DescriptorType::Sh
+                    | DescriptorType::ShSortedMulti
+            );
+            if !signer::verify_input_signatures(&mut verified, n, is_witness, &self.secp)
+                .map_err(CombinePsbtError::Signer)?
+            {
+                return Err(CombinePsbtError::InvalidSignature(outpoint));
+            }
+        }
+
+        Ok(combined)
+    }
+
     /// Finalize a PSBT, i.e., for each input determine if sufficient data is available to pass
     /// validation and construct the respective `scriptSig` or `scriptWitness`. Please refer to
     /// [BIP174](https://github.com/bitcoin/bips/blob/master/bip-0174.mediawiki#Input_Finalizer)
@@ -1896,6 +3289,176 @@ impl<D> Wallet<D> {
         Ok(finished)
     }
 
+    /// Sign `message` with the key of `address`, to prove its ownership
+    ///
+    /// The signature is returned base64-encoded and can be verified with
+    /// [`verify_message`](message::verify_message). The [`MessageSignatureFormat::Legacy`] format
+    /// requires the wallet to have the private key of a p2pkh, p2wpkh or p2sh-p2wpkh address,
+    /// while [`MessageSignatureFormat::Bip322Simple`] works with any segwit address the wallet
+    /// can fully sign for.
+    ///
+    /// The `address` must have been revealed by the wallet.
+    pub fn sign_message(
+        &self,
+        address: &Address,
+        message: &str,
+        format: MessageSignatureFormat,
+    ) -> Result<String, MessageSigningError> {
+        let script_pubkey = address.script_pubkey();
+        let &(keychain, index) = self
+            .indexed_graph
+            .index
+            .index_of_spk(&script_pubkey)
+            .ok_or(MessageSigningError::UnknownAddress)?;
+
+        match format {
+            MessageSignatureFormat::Legacy => {
+                if !(script_pubkey.is_p2pkh()
+                    || script_pubkey.is_p2sh()
+                    || script_pubkey.is_v0_p2wpkh())
+                {
+                    return Err(MessageSigningError::UnsupportedAddress);
+                }
+
+                let signers = match keychain {
+                    KeychainKind::External => &self.signers,
+                    KeychainKind::Internal => &self.change_signers,
+                };
+                signers
+                    .as_key_map(&self.secp)
+                    .values()
+                    .filter_map(|key| match key {
+                        DescriptorSecretKey::Single(single) => Some(single.key),
+                        DescriptorSecretKey::XPrv(xprv) => {
+                            let child = match xprv.wildcard {
+                                Wildcard::None => None,
+                                Wildcard::Unhardened => Some(ChildNumber::from_normal_idx(index)),
+                                Wildcard::Hardened => Some(ChildNumber::from_hardened_idx(index)),
+                            };
+                            let path = match child {
+                                Some(child) => xprv.derivation_path.child(child.ok()?),
+                                None => xprv.derivation_path.clone(),
+                            };
+                            xprv.xkey
+                                .derive_priv(&self.secp, &path)
+                                .ok()
+                                .map(|xkey| xkey.to_priv())
+                        }
+                        DescriptorSecretKey::MultiXPrv(_) => None,
+                    })
+                    .find_map(|key| message::sign_legacy(&self.secp, &key, &script_pubkey, message))
+                    .ok_or(MessageSigningError::MissingKey)
+            }
+            MessageSignatureFormat::Bip322Simple => {
+                if !script_pubkey.is_witness_program() {
+                    return Err(MessageSigningError::UnsupportedAddress);
+                }
+
+                let to_spend = message::bip322_to_spend(&script_pubkey, message);
+                let mut psbt = psbt::PartiallySignedTransaction::from_unsigned_tx(
+                    message::bip322_to_sign(to_spend.txid()),
+                )
+                .expect("the transaction is unsigned");
+                psbt.inputs[0].witness_utxo = Some(to_spend.output[0].clone());
+
+                let sign_options = SignOptions {
+                    trust_witness_utxo: true,
+                    ..Default::default()
+                };
+                self.sign(&mut psbt, sign_options)?;
+
+                psbt.inputs[0]
+                    .final_script_witness
+                    .as_ref()
+                    .map(message::encode_bip322_witness)
+                    .ok_or(MessageSigningError::MissingKey)
+            }
+        }
+    }
+
+    /// Create a proof of reserves for `message`, spending all the UTXOs of the wallet
+    ///
+    /// The returned PSBT is signed and finalized, as far as the signers of the wallet allow. See
+    /// the [`reserves`] module for more.
+    pub fn create_proof(
+        &self,
+        message: &str,
+    ) -> Result<psbt::PartiallySignedTransaction, ProofError> {
+        let utxos = self.list_unspent().collect::<Vec<_>>();
+        if utxos.is_empty() {
+            return Err(ProofError::NoUtxos);
+        }
+
+        let tx = Transaction {
+            version: 1,
+            lock_time: absolute::LockTime::ZERO,
+            input: core::iter::once(reserves::challenge_txin(message))
+                .chain(utxos.iter().map(|utxo| bitcoin::TxIn {
+                    previous_output: utxo.outpoint,
+                    ..Default::default()
+                }))
+                .collect(),
+            output: vec![TxOut {
+                value: utxos.iter().map(|utxo| utxo.txout.value).sum(),
+                script_pubkey: reserves::proof_script_pubkey(),
+            }],
+        };
+        let mut psbt = psbt::PartiallySignedTransaction::from_unsigned_tx(tx)
+            .expect("the transaction is unsigned");
+        psbt.inputs[0] = reserves::challenge_psbt_input();
+        for (index, utxo) in utxos.into_iter().enumerate() {
+            psbt.inputs[index + 1] = self
+                .get_psbt_input(utxo, None, false)
+                .map_err(|_| ProofError::NonSpendableInput(index + 1))?;
+        }
+
+        let sign_options = SignOptions {
+            trust_witness_utxo: true,
+            ..Default::default()
+        };
+        self.sign(&mut psbt, sign_options)?;
+
+        Ok(psbt)
+    }
+
+    /// Verify a proof of reserves for `message` against the UTXOs of the wallet, and return the
+    /// amount it proves
+    ///
+    /// Only the UTXOs confirmed at or below `max_block_height` are accepted, if it's set. The
+    /// wallet doesn't need any private key, so an auditor can use a watch-only wallet synced
+    /// with its own blockchain backend.
+    pub fn verify_proof(
+        &self,
+        psbt: &psbt::PartiallySignedTransaction,
+        message: &str,
+        max_block_height: Option<u32>,
+    ) -> Result<u64, ProofError> {
+        let utxos = self
+            .list_unspent()
+            .filter(|utxo| match (max_block_height, utxo.confirmation_time) {
+                (None, _) => true,
+                (Some(max), ConfirmationTime::Confirmed { height, .. }) => height <= max,
+                (Some(_), ConfirmationTime::Unconfirmed { .. }) => false,
+            })
+            .map(|utxo| (utxo.outpoint, utxo.txout))
+            .collect::<HashMap<_, _>>();
+
+        reserves::verify_proof(psbt, message, |outpoint| utxos.get(outpoint).cloned())
+    }
+
+    /// Return the master fingerprints of all the keys in the descriptors of the wallet
+    fn master_fingerprints(&self) -> Vec<Fingerprint> {
+        let mut fingerprints = BTreeSet::new();
+        for descriptor in self.indexed_graph.index.keychains().values() {
+            descriptor.for_each_key(|key| {
+                fingerprints.insert(key.master_fingerprint());
+                true
+            });
+        }
+
+        fingerprints.into_iter().collect()
+    }
+
     /// Return the secp256k1 context used for all signing operations
     pub fn secp_ctx(&self) -> &SecpCtx {
         &self.secp
@@ -2073,6 +3636,20 @@ impl<D> Wallet<D> {
     where
         D: PersistBackend<ChangeSet>,
     {
+        // SIGHASH_SINGLE with no output at the same index signs the hash `1` instead of the
+        // transaction, which would let anyone spend the input
+        if let Some(input) = tx.input.iter().skip(tx.output.len()).find(|input| {
+            params
+                .input_sighashes
+                .get(&input.previous_output)
+                .or(params.sighash.as_ref())
+                .map_or(false, |sighash| sighash.to_u32() & 0x1f == 0x03)
+        }) {
+            return Err(CreateTxError::SighashSingleWithoutOutput(
+                input.previous_output,
+            ));
+        }
+
         let mut psbt = psbt::PartiallySignedTransaction::from_unsigned_tx(tx)?;
 
         if params.add_global_xpubs {
@@ -2107,19 +3684,24 @@ impl<D> Wallet<D> {
                 None => continue,
             };
 
+            let sighash = params
+                .input_sighashes
+                .get(&input.previous_output)
+                .copied()
+                .or(params.sighash);
             match utxo {
                 Utxo::Local(utxo) => {
-                    *psbt_input =
-                        match self.get_psbt_input(utxo, params.sighash, params.only_witness_utxo) {
-                            Ok(psbt_input) => psbt_input,
-                            Err(e) => match e {
-                                CreateTxError::UnknownUtxo => psbt::Input {
-                                    sighash_type: params.sighash,
-                                    ..psbt::Input::default()
-                                },
-                                _ => return Err(e),
+                    *psbt_input = match self.get_psbt_input(utxo, sighash, params.only_witness_utxo)
+                    {
+                        Ok(psbt_input) => psbt_input,
+                        Err(e) => match e {
+                            CreateTxError::UnknownUtxo => psbt::Input {
+                                sighash_type: sighash,
+                                ..psbt::Input::default()
                             },
-                        }
+                            _ => return Err(e),
+                        },
+                    }
                 }
                 Utxo::Foreign {
                     psbt_input: foreign_psbt_input,
@@ -2275,6 +3857,63 @@ impl<D> Wallet<D> {
         Ok(())
     }
 
+    /// Computes what applying `update` would change in the wallet, without touching the wallet.
+    ///
+    /// This is useful to inspect the result of a sync before committing to it, for instance to
+    /// debug discrepancies between different blockchain backends. Note that the wallet's
+    /// transaction graph is copied to compute the preview.
+    pub fn preview_update(&self, update: Update) -> Result<UpdatePreview, CannotConnectError> {
+        let mut chain = self.chain.clone();
+        let mut changeset = match update.chain {
+            Some(chain_update) => ChangeSet::from(chain.apply_update(chain_update)?),
+            None => ChangeSet::default(),
+        };
+
+        let mut indexed_graph = IndexedTxGraph::<ConfirmationTimeHeightAnchor, _>::new(
+            self.indexed_graph.index.clone(),
+        );
+        indexed_graph.apply_changeset(indexed_tx_graph::ChangeSet {
+            graph: self.indexed_graph.graph().initial_changeset(),
+            indexer: Default::default(),
+        });
+
+        let (_, index_changeset) = indexed_graph
+            .index
+            .reveal_to_target_multi(&update.last_active_indices);
+        changeset.append(ChangeSet::from(indexed_tx_graph::ChangeSet::from(
+            index_changeset,
+        )));
+        changeset.append(ChangeSet::from(indexed_graph.apply_update(update.graph)));
+
+        let unspent_before = self
+            .list_unspent()
+            .map(|utxo| utxo.outpoint)
+            .collect::<BTreeSet<_>>();
+        let unspent_after = indexed_graph
+            .graph()
+            .filter_chain_unspents(
+                &chain,
+                chain.tip().block_id(),
+                indexed_graph.index.outpoints().iter().cloned(),
+            )
+            .map(|(_, full_txo)| full_txo.outpoint)
+            .collect::<BTreeSet<_>>();
+
+        Ok(UpdatePreview {
+            new_txs: changeset
+                .indexed_tx_graph
+                .graph
+                .txs
+                .iter()
+                .map(|tx| tx.txid())
+                .collect(),
+            new_utxos: unspent_after.difference(&unspent_before).cloned().collect(),
+            removed_utxos: unspent_before.difference(&unspent_after).cloned().collect(),
+            index_advances: changeset.indexed_tx_graph.indexer.as_inner().clone(),
+            changeset,
+        })
+    }
+
     /// Commits all currently [`staged`] changed to the persistence backend returning and error when
     /// this fails.
     ///
@@ -2320,6 +3959,22 @@ impl<D> AsRef<bdk_chain::tx_graph::TxGraph<ConfirmationTimeHeightAnchor>> for Wa
     }
 }
 
+/// The checksum of the descriptor of every keychain
+fn descriptor_checksums(
+    keychains: &BTreeMap<KeychainKind, ExtendedDescriptor>,
+) -> BTreeMap<KeychainKind, String> {
+    keychains
+        .iter()
+        .map(|(keychain, descriptor)| {
+            let descriptor = descriptor.to_string();
+            let (_, checksum) = descriptor
+                .split_once('#')
+                .expect("descriptors are displayed with a checksum");
+            (*keychain, checksum.to_string())
+        })
+        .collect()
+}
+
 /// Deterministically generate a unique name given the descriptors defining the wallet
 ///
 /// Compatible with [`wallet_name_from_descriptor`]
@@ -2366,6 +4021,33 @@ fn new_local_utxo(
     }
 }
 
+/// Split `fee` evenly between the outputs at `indexes`, the remainder is paid by the first one
+///
+/// Returns the index of the first output that would become dust or negative
+fn subtract_fee_from_outputs(
+    tx: &mut Transaction,
+    indexes: &[usize],
+    fee: u64,
+    allow_dust: bool,
+) -> Result<(), usize> {
+    let share = fee / indexes.len() as u64;
+    let remainder = fee % indexes.len() as u64;
+
+    for (i, index) in indexes.iter().enumerate() {
+        let to_subtract = if i == 0 { share + remainder } else { share };
+        let output = &mut tx.output[*index];
+        output.value = output.value.checked_sub(to_subtract).ok_or(*index)?;
+        if !allow_dust
+            && output.value.is_dust(&output.script_pubkey)
+            && !output.script_pubkey.is_provably_unspendable()
+        {
+            return Err(*index);
+        }
+    }
+
+    Ok(())
+}
+
 fn create_signers<E: IntoWalletDescriptor>(
     index: &mut KeychainTxOutIndex<KeychainKind>,
     secp: &Secp256k1<All>,
@@ -0,0 +1,599 @@
+// Bitcoin Dev Kit
+//
+// Copyright (c) 2020-2021 Bitcoin Dev Kit Developers
+//
+// This file is licensed under the Apache License, Version 2.0 <LICENSE-APACHE
+// or http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your option.
+// You may not use this file except in accordance with one or both of these
+// licenses.
+
+//! Multisig coordination
+//!
+//! This module contains helpers to set up a `sortedmulti` wallet shared by several cosigners and
+//! to coordinate the signing of its transactions.
+//!
+//! A [`MultisigSetup`] can be serialized to, and parsed from, the multisig setup file format used
+//! by [Coldcard](https://coldcard.com/docs/multisig) and Specter, so that it can be imported by
+//! every participant.
+//!
+//! ## Example
+//!
+//! ```
+//! # use std::str::FromStr;
+//! # use bdk::wallet::multisig::*;
+//! # use bdk::{KeychainKind, Wallet};
+//! # use bitcoin::Network;
+//! let setup = MultisigSetup::from_str(
+//!     r#"# Coldcard Multisig setup file
+//! Name: Savings
+//! Policy: 2 of 2
+//! Format: P2WSH
+//!
+//! Derivation: m/84'/1'/0'
+//! C258D2E4: tpubDD3ynpHgJQW8VvWRzQ5WFDCrs4jqVFGHB3vLC3r49XHJSqP8bHKdK4AriuUKLccK68zfzowx7YhmDN8SiSkgCDENUFx9qVw65YyqM78vyVe
+//!
+//! Derivation: m/84'/1'/0'
+//! C55B303F: tpubDC2Qwo2TFsaNC4ju8nrUJ9mqVT3eSgdmy1yPqhgkjwmke3PRXutNGRYAUo6RCHTcVQaDR3ohNU9we59brGHuEKPvH1ags2nevW5opEE9Z5Q
+//! "#,
+//! )?;
+//!
+//! let wallet = Wallet::new_no_persist(
+//!     setup.descriptor(KeychainKind::External)?,
+//!     Some(setup.descriptor(KeychainKind::Internal)?),
+//!     Network::Testnet,
+//! )?;
+//! # Ok::<_, Box<dyn std::error::Error>>(())
+//! ```
+
+use core::fmt;
+use core::str::FromStr;
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use bitcoin::bip32::{ChildNumber, DerivationPath, ExtendedPubKey, Fingerprint};
+use bitcoin::psbt::{self, PartiallySignedTransaction as Psbt};
+use bitcoin::secp256k1::Secp256k1;
+use bitcoin::PublicKey;
+use miniscript::descriptor::{DescriptorPublicKey, DescriptorXKey, Wildcard};
+use miniscript::Descriptor;
+
+use crate::collections::BTreeSet;
+use crate::descriptor::{DescriptorError, ExtendedDescriptor};
+use crate::types::KeychainKind;
+use crate::wallet::signer::verify_input_signatures;
+
+/// The script type of a multisig wallet
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MultisigScriptType {
+    /// Legacy `sh(sortedmulti(...))`
+    Sh,
+    /// Nested segwit `sh(wsh(sortedmulti(...)))`
+    ShWsh,
+    /// Native segwit `wsh(sortedmulti(...))`
+    Wsh,
+}
+
+impl MultisigScriptType {
+    fn format_name(&self) -> &'static str {
+        match self {
+            MultisigScriptType::Sh => "P2SH",
+            MultisigScriptType::ShWsh => "P2SH-P2WSH",
+            MultisigScriptType::Wsh => "P2WSH",
+        }
+    }
+
+    fn from_format_name(name: &str) -> Option<Self> {
+        match name.to_ascii_uppercase().as_str() {
+            "P2SH" => Some(MultisigScriptType::Sh),
+            "P2SH-P2WSH" | "P2WSH-P2SH" => Some(MultisigScriptType::ShWsh),
+            "P2WSH" => Some(MultisigScriptType::Wsh),
+            _ => None,
+        }
+    }
+}
+
+/// A participant of a multisig wallet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cosigner {
+    /// Fingerprint of the cosigner's master key
+    pub fingerprint: Fingerprint,
+    /// Derivation path from the master key to `xpub`
+    pub derivation_path: DerivationPath,
+    /// The account-level extended public key of the cosigner
+    pub xpub: ExtendedPubKey,
+}
+
+impl Cosigner {
+    fn descriptor_key(&self, keychain: KeychainKind) -> DescriptorPublicKey {
+        DescriptorPublicKey::XPub(DescriptorXKey {
+            origin: Some((self.fingerprint, self.derivation_path.clone())),
+            xkey: self.xpub,
+            derivation_path: vec![ChildNumber::Normal {
+                index: keychain as u32,
+            }]
+            .into(),
+            wildcard: Wildcard::Unhardened,
+        })
+    }
+
+    fn derive_public_key(&self, keychain: KeychainKind, index: u32) -> Option<PublicKey> {
+        let path = [
+            ChildNumber::from_normal_idx(keychain as u32).ok()?,
+            ChildNumber::from_normal_idx(index).ok()?,
+        ];
+        let xpub = self
+            .xpub
+            .derive_pub(&Secp256k1::verification_only(), &path)
+            .ok()?;
+        Some(PublicKey::new(xpub.public_key))
+    }
+}
+
+/// The configuration of a `sortedmulti` wallet shared by several [`Cosigner`]s
+///
+/// See the [module-level documentation](self) for more.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultisigSetup {
+    name: String,
+    threshold: usize,
+    script_type: MultisigScriptType,
+    cosigners: Vec<Cosigner>,
+}
+
+impl MultisigSetup {
+    /// Create a `threshold`-of-`cosigners.len()` setup
+    ///
+    /// This fails if the threshold is zero or larger than the number of cosigners, if two
+    /// cosigners share the same fingerprint, or if there are more cosigners than `sortedmulti`
+    /// allows.
+    pub fn new(
+        name: &str,
+        threshold: usize,
+        script_type: MultisigScriptType,
+        cosigners: Vec<Cosigner>,
+    ) -> Result<Self, MultisigError> {
+        if threshold == 0 || threshold > cosigners.len() {
+            return Err(MultisigError::InvalidThreshold {
+                threshold,
+                cosigners: cosigners.len(),
+            });
+        }
+        let mut fingerprints = BTreeSet::new();
+        if let Some(cosigner) = cosigners
+            .iter()
+            .find(|c| !fingerprints.insert(c.fingerprint))
+        {
+            return Err(MultisigError::DuplicateCosigner(cosigner.fingerprint));
+        }
+
+        let setup = MultisigSetup {
+            name: name.to_string(),
+            threshold,
+            script_type,
+            cosigners,
+        };
+        // Let miniscript check the number of keys for this script type
+        setup.descriptor(KeychainKind::External)?;
+
+        Ok(setup)
+    }
+
+    /// Return the name of the wallet
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Return the number of signatures required to spend
+    pub fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    /// Return the script type of the wallet
+    pub fn script_type(&self) -> MultisigScriptType {
+        self.script_type
+    }
+
+    /// Return the cosigners of the wallet
+    pub fn cosigners(&self) -> &[Cosigner] {
+        &self.cosigners
+    }
+
+    /// Return the descriptor for `keychain`
+    ///
+    /// Every key is derived at `<xpub>/0/*` for [`KeychainKind::External`] and `<xpub>/1/*` for
+    /// [`KeychainKind::Internal`].
+    pub fn descriptor(&self, keychain: KeychainKind) -> Result<ExtendedDescriptor, MultisigError> {
+        let keys = self
+            .cosigners
+            .iter()
+            .map(|c| c.descriptor_key(keychain))
+            .collect();
+        let descriptor = match self.script_type {
+            MultisigScriptType::Sh => Descriptor::new_sh_sortedmulti(self.threshold, keys),
+            MultisigScriptType::ShWsh => Descriptor::new_sh_wsh_sortedmulti(self.threshold, keys),
+            MultisigScriptType::Wsh => Descriptor::new_wsh_sortedmulti(self.threshold, keys),
+        };
+
+        descriptor.map_err(|e| MultisigError::Descriptor(DescriptorError::Miniscript(e)))
+    }
+
+    /// Check that every input of `psbt` spends from this wallet, and that all of its partial
+    /// signatures were made by one of the cosigners
+    ///
+    /// The inputs are matched using their BIP32 derivations and must contain the previous
+    /// output being spent. This is meant to be called on the PSBTs received back from the
+    /// cosigners, before combining them. Note that the signatures themselves are not validated.
+    pub fn verify_psbt(&self, psbt: &Psbt) -> Result<(), MultisigError> {
+        for (index, (input, txin)) in psbt.inputs.iter().zip(&psbt.unsigned_tx.input).enumerate() {
+            let (keychain, child) = self
+                .input_derivation(input)
+                .ok_or(MultisigError::ForeignInput(index))?;
+            let expected = self
+                .descriptor(keychain)?
+                .at_derivation_index(child)
+                .expect("the index is not hardened")
+                .script_pubkey();
+
+            let prev_output = match (&input.witness_utxo, &input.non_witness_utxo) {
+                (Some(txout), _) => txout,
+                (None, Some(tx)) => tx
+                    .output
+                    .get(txin.previous_output.vout as usize)
+                    .ok_or(MultisigError::MissingUtxo(index))?,
+                (None, None) => return Err(MultisigError::MissingUtxo(index)),
+            };
+            if prev_output.script_pubkey != expected {
+                return Err(MultisigError::ForeignInput(index));
+            }
+
+            let keys = self
+                .cosigners
+                .iter()
+                .filter_map(|c| c.derive_public_key(keychain, child))
+                .collect::<BTreeSet<_>>();
+            if let Some(key) = input.partial_sigs.keys().find(|key| !keys.contains(key)) {
+                return Err(MultisigError::ForeignSignature {
+                    input: index,
+                    key: *key,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Return, for every input of `psbt`, the fingerprints of the cosigners that signed it
+    ///
+    /// Inputs that don't belong to this wallet, or that are already finalized, return an empty
+    /// set.
+    pub fn signers(&self, psbt: &Psbt) -> Vec<BTreeSet<Fingerprint>> {
+        psbt.inputs
+            .iter()
+            .map(|input| {
+                let (keychain, child) = match self.input_derivation(input) {
+                    Some(derivation) => derivation,
+                    None => return BTreeSet::new(),
+                };
+                self.cosigners
+                    .iter()
+                    .filter(|c| {
+                        c.derive_public_key(keychain, child)
+                            .map_or(false, |key| input.partial_sigs.contains_key(&key))
+                    })
+                    .map(|c| c.fingerprint)
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Return the cosigners that still haven't signed at least one of the inputs of `psbt`
+    pub fn missing_signers(&self, psbt: &Psbt) -> Vec<Fingerprint> {
+        let signers = self.signers(psbt);
+        self.cosigners
+            .iter()
+            .map(|c| c.fingerprint)
+            .filter(|fingerprint| signers.iter().any(|s| !s.contains(fingerprint)))
+            .collect()
+    }
+
+    /// Return whether every input of `psbt` is finalized or has at least
+    /// [`threshold`](Self::threshold) signatures
+    ///
+    /// The signatures are verified against the spent outputs in `psbt`: an input with an invalid
+    /// signature, or without the previous output, isn't signed.
+    pub fn is_fully_signed(&self, psbt: &Psbt) -> bool {
+        let secp = Secp256k1::new();
+        let is_witness = self.script_type != MultisigScriptType::Sh;
+        // the verification overwrites the sighash type of the inputs
+        let mut scratch = psbt.clone();
+        psbt.inputs
+            .iter()
+            .zip(self.signers(psbt))
+            .enumerate()
+            .all(|(index, (input, signers))| {
+                input.final_script_sig.is_some()
+                    || input.final_script_witness.is_some()
+                    || (signers.len() >= self.threshold
+                        && verify_input_signatures(&mut scratch, index, is_witness, &secp)
+                            .unwrap_or(false))
+            })
+    }
+
+    fn input_derivation(&self, input: &psbt::Input) -> Option<(KeychainKind, u32)> {
+        input
+            .bip32_derivation
+            .values()
+            .find_map(|(fingerprint, path)| {
+                let cosigner = self
+                    .cosigners
+                    .iter()
+                    .find(|c| c.fingerprint == *fingerprint)?;
+                let origin: &[ChildNumber] = cosigner.derivation_path.as_ref();
+                let path: &[ChildNumber] = path.as_ref();
+                let origin_len = origin.len();
+                if path.len() != origin_len + 2 || !path.starts_with(origin) {
+                    return None;
+                }
+                let keychain = match path[origin_len] {
+                    ChildNumber::Normal { index: 0 } => KeychainKind::External,
+                    ChildNumber::Normal { index: 1 } => KeychainKind::Internal,
+                    _ => return None,
+                };
+                match path[origin_len + 1] {
+                    ChildNumber::Normal { index } => Some((keychain, index)),
+                    ChildNumber::Hardened { .. } => None,
+                }
+            })
+    }
+}
+
+impl fmt::Display for MultisigSetup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# Multisig setup file")?;
+        writeln!(f, "#")?;
+        writeln!(f, "Name: {}", self.name)?;
+        writeln!(f, "Policy: {} of {}", self.threshold, self.cosigners.len())?;
+
+        // A single `Derivation` line is enough when all the cosigners use the same path,
+        // otherwise it has to be repeated before every key
+        let common_path = self
+            .cosigners
+            .iter()
+            .all(|c| c.derivation_path == self.cosigners[0].derivation_path);
+        if common_path {
+            writeln!(f, "Derivation: {}", self.cosigners[0].derivation_path)?;
+        }
+        writeln!(f, "Format: {}", self.script_type.format_name())?;
+
+        for cosigner in &self.cosigners {
+            writeln!(f)?;
+            if !common_path {
+                writeln!(f, "Derivation: {}", cosigner.derivation_path)?;
+            }
+            write!(
+                f,
+                "{}: {}",
+                cosigner.fingerprint.to_string().to_uppercase(),
+                cosigner.xpub
+            )?;
+        }
+
+        writeln!(f)
+    }
+}
+
+impl FromStr for MultisigSetup {
+    type Err = MultisigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut name = None;
+        let mut policy = None;
+        let mut derivation_path = None;
+        let mut script_type = MultisigScriptType::Sh;
+        let mut cosigners = Vec::new();
+
+        for (number, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid_line = || MultisigError::InvalidSetupFile(number + 1);
+            let (key, value) = line.split_once(':').ok_or_else(invalid_line)?;
+            let value = value.trim();
+
+            match key.trim().to_ascii_lowercase().as_str() {
+                "name" => name = Some(value.to_string()),
+                "policy" => {
+                    let (m, n) = value.split_once("of").ok_or_else(invalid_line)?;
+                    let m = usize::from_str(m.trim()).map_err(|_| invalid_line())?;
+                    let n = usize::from_str(n.trim()).map_err(|_| invalid_line())?;
+                    policy = Some((m, n));
+                }
+                "derivation" => {
+                    derivation_path =
+                        Some(DerivationPath::from_str(value).map_err(|_| invalid_line())?)
+                }
+                "format" => {
+                    script_type =
+                        MultisigScriptType::from_format_name(value).ok_or_else(invalid_line)?
+                }
+                fingerprint => {
+                    let fingerprint =
+                        Fingerprint::from_str(fingerprint).map_err(|_| invalid_line())?;
+                    let xpub = ExtendedPubKey::from_str(value).map_err(|_| invalid_line())?;
+                    cosigners.push(Cosigner {
+                        fingerprint,
+                        derivation_path: derivation_path.clone().ok_or_else(invalid_line)?,
+                        xpub,
+                    });
+                }
+            }
+        }
+
+        let name = name.ok_or(MultisigError::MissingField("Name"))?;
+        let (threshold, total) = policy.ok_or(MultisigError::MissingField("Policy"))?;
+        if total != cosigners.len() {
+            return Err(MultisigError::InvalidThreshold {
+                threshold,
+                cosigners: cosigners.len(),
+            });
+        }
+
+        MultisigSetup::new(&name, threshold, script_type, cosigners)
+    }
+}
+
+/// Errors related to [`MultisigSetup`]
+#[derive(Debug)]
+pub enum MultisigError {
+    /// The threshold is zero, larger than the number of cosigners, or the policy doesn't match
+    /// the number of keys
+    InvalidThreshold {
+        /// The number of signatures required
+        threshold: usize,
+        /// The number of cosigners
+        cosigners: usize,
+    },
+    /// Two cosigners have the same fingerprint
+    DuplicateCosigner(Fingerprint),
+    /// The descriptor couldn't be built
+    Descriptor(DescriptorError),
+    /// The setup file is invalid at this line
+    InvalidSetupFile(usize),
+    /// A required field is missing from the setup file
+    MissingField(&'static str),
+    /// The input at this index doesn't spend from the multisig wallet
+    ForeignInput(usize),
+    /// The input at this index doesn't contain the output it spends
+    MissingUtxo(usize),
+    /// An input was signed by a key that doesn't belong to any cosigner
+    ForeignSignature {
+        /// Index of the input
+        input: usize,
+        /// The key of the signature
+        key: PublicKey,
+    },
+}
+
+impl fmt::Display for MultisigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidThreshold {
+                threshold,
+                cosigners,
+            } => write!(
+                f,
+                "Invalid threshold: {} of {} cosigners",
+                threshold, cosigners
+            ),
+            Self::DuplicateCosigner(fingerprint) => {
+                write!(f, "Duplicate cosigner: {}", fingerprint)
+            }
+            Self::Descriptor(err) => write!(f, "Descriptor error: {}", err),
+            Self::InvalidSetupFile(line) => write!(f, "Invalid setup file at line {}", line),
+            Self::MissingField(field) => write!(f, "Missing field in setup file: {}", field),
+            Self::ForeignInput(index) => {
+                write!(f, "Input {} doesn't spend from the multisig wallet", index)
+            }
+            Self::MissingUtxo(index) => write!(f, "Missing UTXO for input {}", index),
+            Self::ForeignSignature { input, key } => write!(
+                f,
+                "Input {} was signed by a key that doesn't belong to any cosigner: {}",
+                input, key
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for MultisigError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bitcoin::bip32::ExtendedPrivKey;
+    use bitcoin::Network;
+
+    fn cosigner(seed: u8) -> Cosigner {
+        let secp = Secp256k1::new();
+        let master = ExtendedPrivKey::new_master(Network::Testnet, &[seed; 32]).unwrap();
+        let derivation_path = DerivationPath::from_str("m/48'/1'/0'/2'").unwrap();
+        let xprv = master.derive_priv(&secp, &derivation_path).unwrap();
+        Cosigner {
+            fingerprint: master.fingerprint(&secp),
+            derivation_path,
+            xpub: ExtendedPubKey::from_priv(&secp, &xprv),
+        }
+    }
+
+    #[test]
+    fn test_multisig_setup_file_roundtrip() {
+        let setup = MultisigSetup::new(
+            "Savings",
+            2,
+            MultisigScriptType::Wsh,
+            vec![cosigner(1), cosigner(2), cosigner(3)],
+        )
+        .unwrap();
+
+        let file = setup.to_string();
+        assert!(file.contains("Policy: 2 of 3\nDerivation: m/48'/1'/0'/2'\nFormat: P2WSH\n"));
+        assert_eq!(MultisigSetup::from_str(&file).unwrap(), setup);
+
+        let descriptor = setup.descriptor(KeychainKind::Internal).unwrap();
+        assert!(descriptor.to_string().starts_with("wsh(sortedmulti(2,["));
+        assert!(descriptor.to_string().contains("/1/*"));
+    }
+
+    #[test]
+    fn test_multisig_setup_per_key_derivation() {
+        let mut other = cosigner(2);
+        other.derivation_path = DerivationPath::from_str("m/45'").unwrap();
+        let setup = MultisigSetup::new(
+            "Mixed",
+            1,
+            MultisigScriptType::ShWsh,
+            vec![cosigner(1), other],
+        )
+        .unwrap();
+
+        let file = setup.to_string();
+        assert_eq!(file.matches("Derivation:").count(), 2);
+        assert_eq!(MultisigSetup::from_str(&file).unwrap(), setup);
+    }
+
+    #[test]
+    fn test_multisig_setup_errors() {
+        assert!(matches!(
+            MultisigSetup::new(
+                "",
+                3,
+                MultisigScriptType::Wsh,
+                vec![cosigner(1), cosigner(2)]
+            ),
+            Err(MultisigError::InvalidThreshold {
+                threshold: 3,
+                cosigners: 2
+            })
+        ));
+        assert!(matches!(
+            MultisigSetup::new(
+                "",
+                1,
+                MultisigScriptType::Wsh,
+                vec![cosigner(1), cosigner(1)]
+            ),
+            Err(MultisigError::DuplicateCosigner(_))
+        ));
+        assert!(matches!(
+            MultisigSetup::from_str("Name: x\nPolicy: 1 of 1\nFormat: P2WSH\n"),
+            Err(MultisigError::InvalidThreshold { .. })
+        ));
+        assert!(matches!(
+            MultisigSetup::from_str("Name: x\nPolicy: 1 of 1\nFormat: P2TR\n"),
+            Err(MultisigError::InvalidSetupFile(3))
+        ));
+    }
+}
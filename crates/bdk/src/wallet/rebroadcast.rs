@@ -0,0 +1,238 @@
+// Bitcoin Dev Kit
+//
+// Copyright (c) 2020-2021 Bitcoin Dev Kit Developers
+//
+// This file is licensed under the Apache License, Version 2.0 <LICENSE-APACHE
+// or http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your option.
+// You may not use this file except in accordance with one or both of these
+// licenses.
+
+//! Rebroadcast of pending transactions
+//!
+//! This module provides [`RebroadcastScheduler`], which finds the unconfirmed transactions sent
+//! by a [`Wallet`] that haven't been seen or broadcast for a while, and broadcasts them again
+//! with any chain source. The number of times every transaction was rebroadcast is persisted.
+//!
+//! ## Example
+//!
+//! ```
+//! # use bdk::wallet::rebroadcast::RebroadcastScheduler;
+//! # let wallet = bdk::doctest_wallet!();
+//! # let now = 100_000;
+//! // rebroadcast the transactions not seen in the last hour, with their unconfirmed ancestors
+//! let mut scheduler = RebroadcastScheduler::new(3600, true, ())?;
+//!
+//! // call this periodically, after syncing the wallet
+//! let rebroadcast = scheduler.rebroadcast_pending(&wallet, now, |_tx| {
+//!     // e.g. `esplora_client.broadcast(tx)`
+//!     Ok::<_, core::convert::Infallible>(())
+//! })?;
+//! # Ok::<_, Box<dyn std::error::Error>>(())
+//! ```
+
+use crate::collections::{BTreeMap, BTreeSet};
+use alloc::vec::Vec;
+use core::fmt;
+
+use bdk_chain::{ChainPosition, Persist, PersistBackend};
+use bitcoin::{Transaction, Txid};
+use serde::{Deserialize, Serialize};
+
+use crate::wallet::Wallet;
+
+/// The rebroadcasts of a transaction
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RebroadcastInfo {
+    /// Number of times the transaction was rebroadcast
+    pub count: u32,
+    /// Unix timestamp of the last rebroadcast
+    pub last_broadcast: u64,
+}
+
+/// The changeset produced by a [`RebroadcastScheduler`].
+///
+/// Maps every transaction to its latest [`RebroadcastInfo`].
+pub type ChangeSet = BTreeMap<Txid, RebroadcastInfo>;
+
+/// Schedules the rebroadcast of the pending transactions of a [`Wallet`].
+///
+/// See the [module-level documentation](self) for more.
+#[derive(Debug)]
+pub struct RebroadcastScheduler<D = ()> {
+    min_interval: u64,
+    include_ancestors: bool,
+    rebroadcasts: ChangeSet,
+    persist: Persist<D, ChangeSet>,
+}
+
+impl<D> RebroadcastScheduler<D>
+where
+    D: PersistBackend<ChangeSet>,
+{
+    /// Create a scheduler rebroadcasting the transactions that weren't seen in the mempool nor
+    /// broadcast for `min_interval` seconds, loading the previous rebroadcasts from `db`.
+    ///
+    /// If `include_ancestors` is true, the unconfirmed ancestors of a transaction are
+    /// rebroadcast with it, even if they weren't sent by the wallet.
+    pub fn new(
+        min_interval: u64,
+        include_ancestors: bool,
+        mut db: D,
+    ) -> Result<Self, D::LoadError> {
+        let rebroadcasts = db.load_from_persistence()?.unwrap_or_default();
+        Ok(Self {
+            min_interval,
+            include_ancestors,
+            rebroadcasts,
+            persist: Persist::new(db),
+        })
+    }
+
+    /// Returns the rebroadcasts of `txid`, if it was ever rebroadcast
+    pub fn rebroadcast_info(&self, txid: &Txid) -> Option<RebroadcastInfo> {
+        self.rebroadcasts.get(txid).copied()
+    }
+
+    /// Return the transactions that should be rebroadcast at the unix timestamp `now`, parents
+    /// first.
+    ///
+    /// These are the unconfirmed transactions sent by the wallet that were last seen in the
+    /// mempool, or last rebroadcast, at least `min_interval` seconds ago.
+    pub fn pending<W>(&self, wallet: &Wallet<W>, now: u64) -> Vec<Transaction> {
+        let mut txids = Vec::new();
+        for canonical_tx in wallet.transactions() {
+            let last_seen = match canonical_tx.chain_position {
+                ChainPosition::Unconfirmed(last_seen) => last_seen,
+                ChainPosition::Confirmed(_) => continue,
+            };
+            let txid = canonical_tx.tx_node.txid;
+            let last_broadcast = self
+                .rebroadcast_info(&txid)
+                .map_or(0, |info| info.last_broadcast);
+            if now.saturating_sub(last_seen.max(last_broadcast)) < self.min_interval
+                || wallet.sent_and_received(canonical_tx.tx_node.tx).0 == 0
+            {
+                continue;
+            }
+
+            if self.include_ancestors {
+                // ancestors are returned from the closest, broadcast the furthest first
+                for ancestor in wallet.unconfirmed_ancestors(txid).into_iter().rev() {
+                    if !txids.contains(&ancestor) {
+                        txids.push(ancestor);
+                    }
+                }
+            }
+            if !txids.contains(&txid) {
+                txids.push(txid);
+            }
+        }
+
+        let txs = txids
+            .into_iter()
+            .filter_map(|txid| wallet.tx_graph().get_tx(txid).cloned())
+            .collect();
+        sort_topologically(txs)
+    }
+
+    /// Record that `txids` were rebroadcast at the unix timestamp `now`, and persist it
+    pub fn record_broadcast(
+        &mut self,
+        txids: impl IntoIterator<Item = Txid>,
+        now: u64,
+    ) -> Result<(), D::WriteError> {
+        let mut changeset = ChangeSet::new();
+        for txid in txids {
+            let count = self.rebroadcast_info(&txid).map_or(0, |info| info.count);
+            let info = RebroadcastInfo {
+                count: count + 1,
+                last_broadcast: now,
+            };
+            self.rebroadcasts.insert(txid, info);
+            changeset.insert(txid, info);
+        }
+        self.persist.stage(changeset);
+        self.persist.commit()?;
+
+        Ok(())
+    }
+
+    /// Broadcast the [`pending`](Self::pending) transactions with `broadcast`, record the
+    /// rebroadcasts and return the transactions that were rebroadcast.
+    ///
+    /// Stops at the first transaction `broadcast` fails for. The transactions broadcast before
+    /// it are still recorded.
+    pub fn rebroadcast_pending<W, F, E>(
+        &mut self,
+        wallet: &Wallet<W>,
+        now: u64,
+        mut broadcast: F,
+    ) -> Result<Vec<Txid>, RebroadcastError<E, D::WriteError>>
+    where
+        F: FnMut(&Transaction) -> Result<(), E>,
+    {
+        let mut rebroadcast = Vec::new();
+        let mut result = Ok(());
+        for tx in self.pending(wallet, now) {
+            if let Err(e) = broadcast(&tx) {
+                result = Err(RebroadcastError::Broadcast(e));
+                break;
+            }
+            rebroadcast.push(tx.txid());
+        }
+
+        self.record_broadcast(rebroadcast.iter().copied(), now)
+            .map_err(RebroadcastError::Persist)?;
+        result.map(|_| rebroadcast)
+    }
+}
+
+/// Sort `txs` so that every transaction comes after the ones it spends from, as the wallet's
+/// transactions aren't returned in any particular order
+fn sort_topologically(mut txs: Vec<Transaction>) -> Vec<Transaction> {
+    let mut remaining = txs.iter().map(|tx| tx.txid()).collect::<BTreeSet<_>>();
+    let mut sorted = Vec::with_capacity(txs.len());
+    while !txs.is_empty() {
+        let (ready, blocked): (Vec<_>, Vec<_>) = txs.into_iter().partition(|tx| {
+            tx.input
+                .iter()
+                .all(|txin| !remaining.contains(&txin.previous_output.txid))
+        });
+        // valid transactions can't spend from each other in a cycle
+        if ready.is_empty() {
+            sorted.extend(blocked);
+            break;
+        }
+        for tx in &ready {
+            remaining.remove(&tx.txid());
+        }
+        sorted.extend(ready);
+        txs = blocked;
+    }
+    sorted
+}
+
+/// Errors returned by [`RebroadcastScheduler::rebroadcast_pending`]
+#[derive(Debug)]
+pub enum RebroadcastError<B, W> {
+    /// Error broadcasting a transaction
+    Broadcast(B),
+    /// Error persisting the rebroadcasts
+    Persist(W),
+}
+
+impl<B: fmt::Display, W: fmt::Display> fmt::Display for RebroadcastError<B, W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Broadcast(e) => write!(f, "Failed to broadcast transaction: {}", e),
+            Self::Persist(e) => write!(f, "Failed to persist the rebroadcasts: {}", e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<B: fmt::Display + fmt::Debug, W: fmt::Display + fmt::Debug> std::error::Error
+    for RebroadcastError<B, W>
+{
+}
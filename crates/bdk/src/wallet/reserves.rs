@@ -0,0 +1,231 @@
+// Bitcoin Dev Kit
+//
+// Copyright (c) 2020-2021 Bitcoin Dev Kit Developers
+//
+// This file is licensed under the Apache License, Version 2.0 <LICENSE-APACHE
+// or http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your option.
+// You may not use this file except in accordance with one or both of these
+// licenses.
+
+//! Proof of reserves
+//!
+//! This module implements the proof of reserves format described in
+//! [BIP127](https://github.com/bitcoin/bips/blob/master/bip-0127.mediawiki): a transaction
+//! spending the UTXOs of the wallet plus a "challenge" input that commits to a message. The
+//! challenge input can't exist, which makes the transaction invalid and the proof safe to share,
+//! while the signatures of the other inputs prove that the wallet controls them.
+//!
+//! Proofs are created with [`Wallet::create_proof`](crate::Wallet::create_proof) and verified
+//! with [`Wallet::verify_proof`](crate::Wallet::verify_proof), using the UTXOs known by a
+//! (possibly watch-only) wallet, or with [`verify_proof`], using any other source of UTXOs.
+//!
+//! ## Example
+//!
+//! ```
+//! # use bdk::*;
+//! # let wallet = doctest_wallet!();
+//! let message = "Reserves as of today";
+//! let proof = wallet.create_proof(message)?;
+//!
+//! // an auditor with a watch-only copy of the wallet
+//! let reserves = wallet.verify_proof(&proof, message, None)?;
+//! assert_eq!(reserves, wallet.get_balance().total());
+//! # Ok::<_, anyhow::Error>(())
+//! ```
+
+use core::fmt;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::collections::BTreeSet;
+
+use bitcoin::blockdata::opcodes::OP_TRUE;
+use bitcoin::blockdata::script::Builder;
+use bitcoin::hashes::{hash160, sha256d, Hash};
+use bitcoin::psbt::{self, PartiallySignedTransaction as Psbt};
+use bitcoin::secp256k1::Secp256k1;
+use bitcoin::sighash::{EcdsaSighashType, Prevouts, TapSighashType};
+use bitcoin::{OutPoint, PubkeyHash, ScriptBuf, TxIn, TxOut, Txid};
+use miniscript::interpreter::{Interpreter, KeySigPair, SatisfiedConstraint};
+
+const CHALLENGE_PREFIX: &str = "Proof-of-Reserves: ";
+
+/// Return the challenge input committing to `message`
+pub fn challenge_txin(message: &str) -> TxIn {
+    let mut preimage = String::from(CHALLENGE_PREFIX);
+    preimage.push_str(message);
+    let hash = sha256d::Hash::hash(preimage.as_bytes());
+
+    TxIn {
+        previous_output: OutPoint::new(Txid::from_raw_hash(hash), 0),
+        ..Default::default()
+    }
+}
+
+/// The PSBT input of the challenge, already finalized with an empty script
+pub(crate) fn challenge_psbt_input() -> psbt::Input {
+    psbt::Input {
+        witness_utxo: Some(TxOut {
+            value: 0,
+            script_pubkey: Builder::new().push_opcode(OP_TRUE).into_script(),
+        }),
+        final_script_sig: Some(ScriptBuf::new()),
+        ..Default::default()
+    }
+}
+
+/// The unspendable script receiving the funds of a proof
+pub fn proof_script_pubkey() -> ScriptBuf {
+    ScriptBuf::new_p2pkh(&PubkeyHash::from_raw_hash(hash160::Hash::hash(&[0])))
+}
+
+/// Verify a proof of reserves for `message`, and return the amount it proves
+///
+/// `get_utxo` must return the outputs spent by the proof, as long as they are unspent and
+/// should be counted (for example, only if they were confirmed before a certain height). The
+/// signatures of every input are checked with the miniscript interpreter, so all the scripts
+/// must be representable as miniscript, and must all use `SIGHASH_ALL` (or `SIGHASH_DEFAULT`
+/// for taproot).
+pub fn verify_proof<F>(psbt: &Psbt, message: &str, mut get_utxo: F) -> Result<u64, ProofError>
+where
+    F: FnMut(&OutPoint) -> Option<TxOut>,
+{
+    let tx = &psbt.unsigned_tx;
+    if tx.input.len() < 2 || psbt.inputs.len() != tx.input.len() {
+        return Err(ProofError::WrongNumberOfInputs);
+    }
+    if tx.input[0].previous_output != challenge_txin(message).previous_output {
+        return Err(ProofError::ChallengeInputMismatch);
+    }
+    if tx.output.len() != 1 {
+        return Err(ProofError::WrongNumberOfOutputs);
+    }
+    if tx.output[0].script_pubkey != proof_script_pubkey() {
+        return Err(ProofError::InvalidOutput);
+    }
+    // Spending the same output twice would count it twice
+    let mut outpoints = BTreeSet::new();
+    if let Some(index) = tx
+        .input
+        .iter()
+        .position(|txin| !outpoints.insert(txin.previous_output))
+    {
+        return Err(ProofError::DuplicateInput(index));
+    }
+
+    let mut prevouts = vec![challenge_psbt_input()
+        .witness_utxo
+        .expect("the challenge has a witness utxo")];
+    for (index, txin) in tx.input.iter().enumerate().skip(1) {
+        prevouts.push(get_utxo(&txin.previous_output).ok_or(ProofError::NonSpendableInput(index))?);
+    }
+    let reserves = prevouts.iter().map(|txout| txout.value).sum::<u64>();
+    if reserves != tx.output[0].value {
+        return Err(ProofError::InAndOutValueNotEqual);
+    }
+
+    let signed_tx = psbt.clone().extract_tx();
+    let secp = Secp256k1::verification_only();
+    let prevouts_all = Prevouts::All(&prevouts);
+    for (index, txin) in signed_tx.input.iter().enumerate().skip(1) {
+        let interpreter = Interpreter::from_txdata(
+            &prevouts[index].script_pubkey,
+            &txin.script_sig,
+            &txin.witness,
+            txin.sequence,
+            signed_tx.lock_time,
+        )
+        .map_err(|e| ProofError::SignatureValidation(index, e))?;
+        let satisfied = interpreter
+            .iter(&secp, &signed_tx, index, &prevouts_all)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| ProofError::SignatureValidation(index, e))?;
+        let key_sigs = satisfied
+            .iter()
+            .filter_map(|constraint| match constraint {
+                SatisfiedConstraint::PublicKey { key_sig }
+                | SatisfiedConstraint::PublicKeyHash { key_sig, .. } => Some(key_sig),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        if key_sigs.is_empty() {
+            return Err(ProofError::NotSigned(index));
+        }
+        // Any other sighash would let the signatures be reused for a different transaction
+        let sighash_all = key_sigs.iter().all(|key_sig| match key_sig {
+            KeySigPair::Ecdsa(_, sig) => sig.hash_ty == EcdsaSighashType::All,
+            KeySigPair::Schnorr(_, sig) => {
+                matches!(sig.hash_ty, TapSighashType::All | TapSighashType::Default)
+            }
+        });
+        if !sighash_all {
+            return Err(ProofError::UnsupportedSighashType(index));
+        }
+    }
+
+    Ok(reserves)
+}
+
+/// Errors returned when creating or verifying a proof of reserves
+#[derive(Debug)]
+pub enum ProofError {
+    /// The proof should spend the challenge and at least one other input
+    WrongNumberOfInputs,
+    /// The first input isn't the challenge for the message
+    ChallengeInputMismatch,
+    /// The proof should have exactly one output
+    WrongNumberOfOutputs,
+    /// The output of the proof isn't the unspendable [`proof_script_pubkey`]
+    InvalidOutput,
+    /// The input at this index has a signature with a sighash type other than `ALL`, or
+    /// `DEFAULT` for taproot
+    UnsupportedSighashType(usize),
+    /// The input at this index spends the same outpoint as a previous input
+    DuplicateInput(usize),
+    /// The input at this index is spent, or unknown
+    NonSpendableInput(usize),
+    /// The value of the output doesn't match the value of the inputs
+    InAndOutValueNotEqual,
+    /// The input at this index isn't signed
+    NotSigned(usize),
+    /// The signature of the input at this index is invalid
+    SignatureValidation(usize, miniscript::interpreter::Error),
+    /// The wallet doesn't have any UTXO to prove
+    NoUtxos,
+    /// There was an error signing the proof
+    Signer(crate::signer::SignerError),
+}
+
+impl fmt::Display for ProofError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WrongNumberOfInputs => write!(f, "Wrong number of inputs"),
+            Self::ChallengeInputMismatch => write!(f, "Challenge input doesn't match the message"),
+            Self::WrongNumberOfOutputs => write!(f, "Wrong number of outputs"),
+            Self::InvalidOutput => write!(f, "Invalid output script"),
+            Self::UnsupportedSighashType(index) => {
+                write!(f, "Unsupported sighash type for input {}", index)
+            }
+            Self::DuplicateInput(index) => write!(f, "Input {} is spent twice", index),
+            Self::NonSpendableInput(index) => write!(f, "Input {} is not spendable", index),
+            Self::InAndOutValueNotEqual => write!(f, "Input and output values are not equal"),
+            Self::NotSigned(index) => write!(f, "Input {} is not signed", index),
+            Self::SignatureValidation(index, err) => {
+                write!(f, "Invalid signature for input {}: {}", index, err)
+            }
+            Self::NoUtxos => write!(f, "No UTXOs to prove"),
+            Self::Signer(err) => write!(f, "Error signing the proof: {}", err),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ProofError {}
+
+impl From<crate::signer::SignerError> for ProofError {
+    fn from(err: crate::signer::SignerError) -> Self {
+        ProofError::Signer(err)
+    }
+}
@@ -79,7 +79,8 @@
 //! # Ok::<_, anyhow::Error>(())
 //! ```
 
-use crate::collections::BTreeMap;
+use crate::collections::{BTreeMap, BTreeSet};
+use alloc::string::String;
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 use core::cmp::Ordering;
@@ -92,7 +93,10 @@ use bitcoin::secp256k1::Message;
 use bitcoin::sighash::{EcdsaSighashType, TapSighash, TapSighashType};
 use bitcoin::{ecdsa, psbt, sighash, taproot};
 use bitcoin::{key::TapTweak, key::XOnlyPublicKey, secp256k1};
-use bitcoin::{PrivateKey, PublicKey};
+use bitcoin::{PrivateKey, PublicKey, ScriptBuf, Txid};
+
+use bdk_chain::{Persist, PersistBackend};
+use serde::{Deserialize, Serialize};
 
 use miniscript::descriptor::{
     Descriptor, DescriptorMultiXKey, DescriptorPublicKey, DescriptorSecretKey, DescriptorXKey,
@@ -100,8 +104,8 @@ use miniscript::descriptor::{
 };
 use miniscript::{Legacy, Segwitv0, SigType, Tap, ToPublicKey};
 
-use super::utils::SecpCtx;
-use crate::descriptor::{DescriptorMeta, XKeyUtils};
+use super::utils::{derivation_from_key_origins, SecpCtx};
+use crate::descriptor::{DescriptorMeta, ExtendedDescriptor, XKeyUtils};
 use crate::psbt::PsbtUtils;
 use crate::wallet::error::MiniscriptPsbtError;
 
@@ -162,6 +166,33 @@ pub enum SignerError {
     SighashError(sighash::Error),
     /// Miniscript PSBT error
     MiniscriptPsbt(MiniscriptPsbtError),
+    /// The transaction sends more than allowed by the [`SpendingPolicy`] of a [`PolicySigner`]
+    SpendingLimitExceeded {
+        /// Maximum amount allowed by the policy
+        max: u64,
+        /// Amount sent by the transaction
+        sent: u64,
+    },
+    /// The transaction pays a recipient not allowed by the [`SpendingPolicy`] of a [`PolicySigner`]
+    RecipientNotAllowed(ScriptBuf),
+    /// The transaction would exceed the daily limit of the [`SpendingPolicy`] of a [`PolicySigner`]
+    DailyLimitExceeded {
+        /// Maximum amount allowed by the policy in a day
+        max: u64,
+        /// Amount sent by the transactions already signed today
+        spent_today: u64,
+        /// Amount sent by the transaction
+        sent: u64,
+    },
+    /// The [`PolicySigner`] failed to persist the transaction it signed, so the signatures were
+    /// discarded
+    SpendNotPersisted(String),
+    /// The [`SpendingPolicy`] of a [`PolicySigner`] has a daily limit, but no
+    /// [`clock`](PolicySigner::clock) was given to the signer
+    MissingClock,
+    /// The wallet is watch-only and couldn't finalize any input: there's no signer for the keys
+    /// with these master fingerprints
+    NoSigners(Vec<Fingerprint>),
     /// Error while signing using hardware wallets
     #[cfg(feature = "hardware-signer")]
     HWIError(hwi::error::Error),
@@ -196,6 +227,37 @@ impl fmt::Display for SignerError {
             Self::InvalidSighash => write!(f, "Invalid SIGHASH for the signing context in use"),
             Self::SighashError(err) => write!(f, "Error while computing the hash to sign: {}", err),
             Self::MiniscriptPsbt(err) => write!(f, "Miniscript PSBT error: {}", err),
+            Self::SpendingLimitExceeded { max, sent } => write!(
+                f,
+                "Spending limit exceeded: the transaction sends {} sat, the maximum is {} sat",
+                sent, max
+            ),
+            Self::RecipientNotAllowed(script) => {
+                write!(f, "Recipient not allowed by the spending policy: {}", script)
+            }
+            Self::DailyLimitExceeded {
+                max,
+                spent_today,
+                sent,
+            } => write!(
+                f,
+                "Daily spending limit exceeded: the transaction sends {} sat, {} sat were already sent today, the maximum is {} sat",
+                sent, spent_today, max
+            ),
+            Self::SpendNotPersisted(err) => {
+                write!(f, "Failed to persist the spending of the transaction: {}", err)
+            }
+            Self::MissingClock => write!(f, "A daily spending limit requires a clock"),
+            Self::NoSigners(fingerprints) => {
+                write!(f, "The wallet is watch-only, missing signers for: ")?;
+                for (i, fingerprint) in fingerprints.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", fingerprint)?;
+                }
+                Ok(())
+            }
             #[cfg(feature = "hardware-signer")]
             Self::HWIError(err) => write!(f, "Error while signing using hardware wallets: {}", err),
         }
@@ -306,6 +368,405 @@ impl<T: InputSigner> TransactionSigner for T {
     }
 }
 
+/// Limits enforced by a [`PolicySigner`] before signing a transaction
+#[derive(Debug, Clone, Default)]
+pub struct SpendingPolicy {
+    /// Maximum amount, in satoshis, that a single transaction can send to recipients. `None` means
+    /// no limit
+    pub max_amount_per_tx: Option<u64>,
+    /// Maximum amount, in satoshis, that the transactions signed during the same UTC day can send
+    /// to recipients. `None` means no limit
+    pub max_amount_per_day: Option<u64>,
+    /// Script pubkeys that transactions are allowed to pay. `None` allows any recipient
+    pub allowed_recipients: Option<BTreeSet<ScriptBuf>>,
+}
+
+/// A transaction signed by a [`PolicySigner`] enforcing a daily limit
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SignedSpend {
+    /// Unix timestamp of the signature
+    pub timestamp: u64,
+    /// Amount sent to recipients, in satoshis
+    pub amount: u64,
+}
+
+/// The changeset produced by a [`PolicySigner`].
+///
+/// Maps every transaction signed under a daily limit to its [`SignedSpend`].
+pub type SpendingChangeSet = BTreeMap<Txid, SignedSpend>;
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// The transactions signed by a [`PolicySigner`], counted towards its daily limit
+struct SpendingLedger<D> {
+    spends: SpendingChangeSet,
+    persist: Persist<D, SpendingChangeSet>,
+    now: Option<fn() -> u64>,
+}
+
+impl<D> fmt::Debug for SpendingLedger<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SpendingLedger")
+            .field("spends", &self.spends)
+            .finish()
+    }
+}
+
+/// Signer that refuses to sign transactions violating a [`SpendingPolicy`]
+///
+/// The checks are performed on the whole transaction before handing it over to the inner signer.
+/// Every output counts towards the limits, except the ones derived from one of the descriptors
+/// given to [`own_descriptor`](Self::own_descriptor): the key origins of an output in the
+/// PSBT are only trusted after deriving its script pubkey again.
+///
+/// To enforce [`SpendingPolicy::max_amount_per_day`], the signer keeps track of the transactions
+/// it signed. With [`with_ledger`](Self::with_ledger) they are persisted, so that the limit holds
+/// across restarts. The signer never reads the system clock: a daily limit requires a
+/// [`clock`](Self::clock) returning the current unix timestamp, and days are counted in UTC from
+/// it.
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use bdk::signer::*;
+/// # use bdk::*;
+/// # use bitcoin::secp256k1::{All, Secp256k1};
+/// # use bitcoin::Network;
+/// # let mut wallet = Wallet::new_no_persist("wpkh(tpubD6NzVbkrYhZ4Xferm7Pz4VnjdcDPFyjVu5K4iZXQ4pVN8Cks4pHVowTBXBKRhX64pkRyJZJN5xAKj4UDNnLPb5p2sSKXhewoYx5GbTdUFWq/0/*)", Some("wpkh(tpubD6NzVbkrYhZ4Xferm7Pz4VnjdcDPFyjVu5K4iZXQ4pVN8Cks4pHVowTBXBKRhX64pkRyJZJN5xAKj4UDNnLPb5p2sSKXhewoYx5GbTdUFWq/1/*)"), Network::Testnet)?;
+/// # #[derive(Debug)]
+/// # struct CustomSigner;
+/// # impl SignerCommon for CustomSigner {
+/// #     fn id(&self, _secp: &Secp256k1<All>) -> SignerId {
+/// #         SignerId::Dummy(42)
+/// #     }
+/// # }
+/// # impl TransactionSigner for CustomSigner {
+/// #     fn sign_transaction(&self, _psbt: &mut bitcoin::psbt::PartiallySignedTransaction, _sign_options: &SignOptions, _secp: &Secp256k1<All>) -> Result<(), SignerError> {
+/// #         Ok(())
+/// #     }
+/// # }
+/// # let db = ();
+/// let policy = SpendingPolicy {
+///     max_amount_per_tx: Some(100_000),
+///     max_amount_per_day: Some(500_000),
+///     ..Default::default()
+/// };
+/// let external_descriptor = wallet.get_descriptor_for_keychain(KeychainKind::External).clone();
+/// let internal_descriptor = wallet.get_descriptor_for_keychain(KeychainKind::Internal).clone();
+/// let signer = PolicySigner::with_ledger(CustomSigner, policy, db)?
+///     .clock(|| std::time::UNIX_EPOCH.elapsed().unwrap().as_secs())
+///     .own_descriptor(external_descriptor)
+///     .own_descriptor(internal_descriptor);
+/// wallet.add_signer(KeychainKind::External, SignerOrdering(200), Arc::new(signer));
+/// # Ok::<_, anyhow::Error>(())
+/// ```
+pub struct PolicySigner<S, D = ()> {
+    signer: S,
+    policy: SpendingPolicy,
+    descriptors: Vec<ExtendedDescriptor>,
+    ledger: spin::Mutex<SpendingLedger<D>>,
+}
+
+impl<S: TransactionSigner> PolicySigner<S> {
+    /// Wrap `signer` so that it only signs transactions allowed by `policy`
+    ///
+    /// The transactions counted towards the daily limit are only kept in memory.
+    pub fn new(signer: S, policy: SpendingPolicy) -> Self {
+        PolicySigner {
+            signer,
+            policy,
+            descriptors: Vec::new(),
+            ledger: spin::Mutex::new(SpendingLedger {
+                spends: SpendingChangeSet::new(),
+                persist: Persist::new(()),
+                now: None,
+            }),
+        }
+    }
+}
+
+impl<S, D> PolicySigner<S, D>
+where
+    S: TransactionSigner,
+    D: PersistBackend<SpendingChangeSet>,
+{
+    /// Wrap `signer` so that it only signs transactions allowed by `policy`, loading the
+    /// transactions counted towards the daily limit from `db` and persisting them there
+    pub fn with_ledger(signer: S, policy: SpendingPolicy, mut db: D) -> Result<Self, D::LoadError> {
+        let spends = db.load_from_persistence()?.unwrap_or_default();
+        Ok(PolicySigner {
+            signer,
+            policy,
+            descriptors: Vec::new(),
+            ledger: spin::Mutex::new(SpendingLedger {
+                spends,
+                persist: Persist::new(db),
+                now: None,
+            }),
+        })
+    }
+
+    /// Use `now` to get the current unix timestamp. Required to enforce
+    /// [`SpendingPolicy::max_amount_per_day`]
+    pub fn clock(self, now: fn() -> u64) -> Self {
+        self.ledger.lock().now = Some(now);
+        self
+    }
+
+    /// Return the transactions counted towards the daily limit
+    pub fn spends(&self) -> SpendingChangeSet {
+        self.ledger.lock().spends.clone()
+    }
+
+    /// Sign `psbt` with the inner signer, counting `sent` towards the daily limit if any signature
+    /// was added
+    ///
+    /// The inner signer works on a copy of `psbt`, which is only updated once the spend has been
+    /// persisted, so that a signature is never released without being counted. The ledger stays
+    /// locked in the meantime, so that concurrent signatures can't exceed the limit together.
+    fn sign_and_record(
+        &self,
+        psbt: &mut psbt::PartiallySignedTransaction,
+        sent: u64,
+        sign_options: &SignOptions,
+        secp: &SecpCtx,
+    ) -> Result<(), SignerError> {
+        let max = match self.policy.max_amount_per_day {
+            Some(max) => max,
+            None => return self.signer.sign_transaction(psbt, sign_options, secp),
+        };
+
+        let mut ledger = self.ledger.lock();
+        let txid = psbt.unsigned_tx.txid();
+        if ledger.spends.contains_key(&txid) {
+            return self.signer.sign_transaction(psbt, sign_options, secp);
+        }
+        let now = ledger.now.ok_or(SignerError::MissingClock)?;
+        let now = now();
+        let spent_today = ledger
+            .spends
+            .values()
+            .filter(|spend| spend.timestamp / SECONDS_PER_DAY == now / SECONDS_PER_DAY)
+            .map(|spend| spend.amount)
+            .sum::<u64>();
+        if spent_today.saturating_add(sent) > max {
+            return Err(SignerError::DailyLimitExceeded {
+                max,
+                spent_today,
+                sent,
+            });
+        }
+
+        let mut signed = psbt.clone();
+        self.signer
+            .sign_transaction(&mut signed, sign_options, secp)?;
+        if count_signatures(&signed) == count_signatures(psbt) {
+            *psbt = signed;
+            return Ok(());
+        }
+
+        // the spends of previous days don't count anymore
+        ledger
+            .spends
+            .retain(|_, spend| now.saturating_sub(spend.timestamp) < SECONDS_PER_DAY);
+        let spend = SignedSpend {
+            timestamp: now,
+            amount: sent,
+        };
+        ledger.persist.stage([(txid, spend)].into());
+        ledger
+            .persist
+            .commit()
+            .map_err(|e| SignerError::SpendNotPersisted(format!("{:?}", e)))?;
+        ledger.spends.insert(txid, spend);
+        *psbt = signed;
+
+        Ok(())
+    }
+}
+
+/// Count the signatures of all the inputs of `psbt`
+fn count_signatures(psbt: &psbt::PartiallySignedTransaction) -> usize {
+    psbt.inputs
+        .iter()
+        .map(|input| {
+            input.partial_sigs.len()
+                + input.tap_script_sigs.len()
+                + usize::from(input.tap_key_sig.is_some())
+        })
+        .sum()
+}
+
+impl<S, D> PolicySigner<S, D> {
+    /// Don't count the outputs derived from `descriptor`, one of the descriptors of the wallet, as
+    /// recipients. Should be called for every keychain of the wallet, so that neither the change
+    /// nor the payments to the wallet itself count towards the limits
+    pub fn own_descriptor(mut self, descriptor: ExtendedDescriptor) -> Self {
+        self.descriptors.push(descriptor);
+        self
+    }
+
+    /// Return the policy enforced by this signer
+    pub fn policy(&self) -> &SpendingPolicy {
+        &self.policy
+    }
+
+    /// Check the per-transaction limits and return the amount sent to recipients
+    fn check_policy(&self, psbt: &psbt::PartiallySignedTransaction) -> Result<u64, SignerError> {
+        let mut sent = 0u64;
+        for (txout, output) in psbt.unsigned_tx.output.iter().zip(psbt.outputs.iter()) {
+            let is_own = self.descriptors.iter().any(|descriptor| {
+                derivation_from_key_origins(descriptor, output, &txout.script_pubkey).is_some()
+            });
+            if is_own {
+                continue;
+            }
+
+            if let Some(allowed_recipients) = &self.policy.allowed_recipients {
+                if !allowed_recipients.contains(&txout.script_pubkey) {
+                    return Err(SignerError::RecipientNotAllowed(
+                        txout.script_pubkey.clone(),
+                    ));
+                }
+            }
+            sent = sent.saturating_add(txout.value);
+        }
+
+        match self.policy.max_amount_per_tx {
+            Some(max) if sent > max => Err(SignerError::SpendingLimitExceeded { max, sent }),
+            _ => Ok(sent),
+        }
+    }
+}
+
+impl<S: fmt::Debug, D> fmt::Debug for PolicySigner<S, D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PolicySigner")
+            .field("signer", &self.signer)
+            .field("policy", &self.policy)
+            .field("descriptors", &self.descriptors)
+            .field("ledger", &self.ledger)
+            .finish()
+    }
+}
+
+impl<S, D> SignerCommon for PolicySigner<S, D>
+where
+    S: TransactionSigner,
+    D: PersistBackend<SpendingChangeSet> + Send,
+{
+    fn id(&self, secp: &SecpCtx) -> SignerId {
+        self.signer.id(secp)
+    }
+
+    fn descriptor_secret_key(&self) -> Option<DescriptorSecretKey> {
+        self.signer.descriptor_secret_key()
+    }
+}
+
+impl<S, D> TransactionSigner for PolicySigner<S, D>
+where
+    S: TransactionSigner,
+    D: PersistBackend<SpendingChangeSet> + Send,
+{
+    fn sign_transaction(
+        &self,
+        psbt: &mut psbt::PartiallySignedTransaction,
+        sign_options: &SignOptions,
+        secp: &SecpCtx,
+    ) -> Result<(), SignerError> {
+        let sent = self.check_policy(psbt)?;
+        self.sign_and_record(psbt, sent, sign_options, secp)
+    }
+}
+
+/// Signer that asks for approval before signing
+///
+/// The `approve` callback is given the PSBT about to be signed and has to return `true` for the
+/// inner signer to sign it. Otherwise signing fails with [`SignerError::UserCanceled`]. This can be
+/// used to require a second person or service to review transactions before signatures are added.
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use bdk::signer::*;
+/// # use bdk::*;
+/// # use bitcoin::secp256k1::{All, Secp256k1};
+/// # use bitcoin::Network;
+/// # let mut wallet = Wallet::new_no_persist("wpkh(tpubD6NzVbkrYhZ4Xferm7Pz4VnjdcDPFyjVu5K4iZXQ4pVN8Cks4pHVowTBXBKRhX64pkRyJZJN5xAKj4UDNnLPb5p2sSKXhewoYx5GbTdUFWq/*)", None, Network::Testnet)?;
+/// # #[derive(Debug)]
+/// # struct CustomSigner;
+/// # impl SignerCommon for CustomSigner {
+/// #     fn id(&self, _secp: &Secp256k1<All>) -> SignerId {
+/// #         SignerId::Dummy(42)
+/// #     }
+/// # }
+/// # impl TransactionSigner for CustomSigner {
+/// #     fn sign_transaction(&self, _psbt: &mut bitcoin::psbt::PartiallySignedTransaction, _sign_options: &SignOptions, _secp: &Secp256k1<All>) -> Result<(), SignerError> {
+/// #         Ok(())
+/// #     }
+/// # }
+/// let signer = ApprovalSigner::new(CustomSigner, |psbt| {
+///     // ask for confirmation, e.g. by sending `psbt` to an approval service
+///     psbt.unsigned_tx.output.len() <= 2
+/// });
+/// wallet.add_signer(KeychainKind::External, SignerOrdering(200), Arc::new(signer));
+/// # Ok::<_, anyhow::Error>(())
+/// ```
+pub struct ApprovalSigner<S, F> {
+    signer: S,
+    approve: F,
+}
+
+impl<S, F> ApprovalSigner<S, F>
+where
+    S: TransactionSigner,
+    F: Fn(&psbt::PartiallySignedTransaction) -> bool + Send + Sync,
+{
+    /// Wrap `signer` so that it only signs transactions accepted by `approve`
+    pub fn new(signer: S, approve: F) -> Self {
+        ApprovalSigner { signer, approve }
+    }
+}
+
+impl<S: fmt::Debug, F> fmt::Debug for ApprovalSigner<S, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ApprovalSigner")
+            .field("signer", &self.signer)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S, F> SignerCommon for ApprovalSigner<S, F>
+where
+    S: TransactionSigner,
+    F: Fn(&psbt::PartiallySignedTransaction) -> bool + Send + Sync,
+{
+    fn id(&self, secp: &SecpCtx) -> SignerId {
+        self.signer.id(secp)
+    }
+
+    fn descriptor_secret_key(&self) -> Option<DescriptorSecretKey> {
+        self.signer.descriptor_secret_key()
+    }
+}
+
+impl<S, F> TransactionSigner for ApprovalSigner<S, F>
+where
+    S: TransactionSigner,
+    F: Fn(&psbt::PartiallySignedTransaction) -> bool + Send + Sync,
+{
+    fn sign_transaction(
+        &self,
+        psbt: &mut psbt::PartiallySignedTransaction,
+        sign_options: &SignOptions,
+        secp: &SecpCtx,
+    ) -> Result<(), SignerError> {
+        if !(self.approve)(psbt) {
+            return Err(SignerError::UserCanceled);
+        }
+        self.signer.sign_transaction(psbt, sign_options, secp)
+    }
+}
+
 impl SignerCommon for SignerWrapper<DescriptorXKey<ExtendedPrivKey>> {
     fn id(&self, secp: &SecpCtx) -> SignerId {
         SignerId::from(self.root_fingerprint(secp))
@@ -478,6 +939,7 @@ impl InputSigner for SignerWrapper<PrivateKey> {
                         hash,
                         hash_ty,
                         secp,
+                        sign_options.extra_entropy,
                     );
                 }
             }
@@ -513,6 +975,7 @@ impl InputSigner for SignerWrapper<PrivateKey> {
                         hash,
                         hash_ty,
                         secp,
+                        sign_options.extra_entropy,
                     );
                 }
             }
@@ -545,12 +1008,14 @@ impl InputSigner for SignerWrapper<PrivateKey> {
             hash_ty,
             secp,
             sign_options.allow_grinding,
+            sign_options.extra_entropy,
         );
 
         Ok(())
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn sign_psbt_ecdsa(
     secret_key: &secp256k1::SecretKey,
     pubkey: PublicKey,
@@ -559,12 +1024,29 @@ fn sign_psbt_ecdsa(
     hash_ty: EcdsaSighashType,
     secp: &SecpCtx,
     allow_grinding: bool,
+    extra_entropy: Option<[u8; 32]>,
 ) {
     let msg = &Message::from(hash);
-    let sig = if allow_grinding {
-        secp.sign_ecdsa_low_r(msg, secret_key)
-    } else {
-        secp.sign_ecdsa(msg, secret_key)
+    let sig = match (allow_grinding, extra_entropy) {
+        (true, None) => secp.sign_ecdsa_low_r(msg, secret_key),
+        (false, None) => secp.sign_ecdsa(msg, secret_key),
+        (false, Some(entropy)) => secp.sign_ecdsa_with_noncedata(msg, secret_key, &entropy),
+        (true, Some(entropy)) => {
+            // Same as `sign_ecdsa_low_r`, mixing a counter into the extra entropy until the
+            // signature has a low r
+            let mut counter = 0u32;
+            loop {
+                let mut noncedata = entropy;
+                for (byte, counter_byte) in noncedata.iter_mut().zip(counter.to_le_bytes()) {
+                    *byte ^= counter_byte;
+                }
+                let sig = secp.sign_ecdsa_with_noncedata(msg, secret_key, &noncedata);
+                if sig.serialize_compact()[0] < 0x80 {
+                    break sig;
+                }
+                counter += 1;
+            }
+        }
     };
     secp.verify_ecdsa(msg, &sig, &pubkey.inner)
         .expect("invalid or corrupted ecdsa signature");
@@ -574,6 +1056,7 @@ fn sign_psbt_ecdsa(
 }
 
 // Calling this with `leaf_hash` = `None` will sign for key-spend
+#[allow(clippy::too_many_arguments)]
 fn sign_psbt_schnorr(
     secret_key: &secp256k1::SecretKey,
     pubkey: XOnlyPublicKey,
@@ -582,6 +1065,7 @@ fn sign_psbt_schnorr(
     hash: TapSighash,
     hash_ty: TapSighashType,
     secp: &SecpCtx,
+    extra_entropy: Option<[u8; 32]>,
 ) {
     let keypair = secp256k1::KeyPair::from_seckey_slice(secp, secret_key.as_ref()).unwrap();
     let keypair = match leaf_hash {
@@ -592,7 +1076,10 @@ fn sign_psbt_schnorr(
     };
 
     let msg = &Message::from(hash);
-    let sig = secp.sign_schnorr(msg, &keypair);
+    let sig = match extra_entropy {
+        Some(aux_rand) => secp.sign_schnorr_with_aux_rand(msg, &keypair, &aux_rand),
+        None => secp.sign_schnorr(msg, &keypair),
+    };
     secp.verify_schnorr(&sig, msg, &XOnlyPublicKey::from_keypair(&keypair).0)
         .expect("invalid or corrupted schnorr signature");
 
@@ -809,6 +1296,16 @@ pub struct SignOptions {
     /// or not.
     /// Defaults to `true`, i.e., we always grind ECDSA signature to sign with low r.
     pub allow_grinding: bool,
+
+    /// Extra entropy mixed into the signature nonces
+    ///
+    /// ECDSA nonces are always derived deterministically with RFC6979, which takes this as
+    /// additional data. Schnorr nonces use it as the BIP340 auxiliary randomness, which is drawn
+    /// from the system's random number generator if `None`. Setting this makes every signature
+    /// reproducible, e.g. to compare them in tests or to audit a signer.
+    ///
+    /// Defaults to `None`.
+    pub extra_entropy: Option<[u8; 32]>,
 }
 
 /// Customize which taproot script-path leaves the signer should sign.
@@ -843,10 +1340,65 @@ impl Default for SignOptions {
             tap_leaves_options: TapLeavesOptions::default(),
             sign_with_tap_internal_key: true,
             allow_grinding: true,
+            extra_entropy: None,
         }
     }
 }
 
+/// Check that every signature of the input `input_index` of `psbt` is valid for the sighash it
+/// commits to, using the spent outputs found in `psbt`.
+///
+/// The sighash type of the input is overwritten with the one of each signature, so `psbt`
+/// should be a scratch copy.
+pub(crate) fn verify_input_signatures(
+    psbt: &mut psbt::PartiallySignedTransaction,
+    input_index: usize,
+    is_witness: bool,
+    secp: &SecpCtx,
+) -> Result<bool, SignerError> {
+    let input = psbt
+        .inputs
+        .get(input_index)
+        .cloned()
+        .ok_or(SignerError::InputIndexOutOfRange)?;
+
+    for (pubkey, sig) in &input.partial_sigs {
+        psbt.inputs[input_index].sighash_type = Some(sig.hash_ty.into());
+        let msg = if is_witness {
+            Message::from(Segwitv0::sighash(psbt, input_index, ())?.0)
+        } else {
+            Message::from(Legacy::sighash(psbt, input_index, ())?.0)
+        };
+        if secp.verify_ecdsa(&msg, &sig.sig, &pubkey.inner).is_err() {
+            return Ok(false);
+        }
+    }
+
+    for ((xonly, leaf_hash), sig) in &input.tap_script_sigs {
+        psbt.inputs[input_index].sighash_type = Some(sig.hash_ty.into());
+        let msg = Message::from(Tap::sighash(psbt, input_index, Some(*leaf_hash))?.0);
+        if secp.verify_schnorr(&sig.sig, &msg, xonly).is_err() {
+            return Ok(false);
+        }
+    }
+
+    if let Some(sig) = &input.tap_key_sig {
+        // The key spend is signed by the output key, i.e. the witness program of the spent script
+        let output_key = psbt
+            .get_utxo_for(input_index)
+            .filter(|txout| txout.script_pubkey.is_v1_p2tr())
+            .and_then(|txout| XOnlyPublicKey::from_slice(&txout.script_pubkey.as_bytes()[2..]).ok())
+            .ok_or(SignerError::MissingWitnessUtxo)?;
+        psbt.inputs[input_index].sighash_type = Some(sig.hash_ty.into());
+        let msg = Message::from(Tap::sighash(psbt, input_index, None)?.0);
+        if secp.verify_schnorr(&sig.sig, &msg, &output_key).is_err() {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
 pub(crate) trait ComputeSighash {
     type Extra;
     type Sighash;
@@ -1175,6 +1727,300 @@ mod signers_container_tests {
         }
     }
 
+    #[derive(Debug)]
+    struct FingerprintSigner(Fingerprint);
+
+    impl SignerCommon for FingerprintSigner {
+        fn id(&self, _secp: &SecpCtx) -> SignerId {
+            SignerId::Fingerprint(self.0)
+        }
+    }
+
+    impl TransactionSigner for FingerprintSigner {
+        fn sign_transaction(
+            &self,
+            _psbt: &mut psbt::PartiallySignedTransaction,
+            _sign_options: &SignOptions,
+            _secp: &SecpCtx,
+        ) -> Result<(), SignerError> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn policy_signer_enforces_spending_policy() {
+        let secp = Secp256k1::new();
+        let fingerprint = Fingerprint::from_str("deadbeef").unwrap();
+        let change_descriptor = ExtendedDescriptor::from_str("wpkh(tpubD6NzVbkrYhZ4Xferm7Pz4VnjdcDPFyjVu5K4iZXQ4pVN8Cks4pHVowTBXBKRhX64pkRyJZJN5xAKj4UDNnLPb5p2sSKXhewoYx5GbTdUFWq/1/*)").unwrap();
+        let recipient =
+            ScriptBuf::from_hex("0014aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa").unwrap();
+        let change = change_descriptor
+            .at_derivation_index(3)
+            .unwrap()
+            .script_pubkey();
+        let tx = bitcoin::Transaction {
+            version: 2,
+            lock_time: bitcoin::absolute::LockTime::ZERO,
+            input: vec![],
+            output: vec![
+                bitcoin::TxOut {
+                    value: 50_000,
+                    script_pubkey: recipient.clone(),
+                },
+                bitcoin::TxOut {
+                    value: 200_000,
+                    script_pubkey: change.clone(),
+                },
+            ],
+        };
+        let mut psbt = psbt::PartiallySignedTransaction::from_unsigned_tx(tx).unwrap();
+        let pk = secp256k1::PublicKey::from_str(
+            "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+        )
+        .unwrap();
+        psbt.outputs[1].bip32_derivation.insert(
+            pk,
+            (fingerprint, DerivationPath::from_str("m/1/3").unwrap()),
+        );
+
+        // the change output is not counted towards the limit
+        let signer = PolicySigner::new(
+            FingerprintSigner(fingerprint),
+            SpendingPolicy {
+                max_amount_per_tx: Some(50_000),
+                allowed_recipients: Some([recipient.clone()].into_iter().collect()),
+                ..Default::default()
+            },
+        )
+        .own_descriptor(change_descriptor.clone());
+        assert!(signer
+            .sign_transaction(&mut psbt, &SignOptions::default(), &secp)
+            .is_ok());
+
+        let signer = PolicySigner::new(
+            FingerprintSigner(fingerprint),
+            SpendingPolicy {
+                max_amount_per_tx: Some(49_999),
+                ..Default::default()
+            },
+        )
+        .own_descriptor(change_descriptor.clone());
+        assert_matches!(
+            signer.sign_transaction(&mut psbt, &SignOptions::default(), &secp),
+            Err(SignerError::SpendingLimitExceeded {
+                max: 49_999,
+                sent: 50_000
+            })
+        );
+
+        let signer = PolicySigner::new(
+            FingerprintSigner(fingerprint),
+            SpendingPolicy {
+                allowed_recipients: Some([change.clone()].into_iter().collect()),
+                ..Default::default()
+            },
+        )
+        .own_descriptor(change_descriptor.clone());
+        assert_matches!(
+            signer.sign_transaction(&mut psbt, &SignOptions::default(), &secp),
+            Err(SignerError::RecipientNotAllowed(script)) if script == recipient
+        );
+
+        // without the descriptor every output is a recipient
+        let signer = PolicySigner::new(
+            FingerprintSigner(fingerprint),
+            SpendingPolicy {
+                max_amount_per_tx: Some(50_000),
+                ..Default::default()
+            },
+        );
+        assert_matches!(
+            signer.sign_transaction(&mut psbt, &SignOptions::default(), &secp),
+            Err(SignerError::SpendingLimitExceeded {
+                max: 50_000,
+                sent: 250_000
+            })
+        );
+
+        // key origins copied onto a recipient output don't make it change
+        psbt.outputs[0].bip32_derivation.insert(
+            pk,
+            (fingerprint, DerivationPath::from_str("m/1/0").unwrap()),
+        );
+        let signer = PolicySigner::new(
+            FingerprintSigner(fingerprint),
+            SpendingPolicy {
+                max_amount_per_tx: Some(0),
+                ..Default::default()
+            },
+        )
+        .own_descriptor(change_descriptor);
+        assert_matches!(
+            signer.sign_transaction(&mut psbt, &SignOptions::default(), &secp),
+            Err(SignerError::SpendingLimitExceeded {
+                max: 0,
+                sent: 50_000
+            })
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn policy_signer_enforces_daily_limit() {
+        use core::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
+
+        static NOW: AtomicU64 = AtomicU64::new(SECONDS_PER_DAY * 100);
+        fn now() -> u64 {
+            NOW.load(AtomicOrdering::Relaxed)
+        }
+
+        /// A backend keeping the persisted changes, to load them in another signer
+        #[derive(Debug, Default, Clone)]
+        struct MemoryDb(Arc<std::sync::Mutex<SpendingChangeSet>>);
+
+        impl PersistBackend<SpendingChangeSet> for MemoryDb {
+            type WriteError = core::convert::Infallible;
+            type LoadError = core::convert::Infallible;
+
+            fn write_changes(
+                &mut self,
+                changeset: &SpendingChangeSet,
+            ) -> Result<(), Self::WriteError> {
+                self.0.lock().unwrap().extend(changeset.clone());
+                Ok(())
+            }
+
+            fn load_from_persistence(
+                &mut self,
+            ) -> Result<Option<SpendingChangeSet>, Self::LoadError> {
+                Ok(Some(self.0.lock().unwrap().clone()))
+            }
+        }
+
+        /// A signer adding a (fake) key spend signature to every input
+        #[derive(Debug)]
+        struct KeySpendSigner;
+
+        impl SignerCommon for KeySpendSigner {
+            fn id(&self, _secp: &SecpCtx) -> SignerId {
+                SignerId::Dummy(2)
+            }
+        }
+
+        impl TransactionSigner for KeySpendSigner {
+            fn sign_transaction(
+                &self,
+                psbt: &mut psbt::PartiallySignedTransaction,
+                _sign_options: &SignOptions,
+                _secp: &SecpCtx,
+            ) -> Result<(), SignerError> {
+                for input in &mut psbt.inputs {
+                    input.tap_key_sig = Some(taproot::Signature {
+                        sig: secp256k1::schnorr::Signature::from_slice(&[1; 64]).unwrap(),
+                        hash_ty: TapSighashType::Default,
+                    });
+                }
+                Ok(())
+            }
+        }
+
+        let secp = Secp256k1::new();
+        let psbt = |value| {
+            let tx = bitcoin::Transaction {
+                version: 2,
+                lock_time: bitcoin::absolute::LockTime::ZERO,
+                input: vec![bitcoin::TxIn::default()],
+                output: vec![bitcoin::TxOut {
+                    value,
+                    script_pubkey: ScriptBuf::new(),
+                }],
+            };
+            psbt::PartiallySignedTransaction::from_unsigned_tx(tx).unwrap()
+        };
+        let policy = SpendingPolicy {
+            max_amount_per_day: Some(100_000),
+            ..Default::default()
+        };
+        let db = MemoryDb::default();
+
+        // a daily limit can't be enforced without a clock
+        let signer = PolicySigner::new(KeySpendSigner, policy.clone());
+        assert_matches!(
+            signer.sign_transaction(&mut psbt(60_000), &SignOptions::default(), &secp),
+            Err(SignerError::MissingClock)
+        );
+
+        // transactions the inner signer doesn't sign are not counted
+        let signer =
+            PolicySigner::with_ledger(DummySigner { number: 1 }, policy.clone(), db.clone())
+                .unwrap()
+                .clock(now);
+        assert!(signer
+            .sign_transaction(&mut psbt(60_000), &SignOptions::default(), &secp)
+            .is_ok());
+        assert!(signer.spends().is_empty());
+
+        let signer = PolicySigner::with_ledger(KeySpendSigner, policy.clone(), db.clone())
+            .unwrap()
+            .clock(now);
+        let mut psbt_a = psbt(60_000);
+        assert!(signer
+            .sign_transaction(&mut psbt_a, &SignOptions::default(), &secp)
+            .is_ok());
+        assert!(psbt_a.inputs[0].tap_key_sig.is_some());
+        // signing the same transaction again doesn't count twice
+        assert!(signer
+            .sign_transaction(&mut psbt_a, &SignOptions::default(), &secp)
+            .is_ok());
+        assert_eq!(signer.spends().len(), 1);
+
+        // the spends are loaded from the database by a new signer
+        let signer = PolicySigner::with_ledger(KeySpendSigner, policy, db)
+            .unwrap()
+            .clock(now);
+        assert_matches!(
+            signer.sign_transaction(&mut psbt(50_000), &SignOptions::default(), &secp),
+            Err(SignerError::DailyLimitExceeded {
+                max: 100_000,
+                spent_today: 60_000,
+                sent: 50_000
+            })
+        );
+        assert!(signer
+            .sign_transaction(&mut psbt(40_000), &SignOptions::default(), &secp)
+            .is_ok());
+
+        // the limit resets the next day, and the spends of the previous day are pruned
+        NOW.fetch_add(SECONDS_PER_DAY, AtomicOrdering::Relaxed);
+        assert!(signer
+            .sign_transaction(&mut psbt(50_000), &SignOptions::default(), &secp)
+            .is_ok());
+        assert_eq!(signer.spends().len(), 1);
+    }
+
+    #[test]
+    fn approval_signer_requires_approval() {
+        let secp = Secp256k1::new();
+        let tx = bitcoin::Transaction {
+            version: 2,
+            lock_time: bitcoin::absolute::LockTime::ZERO,
+            input: vec![],
+            output: vec![],
+        };
+        let mut psbt = psbt::PartiallySignedTransaction::from_unsigned_tx(tx).unwrap();
+
+        let signer = ApprovalSigner::new(DummySigner { number: 1 }, |_| true);
+        assert!(signer
+            .sign_transaction(&mut psbt, &SignOptions::default(), &secp)
+            .is_ok());
+
+        let signer = ApprovalSigner::new(DummySigner { number: 1 }, |_| false);
+        assert_matches!(
+            signer.sign_transaction(&mut psbt, &SignOptions::default(), &secp),
+            Err(SignerError::UserCanceled)
+        );
+    }
+
     const TPRV0_STR:&str = "tprv8ZgxMBicQKsPdZXrcHNLf5JAJWFAoJ2TrstMRdSKtEggz6PddbuSkvHKM9oKJyFgZV1B7rw8oChspxyYbtmEXYyg1AjfWbL3ho3XHDpHRZf";
     const TPRV1_STR:&str = "tprv8ZgxMBicQKsPdpkqS7Eair4YxjcuuvDPNYmKX3sCniCf16tHEVrjjiSXEkFRnUH77yXc6ZcwHHcLNfjdi5qUvw3VDfgYiH5mNsj5izuiu2N";
 
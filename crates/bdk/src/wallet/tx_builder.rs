@@ -41,7 +41,7 @@
 //! ```
 
 use crate::collections::BTreeMap;
-use crate::collections::HashSet;
+use crate::collections::{HashMap, HashSet};
 use alloc::{boxed::Box, rc::Rc, string::String, vec::Vec};
 use bdk_chain::PersistBackend;
 use core::cell::RefCell;
@@ -49,9 +49,12 @@ use core::fmt;
 use core::marker::PhantomData;
 
 use bitcoin::psbt::{self, PartiallySignedTransaction as Psbt};
-use bitcoin::{absolute, script::PushBytes, OutPoint, ScriptBuf, Sequence, Transaction, Txid};
+use bitcoin::{
+    absolute, script::PushBytes, OutPoint, ScriptBuf, Sequence, Transaction, Txid, Weight,
+};
 
-use super::coin_selection::{CoinSelectionAlgorithm, DefaultCoinSelectionAlgorithm};
+use super::coin_selection::{CoinSelectionAlgorithm, DefaultCoinSelectionAlgorithm, Excess};
+use super::fee_estimation::{FeeEstimationError, FeeEstimator};
 use super::ChangeSet;
 use crate::types::{FeeRate, KeychainKind, LocalOutput, WeightedUtxo};
 use crate::wallet::CreateTxError;
@@ -140,6 +143,7 @@ pub(crate) struct TxParams {
     pub(crate) recipients: Vec<(ScriptBuf, u64)>,
     pub(crate) drain_wallet: bool,
     pub(crate) drain_to: Option<ScriptBuf>,
+    pub(crate) drain_to_change: bool,
     pub(crate) fee_policy: Option<FeePolicy>,
     pub(crate) internal_policy_path: Option<BTreeMap<String, Vec<usize>>>,
     pub(crate) external_policy_path: Option<BTreeMap<String, Vec<usize>>>,
@@ -147,6 +151,7 @@ pub(crate) struct TxParams {
     pub(crate) unspendable: HashSet<OutPoint>,
     pub(crate) manually_selected_only: bool,
     pub(crate) sighash: Option<psbt::PsbtSighashType>,
+    pub(crate) input_sighashes: HashMap<OutPoint, psbt::PsbtSighashType>,
     pub(crate) ordering: TxOrdering,
     pub(crate) locktime: Option<absolute::LockTime>,
     pub(crate) rbf: Option<RbfValue>,
@@ -158,6 +163,10 @@ pub(crate) struct TxParams {
     pub(crate) bumping_fee: Option<PreviousFee>,
     pub(crate) current_height: Option<absolute::LockTime>,
     pub(crate) allow_dust: bool,
+    pub(crate) subtract_fee_from: Vec<usize>,
+    pub(crate) first_output: Option<usize>,
+    pub(crate) reject_duplicate_recipients: bool,
+    pub(crate) min_change_value: u64,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -208,6 +217,24 @@ impl<'a, D, Cs: CoinSelectionAlgorithm, Ctx: TxBuilderContext> TxBuilder<'a, D,
         self
     }
 
+    /// Set the fee rate to the one estimated by `estimator` for the transaction to confirm
+    /// within `target_blocks` blocks
+    ///
+    /// The estimate is never lower than the minimum relay fee. See [`fee_rate`](Self::fee_rate)
+    /// and the [`fee_estimation`](super::fee_estimation) module for more.
+    pub fn fee_estimator<E: FeeEstimator + ?Sized>(
+        &mut self,
+        estimator: &E,
+        target_blocks: u16,
+    ) -> Result<&mut Self, FeeEstimationError> {
+        let fee_rate = estimator.estimate_fee_rate(target_blocks)?;
+        if fee_rate.is_below_min_relay_fee() {
+            Ok(self.fee_rate(FeeRate::default_min_relay_fee()))
+        } else {
+            Ok(self.fee_rate(fee_rate))
+        }
+    }
+
     /// Set an absolute fee
     /// The fee_absolute method refers to the absolute transaction fee in satoshis (sats).
     /// If anyone sets both the fee_absolute method and the fee_rate method,
@@ -457,6 +484,30 @@ impl<'a, D, Cs: CoinSelectionAlgorithm, Ctx: TxBuilderContext> TxBuilder<'a, D,
         self
     }
 
+    /// Sign the input spending `outpoint` with a specific sig hash, overriding the one set with
+    /// [`sighash`](Self::sighash) for this input.
+    ///
+    /// This allows, for instance, signing a single input with `SIGHASH_SINGLE |
+    /// SIGHASH_ANYONECANPAY` to create an offer that others can complete. Since `SIGHASH_SINGLE`
+    /// commits to the output at the same index as the input, you will probably want to use
+    /// [`TxOrdering::Untouched`] with it. Building the transaction fails with
+    /// [`CreateTxError::SighashSingleWithoutOutput`] if there's no such output.
+    ///
+    /// Signing inputs with a sighash other than `SIGHASH_ALL` (or `SIGHASH_DEFAULT` for taproot)
+    /// requires [`SignOptions::allow_all_sighashes`].
+    ///
+    /// **Use this option very carefully**
+    ///
+    /// [`SignOptions::allow_all_sighashes`]: crate::SignOptions::allow_all_sighashes
+    pub fn input_sighash(
+        &mut self,
+        outpoint: OutPoint,
+        sighash: psbt::PsbtSighashType,
+    ) -> &mut Self {
+        self.params.input_sighashes.insert(outpoint, sighash);
+        self
+    }
+
     /// Choose the ordering for inputs and outputs of the transaction
     pub fn ordering(&mut self, ordering: TxOrdering) -> &mut Self {
         self.params.ordering = ordering;
@@ -571,6 +622,40 @@ impl<'a, D, Cs: CoinSelectionAlgorithm, Ctx: TxBuilderContext> TxBuilder<'a, D,
             .create_tx(self.coin_selection, self.params)
     }
 
+    /// Finish building the transaction, returning a breakdown of how it was built along with the
+    /// [`Psbt`].
+    ///
+    /// The transaction is the same [`finish`] would create; the [`TxDetails`] describe the
+    /// selected UTXOs, the change, the fee and the policy paths used, for instance to show them
+    /// in a review screen.
+    ///
+    /// [`finish`]: Self::finish
+    pub fn finish_with_details(self) -> Result<(Psbt, TxDetails), CreateTxError<D::WriteError>>
+    where
+        D: PersistBackend<ChangeSet>,
+    {
+        self.wallet
+            .borrow_mut()
+            .create_tx_with_details(self.coin_selection, self.params)
+    }
+
+    /// Estimate the weight and fee of the transaction that [`finish`] would create, without
+    /// creating the PSBT.
+    ///
+    /// The coins are selected as [`finish`] would, and the weight of the signed transaction is
+    /// predicted from the satisfaction weight of the descriptors. No change address is revealed,
+    /// so this can be called repeatedly, for instance to preview the fee in a UI.
+    ///
+    /// [`finish`]: Self::finish
+    pub fn estimate(&self) -> Result<TxEstimate, CreateTxError<D::WriteError>>
+    where
+        D: PersistBackend<ChangeSet>,
+    {
+        self.wallet
+            .borrow_mut()
+            .estimate_tx(&self.coin_selection, &self.params)
+    }
+
     /// Enable signaling RBF
     ///
     /// This will use the default nSequence value of `0xFFFFFFFD`.
@@ -608,6 +693,17 @@ impl<'a, D, Cs: CoinSelectionAlgorithm, Ctx: TxBuilderContext> TxBuilder<'a, D,
         self
     }
 
+    /// Don't create a change (or drain) output worth less than `min_change_value` satoshis.
+    ///
+    /// Change below the dust limit is always added to the fee instead of creating an output.
+    /// This raises that threshold, for instance to avoid creating outputs that would cost more
+    /// to spend than they are worth at the expected future fee rate. The excess is added to the
+    /// fee.
+    pub fn min_change_value(&mut self, min_change_value: u64) -> &mut Self {
+        self.params.min_change_value = min_change_value;
+        self
+    }
+
     /// Set whether or not the dust limit is checked.
     ///
     /// **Note**: by avoiding a dust limit check you may end up with a transaction that is non-standard.
@@ -714,7 +810,60 @@ impl<'a, D, Cs: CoinSelectionAlgorithm> TxBuilder<'a, D, Cs, CreateTx> {
         self
     }
 
+    /// Pay the fee of the transaction out of the recipients at `indexes`, instead of adding
+    /// more inputs to cover it.
+    ///
+    /// The indexes refer to the order in which recipients were added. The fee is split evenly
+    /// between the selected recipients, and any remainder is subtracted from the first one.
+    /// Creating the transaction fails with [`CreateTxError::InvalidRecipientIndex`] if an index
+    /// is out of range, or with [`CreateTxError::OutputBelowDustLimit`] if a recipient can't
+    /// afford its share of the fee.
+    ///
+    /// [`CreateTxError::InvalidRecipientIndex`]: crate::wallet::error::CreateTxError::InvalidRecipientIndex
+    /// [`CreateTxError::OutputBelowDustLimit`]: crate::wallet::error::CreateTxError::OutputBelowDustLimit
+    pub fn subtract_fee_from(&mut self, indexes: &[usize]) -> &mut Self {
+        let mut indexes = indexes.to_vec();
+        indexes.sort_unstable();
+        indexes.dedup();
+        self.params.subtract_fee_from = indexes;
+        self
+    }
+
+    /// Add many recipients at once, after the ones already added
+    pub fn add_recipients(
+        &mut self,
+        recipients: impl IntoIterator<Item = (ScriptBuf, u64)>,
+    ) -> &mut Self {
+        self.params.recipients.extend(recipients);
+        self
+    }
+
+    /// Always place the recipient at `index` as the first output of the transaction, whatever
+    /// the [`TxOrdering`] of the other outputs.
+    ///
+    /// The index refers to the order in which recipients were added. This is needed by some
+    /// protocols that identify their output by its position.
+    pub fn first_output(&mut self, index: usize) -> &mut Self {
+        self.params.first_output = Some(index);
+        self
+    }
+
+    /// Fail with [`CreateTxError::DuplicateRecipient`] if two recipients pay the same script
+    /// pubkey, to catch mistakes when building large batches of payments.
+    ///
+    /// [`CreateTxError::DuplicateRecipient`]: crate::wallet::error::CreateTxError::DuplicateRecipient
+    pub fn reject_duplicate_recipients(&mut self) -> &mut Self {
+        self.params.reject_duplicate_recipients = true;
+        self
+    }
+
     /// Add data as an output, using OP_RETURN
+    ///
+    /// To be relayed by the network the whole output script can't be larger than
+    /// [`MAX_OP_RETURN_RELAY`] bytes, which leaves room for 80 bytes of data. Creating the
+    /// transaction fails with [`CreateTxError::OpReturnTooLarge`] if the data is larger.
+    ///
+    /// [`CreateTxError::OpReturnTooLarge`]: crate::wallet::error::CreateTxError::OpReturnTooLarge
     pub fn add_data<T: AsRef<PushBytes>>(&mut self, data: &T) -> &mut Self {
         let script = ScriptBuf::new_op_return(data);
         self.add_recipient(script, 0u64);
@@ -810,6 +959,76 @@ impl<'a, D> TxBuilder<'a, D, DefaultCoinSelectionAlgorithm, BumpFee> {
     }
 }
 
+/// Expected weight and fee of a transaction, as returned by [`TxBuilder::estimate`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TxEstimate {
+    /// Expected weight of the signed transaction
+    pub weight: Weight,
+    /// Fee paid by the transaction, in satoshis
+    pub fee: u64,
+    /// Expected fee rate of the signed transaction
+    pub fee_rate: FeeRate,
+}
+
+impl TxEstimate {
+    /// Expected virtual size of the signed transaction, in vbytes
+    pub fn vsize(&self) -> u64 {
+        self.weight.to_vbytes_ceil()
+    }
+}
+
+/// Why a UTXO was spent by a transaction, see [`TxDetails`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectionReason {
+    /// The UTXO was added with [`TxBuilder::add_utxo`] or [`TxBuilder::add_foreign_utxo`], or
+    /// it's spent by the transaction being replaced
+    Manual,
+    /// The wallet is being drained with [`TxBuilder::drain_wallet`]
+    DrainWallet,
+    /// The UTXO was picked by the coin selection algorithm
+    CoinSelection,
+}
+
+/// A UTXO spent by a transaction, see [`TxDetails`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SelectedUtxo {
+    /// The outpoint of the UTXO
+    pub outpoint: OutPoint,
+    /// The value of the UTXO, in satoshis
+    pub value: u64,
+    /// Why the UTXO was spent
+    pub reason: SelectionReason,
+}
+
+/// Breakdown of a transaction, as returned by [`TxBuilder::finish_with_details`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct TxDetails {
+    /// The UTXOs spent, in the order they were selected
+    pub selected_utxos: Vec<SelectedUtxo>,
+    /// Value of the change (or drain) output, if one was created
+    pub change: Option<u64>,
+    /// Fee paid by the transaction, in satoshis
+    pub fee: u64,
+    /// Expected fee rate of the signed transaction
+    pub fee_rate: FeeRate,
+    /// Expected weight of the signed transaction
+    pub weight: Weight,
+    /// The excess of the coin selection
+    pub excess: Excess,
+    /// Waste of the coin selection, in satoshis, using the fee rate of the transaction as long
+    /// term fee rate: the fee of the change output, or the excess left to the fee if there's
+    /// no change
+    pub waste: u64,
+    /// The policy path used for the external descriptor, if any
+    pub external_policy_path: Option<BTreeMap<String, Vec<usize>>>,
+    /// The policy path used for the internal descriptor, if any
+    pub internal_policy_path: Option<BTreeMap<String, Vec<usize>>>,
+}
+
+/// Maximum size in bytes of an OP_RETURN output script that is relayed by default by
+/// Bitcoin Core (`-datacarriersize`)
+pub const MAX_OP_RETURN_RELAY: usize = 83;
+
 /// Ordering of the transaction's inputs and outputs
 #[derive(Debug, Ord, PartialOrd, Eq, PartialEq, Hash, Clone, Copy)]
 pub enum TxOrdering {
@@ -819,6 +1038,12 @@ pub enum TxOrdering {
     Untouched,
     /// BIP69 / Lexicographic
     Bip69Lexicographic,
+    /// Shuffled deterministically from the given seed
+    ///
+    /// The same seed always results in the same ordering for the same transaction, which is
+    /// useful to reproduce a transaction exactly. The shuffle uses ChaCha20, whose output doesn't
+    /// change across platforms or `rand` releases.
+    SeededShuffle(u64),
 }
 
 impl Default for TxOrdering {
@@ -838,6 +1063,12 @@ impl TxOrdering {
                 tx.input.shuffle(&mut rng);
                 tx.output.shuffle(&mut rng);
             }
+            TxOrdering::SeededShuffle(seed) => {
+                use rand::{seq::SliceRandom, SeedableRng};
+                let mut rng = rand_chacha::ChaCha20Rng::seed_from_u64(*seed);
+                tx.input.shuffle(&mut rng);
+                tx.output.shuffle(&mut rng);
+            }
             TxOrdering::Bip69Lexicographic => {
                 tx.input.sort_unstable_by_key(|txin| {
                     (txin.previous_output.txid, txin.previous_output.vout)
@@ -963,6 +1194,25 @@ mod test {
             .expect("it should have moved the outputs at least once");
     }
 
+    #[test]
+    fn test_output_ordering_seeded_shuffle() {
+        let original_tx = ordering_test_tx!();
+
+        let mut tx1 = original_tx.clone();
+        TxOrdering::SeededShuffle(42).sort_tx(&mut tx1);
+        let mut tx2 = original_tx.clone();
+        TxOrdering::SeededShuffle(42).sort_tx(&mut tx2);
+        assert_eq!(tx1, tx2);
+
+        (0..40)
+            .find(|seed| {
+                let mut tx = original_tx.clone();
+                TxOrdering::SeededShuffle(*seed).sort_tx(&mut tx);
+                original_tx.input != tx.input && original_tx.output != tx.output
+            })
+            .expect("some seed should have moved the inputs and outputs");
+    }
+
     #[test]
     fn test_output_ordering_bip69() {
         use core::str::FromStr;
@@ -9,11 +9,16 @@
 // You may not use this file except in accordance with one or both of these
 // licenses.
 
+use crate::collections::BTreeSet;
+
+use bitcoin::bip32::ChildNumber;
 use bitcoin::secp256k1::{All, Secp256k1};
-use bitcoin::{absolute, Script, Sequence};
+use bitcoin::{absolute, psbt, Script, Sequence};
 
 use miniscript::{MiniscriptKey, Satisfier, ToPublicKey};
 
+use crate::descriptor::ExtendedDescriptor;
+
 /// Trait to check if a value is below the dust limit.
 /// We are performing dust value calculation for a given script public key using rust-bitcoin to
 /// keep it compatible with network dust rate
@@ -112,6 +117,33 @@ impl<Pk: MiniscriptKey + ToPublicKey> Satisfier<Pk> for Older {
 
 pub(crate) type SecpCtx = Secp256k1<All>;
 
+/// Look for a derivation index in the key origins of a PSBT output at which `descriptor` derives
+/// `script_pubkey`
+///
+/// Key origins are provided by whoever built the PSBT, so an index is only returned after deriving
+/// the script again from the descriptor.
+pub(crate) fn derivation_from_key_origins(
+    descriptor: &ExtendedDescriptor,
+    output: &psbt::Output,
+    script_pubkey: &Script,
+) -> Option<u32> {
+    output
+        .bip32_derivation
+        .values()
+        .chain(output.tap_key_origins.values().map(|(_, origin)| origin))
+        .filter_map(|(_, path)| match path.as_ref().last() {
+            Some(ChildNumber::Normal { index }) => Some(*index),
+            _ => None,
+        })
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .find(|index| {
+            descriptor
+                .at_derivation_index(*index)
+                .map_or(false, |derived| derived.script_pubkey() == *script_pubkey)
+        })
+}
+
 #[cfg(test)]
 mod test {
     // When nSequence is lower than this flag the timelock is interpreted as block-height-based,
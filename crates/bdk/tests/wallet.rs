@@ -5,7 +5,7 @@ use bdk::descriptor::calc_checksum;
 use bdk::psbt::PsbtUtils;
 use bdk::signer::{SignOptions, SignerError};
 use bdk::wallet::coin_selection::{self, LargestFirstCoinSelection};
-use bdk::wallet::error::CreateTxError;
+use bdk::wallet::error::{CombinePsbtError, CreateTxError};
 use bdk::wallet::tx_builder::AddForeignUtxoError;
 use bdk::wallet::AddressIndex::*;
 use bdk::wallet::{AddressIndex, AddressInfo, Balance, Wallet};
@@ -87,6 +87,94 @@ fn load_recovers_wallet() {
     }
 }
 
+#[test]
+fn load_checks_descriptors() {
+    let temp_dir = tempfile::tempdir().expect("must create tempdir");
+    let file_path = temp_dir.path().join("store.db");
+
+    {
+        let db = bdk_file_store::Store::create_new(DB_MAGIC, &file_path).expect("must create db");
+        Wallet::new(get_test_wpkh(), None, db, Network::Testnet).expect("must init wallet");
+    }
+
+    // different external descriptor
+    {
+        let db = bdk_file_store::Store::open(DB_MAGIC, &file_path).expect("must recover db");
+        let err =
+            Wallet::load(get_test_tr_single_sig_xprv(), None, db).expect_err("wrong descriptor");
+        assert_matches!(
+            err,
+            bdk::wallet::LoadError::DescriptorMismatch {
+                keychain: KeychainKind::External,
+                ..
+            }
+        );
+    }
+
+    // a change descriptor that wasn't there
+    {
+        let db = bdk_file_store::Store::open(DB_MAGIC, &file_path).expect("must recover db");
+        let err = Wallet::new_or_load(
+            get_test_wpkh(),
+            Some(get_test_tr_single_sig_xprv()),
+            db,
+            Network::Testnet,
+        )
+        .expect_err("wrong change descriptor");
+        assert_matches!(
+            err,
+            bdk::wallet::NewOrLoadError::LoadedDescriptorDoesNotMatch {
+                keychain: KeychainKind::Internal,
+                expected: Some(_),
+                got: None,
+            }
+        );
+    }
+}
+
+#[test]
+fn load_migrates_legacy_store() {
+    use bdk::wallet::{ChangeSet, LegacyChangeSet};
+    use bdk_file_store::Store;
+
+    let temp_dir = tempfile::tempdir().expect("must create tempdir");
+    let scratch_path = temp_dir.path().join("scratch.db");
+    let legacy_path = temp_dir.path().join("legacy.db");
+    let file_path = temp_dir.path().join("store.db");
+
+    // a store written before the descriptor checksums were persisted
+    {
+        let db = Store::create_new(DB_MAGIC, &scratch_path).expect("must create db");
+        Wallet::new(get_test_wpkh(), None, db, Network::Testnet).expect("must init wallet");
+        let changeset = Store::<ChangeSet>::open(DB_MAGIC, &scratch_path)
+            .expect("must open db")
+            .aggregate_changesets()
+            .expect("must read db")
+            .expect("must not be empty");
+        let mut legacy =
+            Store::<LegacyChangeSet>::create_new(DB_MAGIC, &legacy_path).expect("must create db");
+        legacy
+            .append_changeset(&LegacyChangeSet {
+                chain: changeset.chain,
+                indexed_tx_graph: changeset.indexed_tx_graph,
+                network: changeset.network,
+            })
+            .expect("must write db");
+    }
+
+    let db = Store::<ChangeSet>::open(DB_MAGIC, &legacy_path).expect("must open db");
+    let err = Wallet::load(get_test_wpkh(), None, db).expect_err("must not load legacy store");
+    assert_matches!(err, bdk::wallet::LoadError::Load(_));
+    assert!(err.to_string().contains("LegacyChangeSet"));
+
+    let mut legacy = Store::<LegacyChangeSet>::open(DB_MAGIC, &legacy_path).expect("must open db");
+    let mut db = Store::<ChangeSet>::create_new(DB_MAGIC, &file_path).expect("must create db");
+    db.migrate_from(&mut legacy).expect("must migrate db");
+    let wallet = Wallet::load(get_test_wpkh(), None, db).expect("must load wallet");
+    assert_eq!(wallet.network(), Network::Testnet);
+    assert_eq!(wallet.staged().descriptor_checksums.len(), 1);
+}
+
 #[test]
 fn new_or_load() {
     let temp_dir = tempfile::tempdir().expect("must create tempdir");
@@ -256,6 +344,192 @@ fn test_list_output() {
     }
 }
 
+#[test]
+fn test_create_tx_dyn_coin_selection() {
+    let (mut wallet, _) = get_funded_wallet(get_test_wpkh());
+    let addr = wallet.get_address(New);
+    let coin_selection: Box<dyn coin_selection::CoinSelectionAlgorithm> =
+        Box::new(LargestFirstCoinSelection);
+    let mut builder = wallet.build_tx().coin_selection(coin_selection);
+    builder.add_recipient(addr.script_pubkey(), 25_000);
+    let psbt = builder.finish().unwrap();
+
+    assert_eq!(psbt.unsigned_tx.input.len(), 1);
+}
+
+#[test]
+fn test_preview_update() {
+    use bdk::wallet::Update;
+    use bdk_chain::{ConfirmationTimeHeightAnchor, TxGraph};
+
+    let (mut wallet, txid) = get_funded_wallet(get_test_wpkh());
+    let spent = OutPoint { txid, vout: 0 };
+    let receive = wallet.get_address(Peek(5));
+    let tx = Transaction {
+        version: 1,
+        lock_time: absolute::LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: spent,
+            ..Default::default()
+        }],
+        output: vec![TxOut {
+            value: 49_000,
+            script_pubkey: receive.script_pubkey(),
+        }],
+    };
+    let mut graph = TxGraph::default();
+    let _ = graph.insert_tx(tx.clone());
+    let _ = graph.insert_anchor(
+        tx.txid(),
+        ConfirmationTimeHeightAnchor {
+            anchor_block: BlockId {
+                height: 2_000,
+                hash: BlockHash::all_zeros(),
+            },
+            confirmation_height: 2_000,
+            confirmation_time: 200,
+        },
+    );
+    let update = Update {
+        last_active_indices: [(KeychainKind::External, 5)].into(),
+        graph,
+        chain: None,
+    };
+
+    wallet.commit().unwrap();
+    let preview = wallet.preview_update(update.clone()).unwrap();
+    assert_eq!(preview.new_txs, vec![tx.txid()]);
+    assert_eq!(
+        preview.new_utxos,
+        vec![OutPoint {
+            txid: tx.txid(),
+            vout: 0
+        }]
+    );
+    assert_eq!(preview.removed_utxos, vec![spent]);
+    assert_eq!(
+        preview.index_advances.get(&KeychainKind::External),
+        Some(&5)
+    );
+
+    // the wallet is left untouched
+    assert!(wallet.get_tx(tx.txid()).is_none());
+    assert_eq!(wallet.get_balance().confirmed, 50_000);
+
+    wallet.apply_update(update).unwrap();
+    assert_eq!(wallet.staged(), &preview.changeset);
+    assert_eq!(wallet.get_balance().confirmed, 49_000);
+}
+
+#[test]
+fn test_milestone_tracker() {
+    use bdk::wallet::milestones::MilestoneTracker;
+
+    let (mut wallet, txid) = get_funded_wallet(get_test_wpkh());
+    let mut tracker = MilestoneTracker::new([1, 3, 6], ()).unwrap();
+
+    // the funding transaction has 1 confirmation, its parent more than 6
+    let events = tracker.update(&wallet).unwrap();
+    let funding_events = events.iter().filter(|e| e.txid == txid).collect::<Vec<_>>();
+    assert_eq!(events.len(), 4);
+    assert_eq!(funding_events.len(), 1);
+    assert_eq!(funding_events[0].milestone, 1);
+    assert_eq!(funding_events[0].confirmations, 1);
+    assert_eq!(tracker.last_emitted(&txid), Some(1));
+
+    // nothing changed, nothing is emitted again
+    assert!(tracker.update(&wallet).unwrap().is_empty());
+
+    wallet
+        .insert_checkpoint(BlockId {
+            height: 2_002,
+            hash: BlockHash::all_zeros(),
+        })
+        .unwrap();
+    let events = tracker.update(&wallet).unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].txid, txid);
+    assert_eq!(events[0].milestone, 3);
+    assert_eq!(events[0].confirmations, 3);
+    assert!(tracker.update(&wallet).unwrap().is_empty());
+}
+
+#[test]
+fn test_milestone_tracker_write_failure() {
+    use bdk::wallet::milestones::{ChangeSet, MilestoneTracker};
+    use bdk_chain::PersistBackend;
+    use core::cell::Cell;
+    use std::rc::Rc;
+
+    struct FlakyDb(Rc<Cell<bool>>);
+
+    impl PersistBackend<ChangeSet> for FlakyDb {
+        type WriteError = ();
+        type LoadError = ();
+
+        fn write_changes(&mut self, _changeset: &ChangeSet) -> Result<(), ()> {
+            if self.0.get() {
+                Err(())
+            } else {
+                Ok(())
+            }
+        }
+
+        fn load_from_persistence(&mut self) -> Result<Option<ChangeSet>, ()> {
+            Ok(None)
+        }
+    }
+
+    let (wallet, txid) = get_funded_wallet(get_test_wpkh());
+    let fail = Rc::new(Cell::new(true));
+    let mut tracker = MilestoneTracker::new([1, 3, 6], FlakyDb(fail.clone())).unwrap();
+
+    // the milestones are not recorded as emitted if they couldn't be persisted
+    assert!(tracker.update(&wallet).is_err());
+    assert_eq!(tracker.last_emitted(&txid), None);
+
+    fail.set(false);
+    let events = tracker.update(&wallet).unwrap();
+    assert_eq!(events.len(), 4);
+    assert_eq!(tracker.last_emitted(&txid), Some(1));
+    assert!(tracker.update(&wallet).unwrap().is_empty());
+}
+
+#[test]
+fn test_get_new_address_uri() {
+    let mut wallet = Wallet::new_no_persist(get_test_wpkh(), None, Network::Testnet).unwrap();
+    let uri = wallet
+        .get_new_address_uri(Some(50_000), Some("Coffee"))
+        .unwrap();
+
+    assert_eq!(uri.address, wallet.get_address(Peek(0)).address);
+    assert_eq!(
+        uri.to_string(),
+        format!("bitcoin:{}?amount=0.0005&label=Coffee", uri.address)
+    );
+    assert_eq!(wallet.get_address(LastUnused).index, 0);
+}
+
+#[test]
+fn test_get_address_details() {
+    let (mut wallet, _) = get_funded_wallet(get_test_wpkh());
+    let spk = wallet.get_address(Peek(0)).script_pubkey();
+    let details = wallet.get_address_details(&spk).unwrap();
+    assert_eq!(details.keychain, KeychainKind::External);
+    assert_eq!(details.index, 0);
+    assert_eq!(details.tx_count, 2);
+    assert_eq!(details.total_received, 126_000);
+    assert_eq!(details.first_used_height, Some(1_000));
+    assert_eq!(details.last_used_height, Some(2_000));
+
+    let foreign = Address::from_str("bcrt1q3qtze4ys45tgdvguj66zrk4fu6hq3a3v9pfly5")
+        .unwrap()
+        .assume_checked();
+    assert!(wallet
+        .get_address_details(&foreign.script_pubkey())
+        .is_none());
+}
+
 macro_rules! assert_fee_rate {
     ($psbt:expr, $fees:expr, $fee_rate:expr $( ,@dust_change $( $dust_change:expr )* )* $( ,@add_signature $( $add_signature:expr )* )* ) => ({
         let psbt = $psbt.clone();
@@ -684,6 +958,171 @@ fn test_create_tx_absolute_zero_fee() {
     assert_eq!(psbt.unsigned_tx.output[0].value, 50_000 - fee.unwrap_or(0));
 }
 
+#[test]
+fn test_create_tx_subtract_fee_from_recipient() {
+    let (mut wallet, _) = get_funded_wallet(get_test_wpkh());
+    let addr = Address::from_str("2N4eQYCbKUHCCTUjBJeHcJp9ok6J2GZsTDt")
+        .unwrap()
+        .assume_checked();
+    let mut builder = wallet.build_tx();
+    builder
+        .add_recipient(addr.script_pubkey(), 25_000)
+        .subtract_fee_from(&[0])
+        .fee_rate(FeeRate::from_sat_per_vb(5.0));
+    let psbt = builder.finish().unwrap();
+    let fee = check_fee!(wallet, psbt);
+
+    assert_fee_rate!(psbt, fee.unwrap_or(0), FeeRate::from_sat_per_vb(5.0), @add_signature);
+    assert_eq!(psbt.unsigned_tx.output.len(), 2);
+    let recipient = psbt
+        .unsigned_tx
+        .output
+        .iter()
+        .find(|o| o.script_pubkey == addr.script_pubkey())
+        .unwrap();
+    assert_eq!(recipient.value, 25_000 - fee.unwrap_or(0));
+    let change = psbt
+        .unsigned_tx
+        .output
+        .iter()
+        .find(|o| o.script_pubkey != addr.script_pubkey())
+        .unwrap();
+    assert_eq!(change.value, 25_000);
+}
+
+#[test]
+fn test_create_tx_subtract_fee_send_all() {
+    let (mut wallet, _) = get_funded_wallet(get_test_wpkh());
+    let addr = Address::from_str("2N4eQYCbKUHCCTUjBJeHcJp9ok6J2GZsTDt")
+        .unwrap()
+        .assume_checked();
+    let mut builder = wallet.build_tx();
+    builder
+        .add_recipient(addr.script_pubkey(), 50_000)
+        .subtract_fee_from(&[0]);
+    let psbt = builder.finish().unwrap();
+    let fee = check_fee!(wallet, psbt);
+
+    assert_fee_rate!(psbt, fee.unwrap_or(0), FeeRate::default(), @add_signature);
+    assert_eq!(psbt.unsigned_tx.output.len(), 1);
+    assert_eq!(psbt.unsigned_tx.output[0].value, 50_000 - fee.unwrap_or(0));
+}
+
+#[test]
+fn test_create_tx_subtract_fee_invalid_index() {
+    let (mut wallet, _) = get_funded_wallet(get_test_wpkh());
+    let addr = wallet.get_address(New);
+    let mut builder = wallet.build_tx();
+    builder
+        .add_recipient(addr.script_pubkey(), 25_000)
+        .subtract_fee_from(&[1]);
+    assert_matches!(
+        builder.finish(),
+        Err(CreateTxError::InvalidRecipientIndex(1))
+    );
+}
+
+#[test]
+fn test_create_tx_estimate() {
+    let (mut wallet, _) =
+        get_funded_wallet_with_change(get_test_wpkh(), Some(get_test_tr_single_sig_xprv()));
+    let addr = Address::from_str("2N4eQYCbKUHCCTUjBJeHcJp9ok6J2GZsTDt")
+        .unwrap()
+        .assume_checked();
+
+    let estimate = {
+        let mut builder = wallet.build_tx();
+        builder
+            .add_recipient(addr.script_pubkey(), 25_000)
+            .fee_rate(FeeRate::from_sat_per_vb(5.0));
+        builder.estimate().unwrap()
+    };
+    // estimating doesn't reveal any change address
+    assert_eq!(wallet.derivation_index(KeychainKind::Internal), None);
+
+    let mut builder = wallet.build_tx();
+    builder
+        .add_recipient(addr.script_pubkey(), 25_000)
+        .fee_rate(FeeRate::from_sat_per_vb(5.0));
+    let mut psbt = builder.finish().unwrap();
+    let fee = check_fee!(wallet, psbt);
+    assert_eq!(estimate.fee, fee.unwrap_or(0));
+    assert!(estimate.fee_rate >= FeeRate::from_sat_per_vb(5.0));
+
+    assert!(wallet.sign(&mut psbt, SignOptions::default()).unwrap());
+    let tx = psbt.extract_tx();
+    assert!(tx.weight() <= estimate.weight);
+    assert!(estimate.weight.to_wu() - tx.weight().to_wu() <= 4);
+}
+
+#[test]
+fn test_create_tx_min_change_value() {
+    let (mut wallet, _) = get_funded_wallet(get_test_wpkh());
+    let addr = Address::from_str("2N4eQYCbKUHCCTUjBJeHcJp9ok6J2GZsTDt")
+        .unwrap()
+        .assume_checked();
+
+    let mut builder = wallet.build_tx();
+    builder.add_recipient(addr.script_pubkey(), 49_000);
+    let psbt = builder.finish().unwrap();
+    assert_eq!(psbt.unsigned_tx.output.len(), 2);
+
+    let mut builder = wallet.build_tx();
+    builder
+        .add_recipient(addr.script_pubkey(), 49_000)
+        .min_change_value(1_000);
+    let psbt = builder.finish().unwrap();
+    let fee = check_fee!(wallet, psbt);
+    assert_eq!(psbt.unsigned_tx.output.len(), 1);
+    assert_eq!(fee.unwrap_or(0), 1_000);
+}
+
+#[test]
+fn test_create_tx_recipient_batch() {
+    let (mut wallet, _) = get_funded_wallet(get_test_wpkh());
+    let recipients = (0..40)
+        .map(|_| (wallet.get_address(New).script_pubkey(), 1_000))
+        .collect::<Vec<_>>();
+    let mut builder = wallet.build_tx();
+    builder
+        .add_recipients(recipients.clone())
+        .first_output(7)
+        .reject_duplicate_recipients();
+    let psbt = builder.finish().unwrap();
+
+    assert_eq!(psbt.unsigned_tx.output.len(), 41);
+    assert_eq!(psbt.unsigned_tx.output[0].script_pubkey, recipients[7].0);
+
+    let mut builder = wallet.build_tx();
+    builder
+        .add_recipients(recipients.clone())
+        .add_recipient(recipients[3].0.clone(), 1_000)
+        .reject_duplicate_recipients();
+    assert_matches!(builder.finish(), Err(CreateTxError::DuplicateRecipient(40)));
+
+    let mut builder = wallet.build_tx();
+    builder.add_recipients(recipients).first_output(40);
+    assert_matches!(
+        builder.finish(),
+        Err(CreateTxError::InvalidRecipientIndex(40))
+    );
+}
+
+#[test]
+fn test_create_tx_too_large() {
+    let (mut wallet, _) = get_funded_wallet(get_test_wpkh());
+    let addr = wallet.get_address(New);
+    let mut builder = wallet.build_tx();
+    builder
+        .add_recipients((0..3_300).map(|_| (addr.script_pubkey(), 1)))
+        .allow_dust(true)
+        .fee_rate(FeeRate::from_sat_per_vb(0.0));
+    assert_matches!(
+        builder.finish(),
+        Err(CreateTxError::TransactionTooLarge { .. })
+    );
+}
+
 #[test]
 #[should_panic(expected = "InsufficientFunds")]
 fn test_create_tx_absolute_high_fee() {
@@ -1099,6 +1538,37 @@ fn test_create_tx_policy_path_use_csv() {
     assert_eq!(psbt.unsigned_tx.input[0].sequence, Sequence(144));
 }
 
+#[test]
+fn test_list_spendable_by_policy_path() {
+    let (wallet, _) = get_funded_wallet(get_test_a_or_b_plus_csv());
+
+    let external_policy = wallet.policies(KeychainKind::External).unwrap().unwrap();
+    let root_id = external_policy.id;
+    // child #0 is just the key "A"
+    let path_a = vec![(root_id.clone(), vec![0])].into_iter().collect();
+    // child #1 is or(pk(B),older(144))
+    let path_b = vec![(root_id, vec![1])].into_iter().collect();
+
+    let spendable = wallet
+        .list_spendable(KeychainKind::External, &path_a, 2_000)
+        .unwrap();
+    assert_eq!(spendable.len(), 1);
+    assert_eq!(spendable[0].txout.value, 50_000);
+
+    // the utxo was confirmed at 2_000, so it's locked for 144 blocks
+    assert!(wallet
+        .list_spendable(KeychainKind::External, &path_b, 2_143)
+        .unwrap()
+        .is_empty());
+    assert_eq!(
+        wallet
+            .list_spendable(KeychainKind::External, &path_b, 2_144)
+            .unwrap()
+            .len(),
+        1
+    );
+}
+
 #[test]
 fn test_create_tx_policy_path_ignored_subtree_with_csv() {
     let (mut wallet, _) = get_funded_wallet("wsh(or_d(pk(cRjo6jqfVNP33HhSS76UhXETZsGTZYx8FMFvR9kpbtCSV1PmdZdu),or_i(and_v(v:pkh(cVpPVruEDdmutPzisEsYvtST1usBR3ntr8pXSyt6D2YYqXRyPcFW),older(30)),and_v(v:pkh(cMnkdebixpXMPfkcNEjjGin7s94hiehAH4mLbYkZoh9KSiNNmqC8),older(90)))))");
@@ -1467,21 +1937,156 @@ fn test_bump_fee_confirmed_tx() {
 }
 
 #[test]
-#[should_panic(expected = "FeeRateTooLow")]
-fn test_bump_fee_low_fee_rate() {
+fn test_build_cpfp() {
     let (mut wallet, _) = get_funded_wallet(get_test_wpkh());
     let addr = wallet.get_address(New);
     let mut builder = wallet.build_tx();
-    builder
-        .add_recipient(addr.script_pubkey(), 25_000)
-        .enable_rbf();
-    let psbt = builder.finish().unwrap();
-
-    let tx = psbt.extract_tx();
-    let txid = tx.txid();
+    builder.add_recipient(addr.script_pubkey(), 25_000);
+    let parent_psbt = builder.finish().unwrap();
+    let parent_fee = parent_psbt.fee_amount().unwrap();
 
+    let parent = parent_psbt.extract_tx();
+    let parent_txid = parent.txid();
     wallet
-        .insert_tx(tx, ConfirmationTime::Unconfirmed { last_seen: 0 })
+        .insert_tx(
+            parent.clone(),
+            ConfirmationTime::Unconfirmed { last_seen: 0 },
+        )
+        .unwrap();
+
+    wallet.commit().unwrap();
+
+    let package_fee_rate = FeeRate::from_sat_per_vb(20.0);
+    // the change address of the child is only revealed when finishing the builder
+    let change_index = wallet.derivation_index(KeychainKind::Internal);
+    drop(wallet.build_cpfp(parent_txid, package_fee_rate).unwrap());
+    assert_eq!(
+        wallet.derivation_index(KeychainKind::Internal),
+        change_index
+    );
+
+    let mut psbt = wallet
+        .build_cpfp(parent_txid, package_fee_rate)
+        .unwrap()
+        .finish()
+        .unwrap();
+    // and persisted along with it
+    assert_ne!(
+        wallet.derivation_index(KeychainKind::Internal),
+        change_index
+    );
+    assert!(bdk_chain::Append::is_empty(wallet.staged()));
+    // both the outputs of the parent belong to the wallet
+    assert_eq!(psbt.unsigned_tx.input.len(), 2);
+    assert!(psbt
+        .unsigned_tx
+        .input
+        .iter()
+        .all(|txin| txin.previous_output.txid == parent_txid));
+    assert_eq!(psbt.unsigned_tx.output.len(), 1);
+    let child_fee = psbt.fee_amount().unwrap();
+
+    let finalized = wallet.sign(&mut psbt, SignOptions::default()).unwrap();
+    assert!(finalized);
+    let child = psbt.extract_tx();
+    let package_weight = parent.weight() + child.weight();
+    assert!(FeeRate::from_wu(parent_fee + child_fee, package_weight) >= package_fee_rate);
+}
+
+#[test]
+fn test_build_cpfp_unconfirmed_ancestors() {
+    let (mut wallet, _) = get_funded_wallet(get_test_wpkh());
+    let addr = wallet.get_address(New);
+    let mut builder = wallet.build_tx();
+    builder
+        .add_recipient(addr.script_pubkey(), 25_000)
+        .fee_rate(FeeRate::from_sat_per_vb(1.0));
+    let grandparent_psbt = builder.finish().unwrap();
+    let grandparent_fee = grandparent_psbt.fee_amount().unwrap();
+    let grandparent = grandparent_psbt.extract_tx();
+    wallet
+        .insert_tx(
+            grandparent.clone(),
+            ConfirmationTime::Unconfirmed { last_seen: 0 },
+        )
+        .unwrap();
+
+    // the parent can only spend the outputs of the unconfirmed grandparent
+    let mut builder = wallet.build_tx();
+    builder
+        .add_recipient(addr.script_pubkey(), 10_000)
+        .fee_rate(FeeRate::from_sat_per_vb(1.0));
+    let parent_psbt = builder.finish().unwrap();
+    let parent_fee = parent_psbt.fee_amount().unwrap();
+    let parent = parent_psbt.extract_tx();
+    let parent_txid = parent.txid();
+    wallet
+        .insert_tx(
+            parent.clone(),
+            ConfirmationTime::Unconfirmed { last_seen: 0 },
+        )
+        .unwrap();
+
+    let package_fee_rate = FeeRate::from_sat_per_vb(20.0);
+    let mut psbt = wallet
+        .build_cpfp(parent_txid, package_fee_rate)
+        .unwrap()
+        .finish()
+        .unwrap();
+    let child_fee = psbt.fee_amount().unwrap();
+    wallet.sign(&mut psbt, SignOptions::default()).unwrap();
+    let child = psbt.extract_tx();
+
+    // the child also pays for the low fee rate of the grandparent
+    let package_weight = grandparent.weight() + parent.weight() + child.weight();
+    assert!(
+        FeeRate::from_wu(grandparent_fee + parent_fee + child_fee, package_weight)
+            >= package_fee_rate
+    );
+}
+
+#[test]
+fn test_build_cpfp_errors() {
+    let (mut wallet, txid) = get_funded_wallet(get_test_wpkh());
+    assert_matches!(
+        wallet.build_cpfp(txid, FeeRate::from_sat_per_vb(20.0)),
+        Err(bdk::wallet::error::BuildCpfpError::TransactionConfirmed(_))
+    );
+
+    let sendto_address = Address::from_str("bcrt1q3qtze4ys45tgdvguj66zrk4fu6hq3a3v9pfly5")
+        .unwrap()
+        .assume_checked();
+    let mut builder = wallet.build_tx();
+    builder
+        .drain_wallet()
+        .drain_to(sendto_address.script_pubkey());
+    let tx = builder.finish().unwrap().extract_tx();
+    let txid = tx.txid();
+    wallet
+        .insert_tx(tx, ConfirmationTime::Unconfirmed { last_seen: 0 })
+        .unwrap();
+    assert_matches!(
+        wallet.build_cpfp(txid, FeeRate::from_sat_per_vb(20.0)),
+        Err(bdk::wallet::error::BuildCpfpError::NoSpendableOutputs(_))
+    );
+}
+
+#[test]
+#[should_panic(expected = "FeeRateTooLow")]
+fn test_bump_fee_low_fee_rate() {
+    let (mut wallet, _) = get_funded_wallet(get_test_wpkh());
+    let addr = wallet.get_address(New);
+    let mut builder = wallet.build_tx();
+    builder
+        .add_recipient(addr.script_pubkey(), 25_000)
+        .enable_rbf();
+    let psbt = builder.finish().unwrap();
+
+    let tx = psbt.extract_tx();
+    let txid = tx.txid();
+
+    wallet
+        .insert_tx(tx, ConfirmationTime::Unconfirmed { last_seen: 0 })
         .unwrap();
 
     let mut builder = wallet.build_fee_bump(txid).unwrap();
@@ -3478,6 +4083,266 @@ fn test_allow_dust_limit() {
     assert!(builder.finish().is_ok());
 }
 
+#[test]
+fn test_create_tx_op_return_size() {
+    let (mut wallet, _) = get_funded_wallet(get_test_wpkh());
+    let addr = wallet.get_address(New);
+
+    let data = PushBytesBuf::try_from(vec![0xaa; 80]).unwrap();
+    let mut builder = wallet.build_tx();
+    builder
+        .add_recipient(addr.script_pubkey(), 25_000)
+        .add_data(&data);
+    let psbt = builder.finish().unwrap();
+    assert!(psbt
+        .unsigned_tx
+        .output
+        .iter()
+        .any(|o| o.script_pubkey.is_op_return() && o.script_pubkey.len() == 83));
+
+    let data = PushBytesBuf::try_from(vec![0xaa; 81]).unwrap();
+    let mut builder = wallet.build_tx();
+    builder
+        .add_recipient(addr.script_pubkey(), 25_000)
+        .add_data(&data);
+    assert_matches!(
+        builder.finish(),
+        Err(CreateTxError::OpReturnTooLarge { index: 1, size: 84 })
+    );
+}
+
+#[test]
+fn test_classify_psbt() {
+    use bdk::wallet::tx_builder::TxOrdering;
+    use bdk::wallet::OutputOwnership;
+
+    let (mut wallet, _) =
+        get_funded_wallet_with_change(get_test_wpkh(), Some(get_test_tr_single_sig_xprv()));
+    let receive = wallet.get_address(New);
+    let external = Address::from_str("2N4eQYCbKUHCCTUjBJeHcJp9ok6J2GZsTDt")
+        .unwrap()
+        .assume_checked();
+    let mut builder = wallet.build_tx();
+    builder
+        .add_recipient(receive.script_pubkey(), 10_000)
+        .add_recipient(external.script_pubkey(), 10_000)
+        .ordering(TxOrdering::Untouched);
+    let psbt = builder.finish().unwrap();
+
+    let expected = vec![
+        OutputOwnership::Receive {
+            index: receive.index,
+        },
+        OutputOwnership::External,
+        OutputOwnership::Change { index: 0 },
+    ];
+    assert_eq!(wallet.classify_psbt(&psbt), expected);
+
+    // a wallet with the same descriptors that hasn't revealed any address relies on the key
+    // origins of the outputs
+    let other = Wallet::new_no_persist(
+        get_test_wpkh(),
+        Some(get_test_tr_single_sig_xprv()),
+        Network::Regtest,
+    )
+    .unwrap();
+    assert_eq!(other.classify_psbt(&psbt), expected);
+}
+
+#[test]
+fn test_combine_psbts() {
+    let (mut wallet, _) = get_funded_wallet(get_test_wpkh());
+    let addr = Address::from_str("2N4eQYCbKUHCCTUjBJeHcJp9ok6J2GZsTDt")
+        .unwrap()
+        .assume_checked();
+    let mut builder = wallet.build_tx();
+    builder.add_recipient(addr.script_pubkey(), 25_000);
+    let psbt = builder.finish().unwrap();
+
+    let mut signed = psbt.clone();
+    let sign_options = SignOptions {
+        try_finalize: false,
+        ..Default::default()
+    };
+    assert!(!wallet.sign(&mut signed, sign_options).unwrap());
+
+    let mut combined = wallet
+        .combine_psbts(&[psbt.clone(), signed.clone()])
+        .unwrap();
+    assert_eq!(combined.inputs[0].partial_sigs.len(), 1);
+    assert!(wallet
+        .finalize_psbt(&mut combined, SignOptions::default())
+        .unwrap());
+
+    assert_matches!(wallet.combine_psbts(&[]), Err(CombinePsbtError::NoPsbts));
+
+    let mut builder = wallet.build_tx();
+    builder.add_recipient(addr.script_pubkey(), 10_000);
+    let other = builder.finish().unwrap();
+    assert_matches!(
+        wallet.combine_psbts(&[psbt.clone(), other]),
+        Err(CombinePsbtError::Psbt(_))
+    );
+
+    // a signature by a key that isn't in the descriptor
+    let mut mutated = psbt.clone();
+    let sig = *signed.inputs[0].partial_sigs.values().next().unwrap();
+    let other_key = bitcoin::PublicKey::from_str(
+        "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+    )
+    .unwrap();
+    mutated.inputs[0].partial_sigs.insert(other_key, sig);
+    assert_matches!(
+        wallet.combine_psbts(&[psbt.clone(), mutated]),
+        Err(CombinePsbtError::UnexpectedSignature(_))
+    );
+
+    // a signature by the right key that doesn't match the transaction
+    let mut invalid = signed;
+    for sig in invalid.inputs[0].partial_sigs.values_mut() {
+        sig.hash_ty = EcdsaSighashType::None;
+    }
+    assert_matches!(
+        wallet.combine_psbts(&[psbt, invalid]),
+        Err(CombinePsbtError::InvalidSignature(_))
+    );
+}
+
+#[test]
+fn test_combine_psbts_taproot_key_spend() {
+    let (mut wallet, _) = get_funded_wallet(get_test_tr_single_sig());
+    let addr = wallet.get_address(New);
+    let mut builder = wallet.build_tx();
+    builder.add_recipient(addr.script_pubkey(), 25_000);
+    let psbt = builder.finish().unwrap();
+
+    let mut signed = psbt.clone();
+    let sign_options = SignOptions {
+        try_finalize: false,
+        ..Default::default()
+    };
+    wallet.sign(&mut signed, sign_options).unwrap();
+    assert!(signed.inputs[0].tap_key_sig.is_some());
+
+    let combined = wallet
+        .combine_psbts(&[psbt.clone(), signed.clone()])
+        .unwrap();
+    assert_eq!(combined.inputs[0].tap_key_sig, signed.inputs[0].tap_key_sig);
+
+    let mut invalid = signed;
+    invalid.inputs[0].tap_key_sig.as_mut().unwrap().hash_ty = TapSighashType::All;
+    assert_matches!(
+        wallet.combine_psbts(&[psbt, invalid]),
+        Err(CombinePsbtError::InvalidSignature(_))
+    );
+}
+
+#[test]
+fn test_combine_psbts_foreign_input() {
+    let (mut wallet1, _) = get_funded_wallet(get_test_wpkh());
+    let (wallet2, _) =
+        get_funded_wallet("wpkh(cVbZ8ovhye9AoAHFsqobCf7LxbXDAECy9Kb8TZdfsDYMZGBUyCnm)");
+
+    let addr = Address::from_str("2N1Ffz3WaNzbeLFBb51xyFMHYSEUXcbiSoX")
+        .unwrap()
+        .assume_checked();
+    let utxo = wallet2.list_unspent().next().expect("must take!");
+    #[allow(deprecated)]
+    let foreign_utxo_satisfaction = wallet2
+        .get_descriptor_for_keychain(KeychainKind::External)
+        .max_satisfaction_weight()
+        .unwrap();
+    let psbt_input = psbt::Input {
+        witness_utxo: Some(utxo.txout.clone()),
+        ..Default::default()
+    };
+
+    let mut builder = wallet1.build_tx();
+    builder
+        .add_recipient(addr.script_pubkey(), 60_000)
+        .only_witness_utxo()
+        .add_foreign_utxo(utxo.outpoint, psbt_input, foreign_utxo_satisfaction)
+        .unwrap();
+    let psbt = builder.finish().unwrap();
+
+    let sign_options = SignOptions {
+        trust_witness_utxo: true,
+        try_finalize: false,
+        ..Default::default()
+    };
+    let mut signed1 = psbt.clone();
+    wallet1.sign(&mut signed1, sign_options.clone()).unwrap();
+    let mut signed2 = psbt;
+    wallet2.sign(&mut signed2, sign_options).unwrap();
+
+    // the input signed by the other wallet is left unchecked
+    let mut combined = wallet1.combine_psbts(&[signed1, signed2]).unwrap();
+    assert!(combined
+        .inputs
+        .iter()
+        .all(|input| input.partial_sigs.len() == 1));
+    assert!(!wallet1
+        .finalize_psbt(&mut combined, SignOptions::default())
+        .unwrap());
+    assert!(wallet2
+        .finalize_psbt(&mut combined, SignOptions::default())
+        .unwrap());
+}
+
+#[test]
+fn test_decaying_multisig_recovery() {
+    use bdk::template::DecayingMultisig;
+
+    let keys = [
+        "cVpPVruEDdmutPzisEsYvtST1usBR3ntr8pXSyt6D2YYqXRyPcFW",
+        "02e96fe52ef0e22d2f131dd425ce1893073a3c6ad20e8cac36726393dfb4856a4c",
+        "03a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd",
+    ];
+    let descriptor = format!(
+        "wsh(thresh(2,pk({}),s:pk({}),s:pk({}),sndv:older(144)))",
+        keys[0], keys[1], keys[2]
+    );
+    let (mut wallet, _) = get_funded_wallet(&descriptor);
+    let template = Wallet::new_no_persist(
+        DecayingMultisig {
+            threshold: 2,
+            keys: keys.to_vec(),
+            timelocks: vec![144],
+        },
+        None,
+        Network::Regtest,
+    )
+    .unwrap();
+    assert_eq!(
+        template.public_descriptor(KeychainKind::External),
+        wallet.public_descriptor(KeychainKind::External)
+    );
+
+    let root_id = wallet.policies(KeychainKind::External).unwrap().unwrap().id;
+    let addr = wallet.get_address(New);
+    let mut create_tx = |items: Vec<usize>| {
+        let path = vec![(root_id.clone(), items)].into_iter().collect();
+        let mut builder = wallet.build_tx();
+        builder
+            .drain_wallet()
+            .drain_to(addr.script_pubkey())
+            .policy_path(path, KeychainKind::External);
+        builder.finish().unwrap()
+    };
+    let mut two_keys = create_tx(vec![0, 1]);
+    let mut recovery = create_tx(vec![0, 3]);
+    assert_ne!(two_keys.unsigned_tx.input[0].sequence, Sequence(144));
+    assert_eq!(recovery.unsigned_tx.input[0].sequence, Sequence(144));
+
+    // the wallet only has the first key, and the coins were confirmed at height 2000
+    assert!(!wallet.sign(&mut two_keys, SignOptions::default()).unwrap());
+    let sign_options = SignOptions {
+        assume_height: Some(2_144),
+        ..Default::default()
+    };
+    assert!(wallet.sign(&mut recovery, sign_options).unwrap());
+}
+
 #[test]
 fn test_fee_rate_sign_no_grinding_high_r() {
     // Our goal is to obtain a transaction with a signature with high-R (71 bytes
@@ -3577,6 +4442,32 @@ fn test_fee_rate_sign_grinding_low_r() {
     assert_fee_rate!(psbt, fee.unwrap_or(0), fee_rate);
 }
 
+#[test]
+fn test_sign_extra_entropy() {
+    for descriptor in [
+        "wpkh(tprv8ZgxMBicQKsPd3EupYiPRhaMooHKUHJxNsTfYuScep13go8QFfHdtkG9nRkFGb7busX4isf6X9dURGCoKgitaApQ6MupRhZMcELAxTBRJgS/*)",
+        get_test_tr_single_sig_xprv(),
+    ] {
+        let (mut wallet, _) = get_funded_wallet(descriptor);
+        let addr = wallet.get_address(New);
+        let mut builder = wallet.build_tx();
+        builder.drain_to(addr.script_pubkey()).drain_wallet();
+        let psbt = builder.finish().unwrap();
+
+        let sign = |extra_entropy| {
+            let mut psbt = psbt.clone();
+            let sign_options = SignOptions {
+                extra_entropy,
+                ..Default::default()
+            };
+            wallet.sign(&mut psbt, sign_options).unwrap();
+            psbt.extract_tx()
+        };
+        assert_eq!(sign(Some([1; 32])), sign(Some([1; 32])));
+        assert_ne!(sign(Some([1; 32])), sign(Some([2; 32])));
+    }
+}
+
 // #[cfg(feature = "test-hardware-signer")]
 // #[test]
 // fn test_hardware_signer() {
@@ -3697,3 +4588,730 @@ fn test_tx_cancellation() {
         .unwrap();
     assert_eq!(change_derivation_4, (KeychainKind::Internal, 2));
 }
+
+#[test]
+fn test_multisig_setup_signers() {
+    use bdk::wallet::multisig::{Cosigner, MultisigError, MultisigScriptType, MultisigSetup};
+    use bitcoin::bip32::{DerivationPath, ExtendedPrivKey, ExtendedPubKey};
+    use bitcoin::secp256k1::Secp256k1;
+
+    let secp = Secp256k1::new();
+    let path = DerivationPath::from_str("m/48'/1'/0'/2'").unwrap();
+    let masters = [
+        "tprv8ZgxMBicQKsPd3EupYiPRhaMooHKUHJxNsTfYuScep13go8QFfHdtkG9nRkFGb7busX4isf6X9dURGCoKgitaApQ6MupRhZMcELAxTBRJgS",
+        "tprv8ZgxMBicQKsPdDArR4xSAECuVxeX1jwwSXR4ApKbkYgZiziDc4LdBy2WvJeGDfUSE4UT4hHhbgEwbdq8ajjUHiKDegkwrNU6V55CxcxonVN",
+    ]
+    .map(|key| ExtendedPrivKey::from_str(key).unwrap());
+    let accounts = masters.map(|master| master.derive_priv(&secp, &path).unwrap());
+    let cosigners = masters
+        .iter()
+        .zip(&accounts)
+        .map(|(master, account)| Cosigner {
+            fingerprint: master.fingerprint(&secp),
+            derivation_path: path.clone(),
+            xpub: ExtendedPubKey::from_priv(&secp, account),
+        })
+        .collect::<Vec<_>>();
+    let (first, second) = (cosigners[0].fingerprint, cosigners[1].fingerprint);
+    let setup = MultisigSetup::new("test", 2, MultisigScriptType::Wsh, cosigners).unwrap();
+
+    // the wallet of the first cosigner, which has its private key
+    let descriptor = setup
+        .descriptor(KeychainKind::External)
+        .unwrap()
+        .to_string()
+        .replace(
+            &setup.cosigners()[0].xpub.to_string(),
+            &accounts[0].to_string(),
+        );
+    let descriptor = descriptor.split('#').next().unwrap();
+    let (mut wallet, _) = get_funded_wallet(descriptor);
+    let addr = Address::from_str("2N4eQYCbKUHCCTUjBJeHcJp9ok6J2GZsTDt")
+        .unwrap()
+        .assume_checked();
+    let mut builder = wallet.build_tx();
+    builder.add_recipient(addr.script_pubkey(), 25_000);
+    let mut psbt = builder.finish().unwrap();
+    assert!(setup
+        .signers(&psbt)
+        .iter()
+        .all(|signers| signers.is_empty()));
+
+    let sign_options = SignOptions {
+        try_finalize: false,
+        ..Default::default()
+    };
+    wallet.sign(&mut psbt, sign_options.clone()).unwrap();
+    setup.verify_psbt(&psbt).unwrap();
+    assert!(setup
+        .signers(&psbt)
+        .iter()
+        .all(|signers| signers.len() == 1 && signers.contains(&first)));
+    assert_eq!(setup.missing_signers(&psbt), vec![second]);
+    assert!(!setup.is_fully_signed(&psbt));
+
+    // the wallet of the second cosigner completes the signatures
+    let descriptor = setup
+        .descriptor(KeychainKind::External)
+        .unwrap()
+        .to_string()
+        .replace(
+            &setup.cosigners()[1].xpub.to_string(),
+            &accounts[1].to_string(),
+        );
+    let descriptor = descriptor.split('#').next().unwrap();
+    let (second_wallet, _) = get_funded_wallet(descriptor);
+    second_wallet.sign(&mut psbt, sign_options.clone()).unwrap();
+    assert!(setup.missing_signers(&psbt).is_empty());
+    assert!(setup.is_fully_signed(&psbt));
+
+    // signatures that don't match the transaction don't count
+    let mut tampered = psbt.clone();
+    for sig in tampered.inputs[0].partial_sigs.values_mut() {
+        sig.hash_ty = EcdsaSighashType::None;
+    }
+    assert!(setup.missing_signers(&tampered).is_empty());
+    assert!(!setup.is_fully_signed(&tampered));
+
+    // a PSBT spending from another wallet is rejected
+    let (mut other, _) = get_funded_wallet(get_test_wpkh());
+    let mut builder = other.build_tx();
+    builder.add_recipient(addr.script_pubkey(), 25_000);
+    let psbt = builder.finish().unwrap();
+    assert_matches!(
+        setup.verify_psbt(&psbt),
+        Err(MultisigError::ForeignInput(0))
+    );
+}
+
+#[test]
+fn test_sign_message() {
+    use bdk::wallet::message::{verify_message, MessageSignatureFormat, MessageSigningError};
+
+    for descriptor in [get_test_wpkh(), get_test_tr_single_sig_xprv()] {
+        let mut wallet = Wallet::new_no_persist(descriptor, None, Network::Regtest).unwrap();
+        let address = wallet.get_address(New).address;
+
+        let signature = wallet
+            .sign_message(&address, "message", MessageSignatureFormat::Bip322Simple)
+            .unwrap();
+        assert!(verify_message(&address, "message", &signature).unwrap());
+        assert!(!verify_message(&address, "other message", &signature).unwrap());
+    }
+
+    let mut wallet = Wallet::new_no_persist(get_test_wpkh(), None, Network::Regtest).unwrap();
+    let address = wallet.get_address(New).address;
+    let signature = wallet
+        .sign_message(&address, "message", MessageSignatureFormat::Legacy)
+        .unwrap();
+    assert!(verify_message(&address, "message", &signature).unwrap());
+
+    let mut tr_wallet =
+        Wallet::new_no_persist(get_test_tr_single_sig_xprv(), None, Network::Regtest).unwrap();
+    let tr_address = tr_wallet.get_address(New).address;
+    assert_matches!(
+        tr_wallet.sign_message(&tr_address, "message", MessageSignatureFormat::Legacy),
+        Err(MessageSigningError::UnsupportedAddress)
+    );
+    assert_matches!(
+        wallet.sign_message(&tr_address, "message", MessageSignatureFormat::Legacy),
+        Err(MessageSigningError::UnknownAddress)
+    );
+}
+
+#[test]
+fn test_proof_of_reserves() {
+    use bdk::wallet::reserves::ProofError;
+
+    let (wallet, _) = get_funded_wallet(get_test_wpkh());
+    let message = "This belongs to me.";
+    let proof = wallet.create_proof(message).unwrap();
+    // the whole previous transaction is included, like in any other PSBT
+    assert!(proof.inputs[1].non_witness_utxo.is_some());
+
+    assert_eq!(
+        wallet.verify_proof(&proof, message, None).unwrap(),
+        wallet.get_balance().total()
+    );
+    assert_matches!(
+        wallet.verify_proof(&proof, "Another message", None),
+        Err(ProofError::ChallengeInputMismatch)
+    );
+    // the only UTXO was confirmed at height 2_000
+    assert_matches!(
+        wallet.verify_proof(&proof, message, Some(1_500)),
+        Err(ProofError::NonSpendableInput(1))
+    );
+
+    let mut tampered = proof.clone();
+    tampered.unsigned_tx.output[0].value += 1;
+    assert_matches!(
+        wallet.verify_proof(&tampered, message, None),
+        Err(ProofError::InAndOutValueNotEqual)
+    );
+
+    // the same UTXO, or the challenge, can't be spent twice
+    for index in [1, 0] {
+        let mut duplicated = proof.clone();
+        let txin = duplicated.unsigned_tx.input[index].clone();
+        let psbt_input = duplicated.inputs[index].clone();
+        duplicated.unsigned_tx.input.push(txin);
+        duplicated.inputs.push(psbt_input);
+        assert_matches!(
+            wallet.verify_proof(&duplicated, message, None),
+            Err(ProofError::DuplicateInput(2))
+        );
+    }
+
+    // a signature that doesn't commit to the whole transaction
+    let mut resigned = proof.clone();
+    resigned.inputs[1].final_script_sig = None;
+    resigned.inputs[1].final_script_witness = None;
+    resigned.inputs[1].sighash_type = Some(EcdsaSighashType::None.into());
+    let sign_options = SignOptions {
+        trust_witness_utxo: true,
+        allow_all_sighashes: true,
+        ..Default::default()
+    };
+    assert!(wallet.sign(&mut resigned, sign_options).unwrap());
+    resigned.inputs[1].sighash_type = None;
+    assert_matches!(
+        wallet.verify_proof(&resigned, message, None),
+        Err(ProofError::UnsupportedSighashType(1))
+    );
+
+    let empty = Wallet::new_no_persist(get_test_wpkh(), None, Network::Regtest).unwrap();
+    assert_matches!(empty.create_proof(message), Err(ProofError::NoUtxos));
+}
+
+#[test]
+fn test_proof_of_reserves_pkh() {
+    let (wallet, _) =
+        get_funded_wallet("pkh(cVpPVruEDdmutPzisEsYvtST1usBR3ntr8pXSyt6D2YYqXRyPcFW)");
+    let message = "This belongs to me.";
+    let proof = wallet.create_proof(message).unwrap();
+    assert!(proof.inputs[1].non_witness_utxo.is_some());
+    assert!(proof.inputs[1].final_script_sig.is_some());
+
+    assert_eq!(
+        wallet.verify_proof(&proof, message, None).unwrap(),
+        wallet.get_balance().total()
+    );
+}
+
+#[test]
+fn test_watch_only_add_signers() {
+    use bdk::descriptor::{Descriptor, DescriptorPublicKey};
+    use bitcoin::secp256k1::Secp256k1;
+
+    let secp = Secp256k1::new();
+    let (public, keymap) = Descriptor::<DescriptorPublicKey>::parse_descriptor(
+        &secp,
+        "wpkh(tprv8ZgxMBicQKsPd3EupYiPRhaMooHKUHJxNsTfYuScep13go8QFfHdtkG9nRkFGb7busX4isf6X9dURGCoKgitaApQ6MupRhZMcELAxTBRJgS/*)",
+    )
+    .unwrap();
+    let fingerprint = keymap.keys().next().unwrap().master_fingerprint();
+
+    let (mut wallet, _) = get_funded_wallet(&public.to_string());
+    assert!(wallet.is_watch_only());
+
+    let addr = wallet.get_address(New);
+    let mut builder = wallet.build_tx();
+    builder.drain_to(addr.script_pubkey()).drain_wallet();
+    let mut psbt = builder.finish().unwrap();
+    assert_matches!(
+        wallet.sign(&mut psbt, Default::default()),
+        Err(SignerError::NoSigners(fingerprints)) if fingerprints == vec![fingerprint]
+    );
+
+    wallet.add_signers_from_keymap(KeychainKind::External, keymap);
+    assert!(!wallet.is_watch_only());
+    assert!(wallet.sign(&mut psbt, Default::default()).unwrap());
+}
+
+#[test]
+fn test_watch_only_finalizes_signed_psbt() {
+    use bdk::descriptor::{Descriptor, DescriptorPublicKey};
+    use bitcoin::secp256k1::Secp256k1;
+
+    let secp = Secp256k1::new();
+    let private = "wpkh(tprv8ZgxMBicQKsPd3EupYiPRhaMooHKUHJxNsTfYuScep13go8QFfHdtkG9nRkFGb7busX4isf6X9dURGCoKgitaApQ6MupRhZMcELAxTBRJgS/*)";
+    let (public, _) = Descriptor::<DescriptorPublicKey>::parse_descriptor(&secp, private).unwrap();
+
+    let (mut wallet, _) = get_funded_wallet(&public.to_string());
+    assert!(wallet.is_watch_only());
+    let addr = wallet.get_address(New);
+    let mut builder = wallet.build_tx();
+    builder.drain_to(addr.script_pubkey()).drain_wallet();
+    let mut psbt = builder.finish().unwrap();
+
+    // the cosigner holding the key signs without finalizing
+    let (cosigner, _) = get_funded_wallet(private);
+    let sign_options = SignOptions {
+        try_finalize: false,
+        ..Default::default()
+    };
+    assert!(!cosigner.sign(&mut psbt, sign_options).unwrap());
+    assert!(psbt.inputs[0].final_script_witness.is_none());
+
+    assert!(wallet.sign(&mut psbt, Default::default()).unwrap());
+    assert!(psbt.inputs[0].final_script_witness.is_some());
+}
+
+#[test]
+fn test_verify_address() {
+    use bitcoin::bip32::{DerivationPath, Fingerprint};
+
+    let mut wallet = Wallet::new_no_persist("wpkh([d34db33f/44'/0'/0']tpubDEnoLuPdBep9bzw5LoGYpsxUQYheRQ9gcgrJhJEcdKFB9cWQRyYmkCyRoTqeD4tJYiVVgt6A3rN6rWn9RYhR9sBsGxji29LYWHuKKbdb1ev/0/*)", None, Network::Regtest).unwrap();
+    let address = wallet.get_address(Peek(5)).address;
+
+    let origin = wallet.verify_address(&address, 0..10).unwrap();
+    assert_eq!(origin.keychain, KeychainKind::External);
+    assert_eq!(origin.index, 5);
+    assert_eq!(
+        origin.derivation_paths.into_iter().collect::<Vec<_>>(),
+        vec![(
+            Fingerprint::from_str("d34db33f").unwrap(),
+            DerivationPath::from_str("m/44'/0'/0'/0/5").unwrap()
+        )]
+    );
+
+    // outside of the range
+    assert_eq!(wallet.verify_address(&address, 0..5), None);
+    // not derived from the wallet's descriptor
+    let foreign = Address::from_str("bcrt1q3qtze4ys45tgdvguj66zrk4fu6hq3a3v9pfly5")
+        .unwrap()
+        .assume_checked();
+    assert_eq!(wallet.verify_address(&foreign, 0..100), None);
+}
+
+#[test]
+fn test_gap_limit() {
+    let mut wallet =
+        Wallet::new_no_persist(get_test_tr_single_sig_xprv(), None, Network::Testnet).unwrap();
+    wallet.set_gap_limit(2);
+    assert_eq!(wallet.gap_limit(), Some(2));
+
+    assert_eq!(wallet.get_address(NewWithinGapLimit).index, 0);
+    assert_eq!(wallet.get_address(NewWithinGapLimit).index, 1);
+    // two unused addresses already revealed
+    assert_eq!(wallet.get_address(NewWithinGapLimit).index, 0);
+
+    receive_output(
+        &mut wallet,
+        25_000,
+        ConfirmationTime::Unconfirmed { last_seen: 0 },
+    );
+    assert_eq!(wallet.get_address(NewWithinGapLimit).index, 2);
+    assert_eq!(wallet.get_address(NewWithinGapLimit).index, 1);
+
+    let unused = wallet
+        .list_unused_addresses(KeychainKind::External)
+        .map(|info| info.index)
+        .collect::<Vec<_>>();
+    assert_eq!(unused, vec![1, 2]);
+
+    // `New` always reveals a fresh address
+    assert_eq!(wallet.get_address(New).index, 3);
+    assert_eq!(wallet.get_address(New).index, 4);
+}
+
+#[test]
+fn test_gap_limit_is_persisted() {
+    let temp_dir = tempfile::tempdir().expect("must create tempdir");
+    let file_path = temp_dir.path().join("store.db");
+
+    {
+        let db = bdk_file_store::Store::create_new(DB_MAGIC, &file_path).expect("must create db");
+        let mut wallet =
+            Wallet::new(get_test_wpkh(), None, db, Network::Testnet).expect("must init wallet");
+        wallet.set_gap_limit(5);
+        assert!(wallet.commit().unwrap());
+    }
+
+    let db = bdk_file_store::Store::open(DB_MAGIC, &file_path).expect("must recover db");
+    let wallet = Wallet::load(get_test_wpkh(), None, db).expect("must recover wallet");
+    assert_eq!(wallet.gap_limit(), Some(5));
+}
+
+#[test]
+fn test_list_transactions() {
+    use bdk::wallet::{TxDirection, TxFilter, TxSortOrder};
+
+    let (mut wallet, txid1) = get_funded_wallet(get_test_wpkh());
+    let txid0 = wallet.get_tx(txid1).unwrap().tx_node.tx.input[0]
+        .previous_output
+        .txid;
+    let txid2 = receive_output(
+        &mut wallet,
+        10_000,
+        ConfirmationTime::Unconfirmed { last_seen: 0 },
+    )
+    .txid;
+
+    let list = |filter: TxFilter| {
+        wallet
+            .list_transactions(&filter)
+            .into_iter()
+            .map(|canonical_tx| canonical_tx.tx_node.txid)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(list(TxFilter::default()), vec![txid2, txid1, txid0]);
+    assert_eq!(
+        list(TxFilter {
+            confirmed_only: true,
+            ..Default::default()
+        }),
+        vec![txid1, txid0]
+    );
+    assert_eq!(
+        list(TxFilter {
+            direction: Some(TxDirection::Incoming),
+            ordering: TxSortOrder::OldestFirst,
+            ..Default::default()
+        }),
+        vec![txid0, txid2]
+    );
+    // tx1 sends 26_000 out of the wallet
+    assert_eq!(
+        list(TxFilter {
+            min_amount: Some(20_000),
+            ..Default::default()
+        }),
+        vec![txid1, txid0]
+    );
+    assert_eq!(
+        list(TxFilter {
+            height_range: Some(1_500..=2_500),
+            ..Default::default()
+        }),
+        vec![txid1]
+    );
+    assert_eq!(
+        list(TxFilter {
+            offset: 1,
+            limit: Some(1),
+            ..Default::default()
+        }),
+        vec![txid1]
+    );
+}
+
+#[test]
+fn test_unconfirmed_ancestry() {
+    let (mut wallet, funding_txid) =
+        get_funded_wallet_with_change(get_test_wpkh(), Some(get_test_tr_single_sig_xprv()));
+    // a foreign transaction paying the wallet
+    let parent = receive_output(
+        &mut wallet,
+        10_000,
+        ConfirmationTime::Unconfirmed { last_seen: 0 },
+    );
+    // a wallet transaction spending it, with its change going back to the wallet
+    let child = Transaction {
+        version: 1,
+        lock_time: absolute::LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: parent,
+            ..Default::default()
+        }],
+        output: vec![TxOut {
+            script_pubkey: wallet.get_internal_address(New).script_pubkey(),
+            value: 9_000,
+        }],
+    };
+    let child_txid = child.txid();
+    wallet
+        .insert_tx(child, ConfirmationTime::Unconfirmed { last_seen: 0 })
+        .unwrap();
+
+    assert_eq!(wallet.unconfirmed_ancestors(child_txid), vec![parent.txid]);
+    assert_eq!(
+        wallet.unconfirmed_descendants(parent.txid),
+        vec![child_txid]
+    );
+    // the funding transactions are confirmed
+    assert!(wallet.unconfirmed_descendants(funding_txid).is_empty());
+
+    // the change is trusted, but depends on a foreign unconfirmed transaction
+    assert_eq!(wallet.get_balance().trusted_spendable(), 59_000);
+    assert_eq!(wallet.effective_spendable_balance(), 50_000);
+}
+
+#[test]
+fn test_conflict_status() {
+    use bdk::wallet::ConflictStatus;
+
+    let (mut wallet, txid) = get_funded_wallet(get_test_wpkh());
+    let spend = |wallet: &mut Wallet, value: u64| Transaction {
+        version: 1,
+        lock_time: absolute::LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint { txid, vout: 0 },
+            ..Default::default()
+        }],
+        output: vec![TxOut {
+            script_pubkey: wallet.get_address(New).script_pubkey(),
+            value,
+        }],
+    };
+    let original = spend(&mut wallet, 49_000);
+    let replacement = spend(&mut wallet, 48_000);
+    let child = Transaction {
+        version: 1,
+        lock_time: absolute::LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint {
+                txid: original.txid(),
+                vout: 0,
+            },
+            ..Default::default()
+        }],
+        output: vec![TxOut {
+            script_pubkey: wallet.get_address(New).script_pubkey(),
+            value: 48_500,
+        }],
+    };
+    let (original_txid, replacement_txid, child_txid) =
+        (original.txid(), replacement.txid(), child.txid());
+
+    for (tx, last_seen) in [(original, 1), (child, 1), (replacement, 2)] {
+        wallet
+            .insert_tx(tx, ConfirmationTime::Unconfirmed { last_seen })
+            .unwrap();
+    }
+
+    assert_eq!(
+        wallet.get_conflict_status(original_txid),
+        Some(ConflictStatus::Replaced(replacement_txid))
+    );
+    assert_eq!(
+        wallet.get_conflict_status(child_txid),
+        Some(ConflictStatus::Conflicting)
+    );
+    assert_eq!(wallet.get_conflict_status(replacement_txid), None);
+    assert_eq!(wallet.get_conflict_status(txid), None);
+    assert_eq!(wallet.list_conflicted_transactions().len(), 2);
+
+    // only the output of the replacement can be spent
+    let utxos = wallet.list_unspent().collect::<Vec<_>>();
+    assert_eq!(utxos.len(), 1);
+    assert_eq!(utxos[0].outpoint.txid, replacement_txid);
+}
+
+#[test]
+fn test_rebroadcast_scheduler() {
+    use bdk::wallet::rebroadcast::RebroadcastScheduler;
+
+    let (mut wallet, _) = get_funded_wallet(get_test_wpkh());
+    let addr = Address::from_str("2N1Ffz3WaNzbeLFBb51xyFMHYSEUXcbiSoX")
+        .unwrap()
+        .assume_checked();
+    let mut builder = wallet.build_tx();
+    builder.add_recipient(addr.script_pubkey(), 25_000);
+    let mut psbt = builder.finish().unwrap();
+    wallet.sign(&mut psbt, Default::default()).unwrap();
+    let tx = psbt.extract_tx();
+    let txid = tx.txid();
+    wallet
+        .insert_tx(tx, ConfirmationTime::Unconfirmed { last_seen: 1_000 })
+        .unwrap();
+    // a foreign transaction is never rebroadcast
+    receive_output(
+        &mut wallet,
+        10_000,
+        ConfirmationTime::Unconfirmed { last_seen: 0 },
+    );
+
+    let mut scheduler = RebroadcastScheduler::new(600, false, ()).unwrap();
+    assert!(scheduler.pending(&wallet, 1_500).is_empty());
+
+    let mut broadcast = vec![];
+    let rebroadcast = scheduler
+        .rebroadcast_pending(&wallet, 2_000, |tx| {
+            broadcast.push(tx.txid());
+            Ok::<_, core::convert::Infallible>(())
+        })
+        .unwrap();
+    assert_eq!(rebroadcast, vec![txid]);
+    assert_eq!(broadcast, vec![txid]);
+    let info = scheduler.rebroadcast_info(&txid).unwrap();
+    assert_eq!((info.count, info.last_broadcast), (1, 2_000));
+
+    // not again before the interval
+    assert!(scheduler.pending(&wallet, 2_500).is_empty());
+    assert_eq!(scheduler.pending(&wallet, 2_600).len(), 1);
+}
+
+#[test]
+fn test_rebroadcast_scheduler_parents_first() {
+    use bdk::wallet::rebroadcast::RebroadcastScheduler;
+
+    let (mut wallet, _) = get_funded_wallet(get_test_wpkh());
+    let addr = Address::from_str("2N1Ffz3WaNzbeLFBb51xyFMHYSEUXcbiSoX")
+        .unwrap()
+        .assume_checked();
+    // a chain of transactions, each spending the change of the previous one
+    for _ in 0..5 {
+        let mut builder = wallet.build_tx();
+        builder.add_recipient(addr.script_pubkey(), 1_000);
+        let mut psbt = builder.finish().unwrap();
+        wallet.sign(&mut psbt, Default::default()).unwrap();
+        wallet
+            .insert_tx(
+                psbt.extract_tx(),
+                ConfirmationTime::Unconfirmed { last_seen: 1_000 },
+            )
+            .unwrap();
+    }
+
+    let scheduler = RebroadcastScheduler::new(600, false, ()).unwrap();
+    let pending = scheduler.pending(&wallet, 2_000);
+    assert_eq!(pending.len(), 5);
+    for (i, tx) in pending.iter().enumerate() {
+        for txin in &tx.input {
+            assert!(!pending[i..]
+                .iter()
+                .any(|other| other.txid() == txin.previous_output.txid));
+        }
+    }
+}
+
+#[test]
+fn test_finish_with_details() {
+    use bdk::wallet::tx_builder::SelectionReason;
+
+    let (mut wallet, _) = get_funded_wallet(get_test_wpkh());
+    let addr = Address::from_str("2N1Ffz3WaNzbeLFBb51xyFMHYSEUXcbiSoX")
+        .unwrap()
+        .assume_checked();
+    let mut builder = wallet.build_tx();
+    builder
+        .add_recipient(addr.script_pubkey(), 25_000)
+        .fee_rate(FeeRate::from_sat_per_vb(5.0));
+    let (psbt, details) = builder.finish_with_details().unwrap();
+
+    assert_eq!(details.selected_utxos.len(), 1);
+    assert_eq!(details.selected_utxos[0].value, 50_000);
+    assert_eq!(
+        details.selected_utxos[0].reason,
+        SelectionReason::CoinSelection
+    );
+    assert_eq!(details.fee, psbt.fee_amount().unwrap());
+    assert_eq!(details.change, Some(50_000 - 25_000 - details.fee));
+    assert_matches!(details.excess, coin_selection::Excess::Change { fee, .. } if fee == details.waste);
+    assert!(details.fee_rate.as_sat_per_vb() >= 5.0);
+    assert_eq!(details.external_policy_path, None);
+}
+
+#[test]
+fn test_input_sighash() {
+    use bdk::wallet::tx_builder::TxOrdering;
+
+    let (mut wallet, txid) = get_funded_wallet(get_test_wpkh());
+    let funded = OutPoint { txid, vout: 0 };
+    let other = receive_output_in_latest_block(&mut wallet, 25_000);
+    let addr = wallet.get_address(New);
+    let sighash = EcdsaSighashType::SinglePlusAnyoneCanPay;
+
+    let mut builder = wallet.build_tx();
+    builder
+        .add_utxo(funded)
+        .unwrap()
+        .manually_selected_only()
+        .add_recipient(addr.script_pubkey(), 30_000)
+        .ordering(TxOrdering::Untouched)
+        .input_sighash(funded, sighash.into());
+    let mut psbt = builder.finish().unwrap();
+    assert_eq!(psbt.inputs[0].sighash_type, Some(sighash.into()));
+    assert!(wallet
+        .sign(
+            &mut psbt,
+            SignOptions {
+                allow_all_sighashes: true,
+                ..Default::default()
+            },
+        )
+        .unwrap());
+
+    // the second input would have no output to commit to
+    let mut builder = wallet.build_tx();
+    builder
+        .drain_to(addr.script_pubkey())
+        .drain_wallet()
+        .input_sighash(funded, sighash.into())
+        .input_sighash(other, sighash.into());
+    assert_matches!(
+        builder.finish(),
+        Err(CreateTxError::SighashSingleWithoutOutput(_))
+    );
+}
+
+#[test]
+fn test_reserve_addresses() {
+    let mut wallet =
+        Wallet::new_no_persist(get_test_tr_single_sig_xprv(), None, Network::Testnet).unwrap();
+    assert_eq!(wallet.get_address(New).index, 0);
+    let next = wallet.get_address(Peek(1)).address;
+    let reserved = wallet.reserve_addresses(3).unwrap();
+    assert_eq!(
+        reserved.iter().map(|info| info.index).collect::<Vec<_>>(),
+        vec![1, 2, 3]
+    );
+    assert_eq!(reserved[0].address, next);
+    assert_eq!(wallet.get_address(New).index, 4);
+    assert_eq!(wallet.reserve_addresses(0).unwrap(), vec![]);
+
+    // without a wildcard there's a single address, already revealed
+    let mut wallet = get_funded_wallet(get_test_wpkh()).0;
+    assert_eq!(wallet.reserve_addresses(2).unwrap(), vec![]);
+}
+
+#[test]
+fn test_address_types() {
+    use bitcoin::AddressType;
+
+    let mut wallet = get_funded_wallet(get_test_wpkh()).0;
+    assert_eq!(wallet.supported_address_types(), vec![AddressType::P2wpkh]);
+    assert_eq!(wallet.get_new_address_of_type(AddressType::P2pkh), Ok(None));
+    let info = wallet
+        .get_new_address_of_type(AddressType::P2wpkh)
+        .unwrap()
+        .unwrap();
+    assert_eq!(info.address.address_type(), Some(AddressType::P2wpkh));
+    assert_eq!(info.keychain, KeychainKind::External);
+
+    let wallet = Wallet::new_no_persist(get_test_tr_single_sig(), None, Network::Regtest).unwrap();
+    assert_eq!(wallet.supported_address_types(), vec![AddressType::P2tr]);
+}
+
+#[test]
+fn test_get_address_with_proof() {
+    let descriptor = "wsh(sortedmulti(2,[73756c7f/48'/1'/0'/2']tpubDCKxNyM3bLgbEX13Mcd8mYxbVg9ajDkWXMh29hMWBurKfVmBfWAM96QVP3zaUcN51HvkZ3ar4VwP82kC8JZhhux8vFQoJintSpVBwpFvyU3/0/*,[f9f62194/48'/1'/0'/2']tpubDDp3ZSH1yCwusRppH7zgSxq2t1VEUyXSeEp8E5aFS8m43MknUjiF1bSLo3CGWAxbDyhF1XowA5ukPzyJZjznYk3kYi6oe7QxtX2euvKWsk4/0/*))";
+    let mut wallet = Wallet::new_no_persist(descriptor, None, Network::Testnet).unwrap();
+
+    let proof = wallet.get_address_with_proof(5).unwrap();
+    assert_eq!(proof.address, wallet.get_address(Peek(5)).address);
+    assert_eq!(proof.keychain, KeychainKind::External);
+    assert_eq!(proof.index, 5);
+
+    let origins = proof
+        .key_origins
+        .iter()
+        .map(|(_, (fingerprint, path))| (fingerprint.to_string(), path.to_string()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        origins,
+        vec![
+            ("73756c7f".to_string(), "m/48'/1'/0'/2'/0/5".to_string()),
+            ("f9f62194".to_string(), "m/48'/1'/0'/2'/0/5".to_string()),
+        ]
+    );
+    let mut sorted = proof.sorted_pubkeys.clone();
+    sorted.sort_by_key(|pk| pk.to_bytes());
+    assert_eq!(proof.sorted_pubkeys, sorted);
+    assert_eq!(proof.sorted_pubkeys.len(), 2);
+
+    assert!(wallet.get_address_with_proof(1 << 31).is_none());
+}
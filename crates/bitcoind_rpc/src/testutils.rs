@@ -0,0 +1,156 @@
+//! Helpers to drive a regtest `bitcoind` from integration tests.
+//!
+//! [`RegtestExt`] is implemented for every [`RpcApi`] client, so it can be used with the node of
+//! any chain source under test (`bitcoind` RPC, Electrum or Esplora). Mining to an address and
+//! sending coins require the node to have a wallet loaded, while the other helpers don't.
+//!
+//! ```no_run
+//! use bdk_bitcoind_rpc::bitcoincore_rpc::{Auth, Client};
+//! use bdk_bitcoind_rpc::testutils::RegtestExt;
+//! # use bitcoin::Amount;
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! # let address: bitcoin::Address = unimplemented!();
+//! let client = Client::new("http://127.0.0.1:18443", Auth::None)?;
+//!
+//! // coinbase outputs can only be spent after 100 blocks
+//! client.mine_blocks(101, None)?;
+//! client.send(&address, Amount::from_sat(50_000))?;
+//! client.mine_blocks(1, None)?;
+//!
+//! // replace the block confirming the transaction, which is back in the mempool
+//! client.reorg_empty_blocks(1)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use bitcoin::{
+    absolute, block::Header, hash_types::TxMerkleNode, hashes::Hash, Address, Amount, Block,
+    BlockHash, CompactTarget, OutPoint, ScriptBuf, ScriptHash, Sequence, Transaction, TxIn, TxOut,
+    Txid, Witness,
+};
+use bitcoincore_rpc::{
+    bitcoincore_rpc_json::{GetBlockTemplateModes, GetBlockTemplateRules},
+    Error, RpcApi,
+};
+
+/// Extends [`RpcApi`] with helpers for regtest nodes.
+///
+/// Refer to [module-level documentation] for more.
+///
+/// [module-level documentation]: crate::testutils
+pub trait RegtestExt: RpcApi {
+    /// Mine `count` blocks, paying the coinbase to `address` or to a new address of the node's
+    /// wallet, and return their hashes.
+    fn mine_blocks(
+        &self,
+        count: usize,
+        address: Option<&Address>,
+    ) -> Result<Vec<BlockHash>, Error> {
+        let coinbase_address = match address {
+            Some(address) => address.clone(),
+            None => self.get_new_address(None, None)?.assume_checked(),
+        };
+        self.generate_to_address(count as _, &coinbase_address)
+    }
+
+    /// Mine a block without any transaction from the mempool, and return its height and hash.
+    ///
+    /// The block doesn't need the node to have a wallet, and is different from the blocks
+    /// previously mined at the same height.
+    fn mine_empty_block(&self) -> Result<(usize, BlockHash), Error> {
+        let bt = self.get_block_template(
+            GetBlockTemplateModes::Template,
+            &[GetBlockTemplateRules::SegWit],
+            &[],
+        )?;
+        let now = std::time::UNIX_EPOCH
+            .elapsed()
+            .map_err(|e| Error::ReturnedError(e.to_string()))?;
+
+        let txdata = vec![Transaction {
+            version: 1,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint::default(),
+                script_sig: ScriptBuf::builder()
+                    .push_int(bt.height as _)
+                    // so that re-mining a block at the same height creates a unique block
+                    .push_int(now.subsec_nanos() as _)
+                    .into_script(),
+                sequence: Sequence::default(),
+                witness: Witness::new(),
+            }],
+            output: vec![TxOut {
+                value: 0,
+                script_pubkey: ScriptBuf::new_p2sh(&ScriptHash::all_zeros()),
+            }],
+        }];
+
+        let bits: [u8; 4] = bt.bits.clone().try_into().map_err(|_| {
+            Error::ReturnedError("getblocktemplate returned invalid bits".to_string())
+        })?;
+
+        let mut block = Block {
+            header: Header {
+                version: bitcoin::block::Version::default(),
+                prev_blockhash: bt.previous_block_hash,
+                merkle_root: TxMerkleNode::all_zeros(),
+                time: Ord::max(bt.min_time, now.as_secs()) as u32,
+                bits: CompactTarget::from_consensus(u32::from_be_bytes(bits)),
+                nonce: 0,
+            },
+            txdata,
+        };
+
+        block.header.merkle_root = block.compute_merkle_root().expect("must compute");
+
+        for nonce in 0..=u32::MAX {
+            block.header.nonce = nonce;
+            if block.header.target().is_met_by(block.block_hash()) {
+                break;
+            }
+        }
+
+        self.submit_block(&block)?;
+        Ok((bt.height as usize, block.block_hash()))
+    }
+
+    /// Invalidate the last `count` blocks of the best chain.
+    ///
+    /// Their transactions go back to the mempool.
+    fn invalidate_blocks(&self, count: usize) -> Result<(), Error> {
+        let mut hash = self.get_best_block_hash()?;
+        for _ in 0..count {
+            let prev_hash = self.get_block_info(&hash)?.previousblockhash;
+            self.invalidate_block(&hash)?;
+            match prev_hash {
+                Some(prev_hash) => hash = prev_hash,
+                None => break,
+            }
+        }
+        Ok(())
+    }
+
+    /// Replace the last `count` blocks with new ones, returning their hashes.
+    ///
+    /// The first new block confirms the transactions of the invalidated blocks again.
+    fn reorg(&self, count: usize) -> Result<Vec<BlockHash>, Error> {
+        self.invalidate_blocks(count)?;
+        self.mine_blocks(count, None)
+    }
+
+    /// Replace the last `count` blocks with empty blocks, returning their heights and hashes.
+    ///
+    /// The transactions of the invalidated blocks stay in the mempool.
+    fn reorg_empty_blocks(&self, count: usize) -> Result<Vec<(usize, BlockHash)>, Error> {
+        self.invalidate_blocks(count)?;
+        (0..count).map(|_| self.mine_empty_block()).collect()
+    }
+
+    /// Send `amount` from the node's wallet to `address`, and return the txid.
+    fn send(&self, address: &Address, amount: Amount) -> Result<Txid, Error> {
+        self.send_to_address(address, amount, None, None, None, None, None, None)
+    }
+}
+
+impl<C: RpcApi> RegtestExt for C {}
@@ -0,0 +1,45 @@
+use bdk_bitcoind_rpc::testutils::RegtestExt;
+use bitcoin::Amount;
+use bitcoincore_rpc::RpcApi;
+
+fn setup() -> anyhow::Result<(bitcoind::BitcoinD, bitcoincore_rpc::Client)> {
+    let daemon = match std::env::var_os("TEST_BITCOIND") {
+        Some(bitcoind_path) => bitcoind::BitcoinD::new(bitcoind_path),
+        None => bitcoind::BitcoinD::from_downloaded(),
+    }?;
+    let client = bitcoincore_rpc::Client::new(
+        &daemon.rpc_url(),
+        bitcoincore_rpc::Auth::CookieFile(daemon.params.cookie_file.clone()),
+    )?;
+    Ok((daemon, client))
+}
+
+/// Fund an address, then reorg out the block confirming the transaction.
+#[test]
+pub fn test_regtest_helpers() -> anyhow::Result<()> {
+    let (_daemon, client) = setup()?;
+
+    let hashes = client.mine_blocks(101, None)?;
+    assert_eq!(hashes.len(), 101);
+    assert_eq!(client.get_block_count()?, 101);
+
+    let address = client.get_new_address(None, None)?.assume_checked();
+    let txid = client.send(&address, Amount::from_sat(50_000))?;
+    let hashes = client.mine_blocks(1, None)?;
+    assert_eq!(client.get_transaction(&txid, None)?.info.confirmations, 1);
+
+    // the transaction goes back to the mempool
+    let reorged = client.reorg_empty_blocks(1)?;
+    assert_eq!(reorged.len(), 1);
+    assert_eq!(reorged[0].0, 102);
+    assert_ne!(reorged[0].1, hashes[0]);
+    assert_eq!(client.get_best_block_hash()?, reorged[0].1);
+    assert!(client.get_raw_mempool()?.contains(&txid));
+
+    // and is confirmed again
+    let reorged = client.reorg(1)?;
+    assert_eq!(client.get_best_block_hash()?, reorged[0]);
+    assert_eq!(client.get_transaction(&txid, None)?.info.confirmations, 1);
+
+    Ok(())
+}
@@ -1,3 +1,4 @@
+use bdk_bitcoind_rpc::testutils::RegtestExt;
 use bdk_esplora::EsploraAsyncExt;
 use electrsd::bitcoind::bitcoincore_rpc::RpcApi;
 use electrsd::bitcoind::{self, anyhow, BitcoinD};
@@ -8,7 +9,7 @@ use std::str::FromStr;
 use std::thread::sleep;
 use std::time::Duration;
 
-use bdk_chain::bitcoin::{Address, Amount, BlockHash, Txid};
+use bdk_chain::bitcoin::{Address, Amount, Txid};
 
 struct TestEnv {
     bitcoind: BitcoinD,
@@ -38,26 +39,6 @@ impl TestEnv {
             client,
         })
     }
-
-    fn mine_blocks(
-        &self,
-        count: usize,
-        address: Option<Address>,
-    ) -> anyhow::Result<Vec<BlockHash>> {
-        let coinbase_address = match address {
-            Some(address) => address,
-            None => self
-                .bitcoind
-                .client
-                .get_new_address(None, None)?
-                .assume_checked(),
-        };
-        let block_hashes = self
-            .bitcoind
-            .client
-            .generate_to_address(count as _, &coinbase_address)?;
-        Ok(block_hashes)
-    }
 }
 
 #[tokio::test]
@@ -73,7 +54,7 @@ pub async fn test_update_tx_graph_without_keychain() -> anyhow::Result<()> {
         receive_address1.script_pubkey(),
     ];
 
-    let _block_hashes = env.mine_blocks(101, None)?;
+    let _block_hashes = env.bitcoind.client.mine_blocks(101, None)?;
     let txid1 = env.bitcoind.client.send_to_address(
         &receive_address1,
         Amount::from_sat(10000),
@@ -94,7 +75,7 @@ pub async fn test_update_tx_graph_without_keychain() -> anyhow::Result<()> {
         Some(1),
         None,
     )?;
-    let _block_hashes = env.mine_blocks(1, None)?;
+    let _block_hashes = env.bitcoind.client.mine_blocks(1, None)?;
     while env.client.get_height().await.unwrap() < 102 {
         sleep(Duration::from_millis(10))
     }
@@ -121,7 +102,7 @@ pub async fn test_update_tx_graph_without_keychain() -> anyhow::Result<()> {
 #[tokio::test]
 pub async fn test_async_update_tx_graph_gap_limit() -> anyhow::Result<()> {
     let env = TestEnv::new()?;
-    let _block_hashes = env.mine_blocks(101, None)?;
+    let _block_hashes = env.bitcoind.client.mine_blocks(101, None)?;
 
     // Now let's test the gap limit. First of all get a chain of 10 addresses.
     let addresses = [
@@ -159,7 +140,7 @@ pub async fn test_async_update_tx_graph_gap_limit() -> anyhow::Result<()> {
         Some(1),
         None,
     )?;
-    let _block_hashes = env.mine_blocks(1, None)?;
+    let _block_hashes = env.bitcoind.client.mine_blocks(1, None)?;
     while env.client.get_height().await.unwrap() < 103 {
         sleep(Duration::from_millis(10))
     }
@@ -202,7 +183,7 @@ pub async fn test_async_update_tx_graph_gap_limit() -> anyhow::Result<()> {
         Some(1),
         None,
     )?;
-    let _block_hashes = env.mine_blocks(1, None)?;
+    let _block_hashes = env.bitcoind.client.mine_blocks(1, None)?;
     while env.client.get_height().await.unwrap() < 104 {
         sleep(Duration::from_millis(10))
     }
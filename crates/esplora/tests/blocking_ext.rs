@@ -1,3 +1,4 @@
+use bdk_bitcoind_rpc::testutils::RegtestExt;
 use bdk_esplora::EsploraExt;
 use electrsd::bitcoind::bitcoincore_rpc::RpcApi;
 use electrsd::bitcoind::{self, anyhow, BitcoinD};
@@ -8,7 +9,7 @@ use std::str::FromStr;
 use std::thread::sleep;
 use std::time::Duration;
 
-use bdk_chain::bitcoin::{Address, Amount, BlockHash, Txid};
+use bdk_chain::bitcoin::{Address, Amount, Txid};
 
 struct TestEnv {
     bitcoind: BitcoinD,
@@ -38,26 +39,6 @@ impl TestEnv {
             client,
         })
     }
-
-    fn mine_blocks(
-        &self,
-        count: usize,
-        address: Option<Address>,
-    ) -> anyhow::Result<Vec<BlockHash>> {
-        let coinbase_address = match address {
-            Some(address) => address,
-            None => self
-                .bitcoind
-                .client
-                .get_new_address(None, None)?
-                .assume_checked(),
-        };
-        let block_hashes = self
-            .bitcoind
-            .client
-            .generate_to_address(count as _, &coinbase_address)?;
-        Ok(block_hashes)
-    }
 }
 
 #[test]
@@ -73,7 +54,7 @@ pub fn test_update_tx_graph_without_keychain() -> anyhow::Result<()> {
         receive_address1.script_pubkey(),
     ];
 
-    let _block_hashes = env.mine_blocks(101, None)?;
+    let _block_hashes = env.bitcoind.client.mine_blocks(101, None)?;
     let txid1 = env.bitcoind.client.send_to_address(
         &receive_address1,
         Amount::from_sat(10000),
@@ -94,7 +75,7 @@ pub fn test_update_tx_graph_without_keychain() -> anyhow::Result<()> {
         Some(1),
         None,
     )?;
-    let _block_hashes = env.mine_blocks(1, None)?;
+    let _block_hashes = env.bitcoind.client.mine_blocks(1, None)?;
     while env.client.get_height().unwrap() < 102 {
         sleep(Duration::from_millis(10))
     }
@@ -119,7 +100,7 @@ pub fn test_update_tx_graph_without_keychain() -> anyhow::Result<()> {
 #[test]
 pub fn test_update_tx_graph_gap_limit() -> anyhow::Result<()> {
     let env = TestEnv::new()?;
-    let _block_hashes = env.mine_blocks(101, None)?;
+    let _block_hashes = env.bitcoind.client.mine_blocks(101, None)?;
 
     // Now let's test the gap limit. First of all get a chain of 10 addresses.
     let addresses = [
@@ -157,7 +138,7 @@ pub fn test_update_tx_graph_gap_limit() -> anyhow::Result<()> {
         Some(1),
         None,
     )?;
-    let _block_hashes = env.mine_blocks(1, None)?;
+    let _block_hashes = env.bitcoind.client.mine_blocks(1, None)?;
     while env.client.get_height().unwrap() < 103 {
         sleep(Duration::from_millis(10))
     }
@@ -194,7 +175,7 @@ pub fn test_update_tx_graph_gap_limit() -> anyhow::Result<()> {
         Some(1),
         None,
     )?;
-    let _block_hashes = env.mine_blocks(1, None)?;
+    let _block_hashes = env.bitcoind.client.mine_blocks(1, None)?;
     while env.client.get_height().unwrap() < 104 {
         sleep(Duration::from_millis(10))
     }
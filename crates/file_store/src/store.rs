@@ -193,8 +193,156 @@ where
 
         Ok(())
     }
+
+    /// Rewrites the file so that it only contains the aggregate of all stored changesets.
+    ///
+    /// Since every call to [`append_changeset`] adds a new entry, the file grows with each
+    /// commit. Compacting replaces all entries with a single one that is equivalent to applying
+    /// them sequentially. If any entry fails to be read, the file is left untouched and the error
+    /// is returned.
+    ///
+    /// **WARNING**: The file is rewritten in place. If the process is interrupted while compacting,
+    /// the file may be left in an inconsistent state, so you may want to back it up first.
+    ///
+    /// [`append_changeset`]: Store::append_changeset
+    pub fn compact(&mut self) -> Result<(), CompactError<C>> {
+        let changeset = self
+            .aggregate_changesets()
+            .map_err(CompactError::Aggregate)?
+            .filter(|changeset| !changeset.is_empty());
+
+        let start_pos = self.magic.len() as u64;
+        self.db_file
+            .seek(io::SeekFrom::Start(start_pos))
+            .map_err(CompactError::Io)?;
+        match changeset {
+            Some(changeset) => self.append_changeset(&changeset),
+            None => self.db_file.set_len(start_pos),
+        }
+        .map_err(CompactError::Io)
+    }
+
+    /// Appends the aggregate of the changesets stored in `old`, converted to `C`.
+    ///
+    /// Entries are encoded with bincode, which isn't self-describing: once fields are added to a
+    /// changeset type, the files written with the previous version of the type can't be read
+    /// anymore. Open such a file as a `Store` of the previous type `O`, and migrate its content
+    /// to a new `Store` with this method. `old` is only read.
+    ///
+    /// **WARNING**: This method changes the write position of `old` in the same way as
+    /// [`aggregate_changesets`] does.
+    ///
+    /// [`aggregate_changesets`]: Store::aggregate_changesets
+    pub fn migrate_from<O>(&mut self, old: &mut Store<O>) -> Result<(), MigrateError<O>>
+    where
+        O: Append + serde::Serialize + serde::de::DeserializeOwned + Into<C>,
+    {
+        match old
+            .aggregate_changesets()
+            .map_err(MigrateError::Aggregate)?
+        {
+            Some(changeset) => self
+                .append_changeset(&changeset.into())
+                .map_err(MigrateError::Io),
+            None => Ok(()),
+        }
+    }
+
+    /// Reads every entry of the file and reports whether they can all be decoded.
+    ///
+    /// This only detects entries that are truncated or corrupted to the point of not decoding as
+    /// a `C`: the content of the changesets isn't checked for consistency.
+    ///
+    /// This does not modify the file. If the returned [`DecodeReport`] contains an error, the
+    /// entries after [`DecodeReport::valid_len`] cannot be read; they will be overwritten the
+    /// next time a changeset is appended.
+    ///
+    /// **WARNING**: This method changes the write position of the underlying file in the same way
+    /// as [`aggregate_changesets`] does.
+    ///
+    /// [`aggregate_changesets`]: Store::aggregate_changesets
+    pub fn check_decoding(&mut self) -> Result<DecodeReport, io::Error> {
+        let mut valid_entries = 0;
+        let mut error = None;
+        for next_changeset in self.iter_changesets() {
+            match next_changeset {
+                Ok(_) => valid_entries += 1,
+                Err(iter_error) => error = Some(iter_error),
+            }
+        }
+
+        Ok(DecodeReport {
+            valid_entries,
+            valid_len: self.db_file.stream_position()?,
+            file_len: self.db_file.metadata()?.len(),
+            error,
+        })
+    }
+}
+
+/// Report returned by [`Store::check_decoding`].
+#[derive(Debug)]
+pub struct DecodeReport {
+    /// The number of changesets that were successfully read.
+    pub valid_entries: usize,
+
+    /// The length in bytes of the readable part of the file (magic bytes included).
+    pub valid_len: u64,
+
+    /// The total length of the file in bytes.
+    pub file_len: u64,
+
+    /// The error returned when reading the first invalid entry, if any.
+    pub error: Option<IterError>,
+}
+
+impl DecodeReport {
+    /// Whether every entry of the file could be decoded.
+    pub fn is_valid(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Error type for [`Store::compact`].
+#[derive(Debug)]
+pub enum CompactError<C> {
+    /// Failed to read the changesets stored in the file.
+    Aggregate(AggregateChangesetsError<C>),
+    /// Failed to write the compacted changeset to the file.
+    Io(io::Error),
+}
+
+impl<C> std::fmt::Display for CompactError<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Aggregate(e) => write!(f, "failed to read changesets: {}", e),
+            Self::Io(e) => write!(f, "io error trying to write compacted changeset: {}", e),
+        }
+    }
+}
+
+impl<C: std::fmt::Debug> std::error::Error for CompactError<C> {}
+
+/// Error type for [`Store::migrate_from`].
+#[derive(Debug)]
+pub enum MigrateError<O> {
+    /// Failed to read the changesets stored in the old file.
+    Aggregate(AggregateChangesetsError<O>),
+    /// Failed to write the migrated changeset to the new file.
+    Io(io::Error),
+}
+
+impl<O> std::fmt::Display for MigrateError<O> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Aggregate(e) => write!(f, "failed to read changesets to migrate: {}", e),
+            Self::Io(e) => write!(f, "io error trying to write migrated changeset: {}", e),
+        }
+    }
 }
 
+impl<O: std::fmt::Debug> std::error::Error for MigrateError<O> {}
+
 /// Error type for [`Store::aggregate_changesets`].
 #[derive(Debug)]
 pub struct AggregateChangesetsError<C> {
@@ -340,4 +488,141 @@ mod test {
 
         assert_eq!(got_bytes, expected_bytes);
     }
+
+    #[test]
+    fn compact_merges_all_changesets() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let file_path = temp_dir.path().join("db_file");
+        let changesets: [TestChangeSet; 3] = [
+            vec!["one".into()],
+            vec!["two".into(), "three".into()],
+            vec!["four".into()],
+        ];
+
+        let mut store = Store::<TestChangeSet>::create_new(&TEST_MAGIC_BYTES, &file_path)
+            .expect("must create");
+        for changeset in &changesets {
+            store.append_changeset(changeset).expect("must append");
+        }
+        store.compact().expect("must compact");
+        drop(store);
+
+        let mut store =
+            Store::<TestChangeSet>::open(&TEST_MAGIC_BYTES, &file_path).expect("must open");
+        let entries = store
+            .iter_changesets()
+            .collect::<Result<Vec<_>, _>>()
+            .expect("must read all entries");
+        assert_eq!(entries, vec![changesets.concat()]);
+
+        // appending after compaction must still work
+        store
+            .append_changeset(&vec!["five".into()])
+            .expect("must append");
+        assert_eq!(store.iter_changesets().count(), 2);
+    }
+
+    /// A newer version of [`TestChangeSet`], with a field that the old entries don't have.
+    #[derive(Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct TestChangeSetV2 {
+        items: Vec<String>,
+        counter: u32,
+    }
+
+    impl Append for TestChangeSetV2 {
+        fn append(&mut self, other: Self) {
+            Append::append(&mut self.items, other.items);
+            self.counter += other.counter;
+        }
+
+        fn is_empty(&self) -> bool {
+            self.items.is_empty() && self.counter == 0
+        }
+    }
+
+    impl From<TestChangeSet> for TestChangeSetV2 {
+        fn from(items: TestChangeSet) -> Self {
+            Self { items, counter: 0 }
+        }
+    }
+
+    #[test]
+    fn migrate_from_old_changeset_type() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let old_path = temp_dir.path().join("old_db_file");
+        let new_path = temp_dir.path().join("new_db_file");
+
+        let mut old =
+            Store::<TestChangeSet>::create_new(&TEST_MAGIC_BYTES, &old_path).expect("must create");
+        old.append_changeset(&vec!["one".into()])
+            .expect("must append");
+        old.append_changeset(&vec!["two".into()])
+            .expect("must append");
+
+        let mut new = Store::<TestChangeSetV2>::create_new(&TEST_MAGIC_BYTES, &new_path)
+            .expect("must create");
+        new.migrate_from(&mut old).expect("must migrate");
+        new.append_changeset(&TestChangeSetV2 {
+            items: vec![],
+            counter: 1,
+        })
+        .expect("must append");
+
+        let mut new =
+            Store::<TestChangeSetV2>::open(&TEST_MAGIC_BYTES, &new_path).expect("must open");
+        assert_eq!(
+            new.aggregate_changesets().expect("must read"),
+            Some(TestChangeSetV2 {
+                items: vec!["one".into(), "two".into()],
+                counter: 1,
+            })
+        );
+    }
+
+    #[test]
+    fn compact_fails_on_invalid_entries() {
+        let mut data = [255_u8; 2000];
+        data[..TEST_MAGIC_BYTES_LEN].copy_from_slice(&TEST_MAGIC_BYTES);
+
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(&data).expect("should write");
+
+        let mut store =
+            Store::<TestChangeSet>::open(&TEST_MAGIC_BYTES, file.path()).expect("should open");
+        match store.compact() {
+            Err(CompactError::Aggregate(_)) => {}
+            unexpected => panic!("unexpected result: {:?}", unexpected),
+        }
+
+        let file_len = file.as_file().metadata().unwrap().len();
+        assert_eq!(file_len, data.len() as u64, "file must be left untouched");
+    }
+
+    #[test]
+    fn check_decoding_reports_invalid_tail() {
+        let changeset: TestChangeSet = vec!["one".into(), "two".into()];
+
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(&TEST_MAGIC_BYTES).expect("should write");
+        let mut store =
+            Store::<TestChangeSet>::open(&TEST_MAGIC_BYTES, file.path()).expect("should open");
+        store.append_changeset(&changeset).expect("should append");
+
+        let report = store.check_decoding().expect("should read");
+        assert!(report.is_valid());
+        assert_eq!(report.valid_entries, 1);
+        assert_eq!(report.valid_len, report.file_len);
+
+        let valid_len = report.valid_len;
+        file.as_file_mut()
+            .seek(io::SeekFrom::End(0))
+            .expect("should seek");
+        file.write_all(&[255_u8; 16]).expect("should write");
+
+        let report = store.check_decoding().expect("should read");
+        assert!(!report.is_valid());
+        assert_eq!(report.valid_entries, 1);
+        assert_eq!(report.valid_len, valid_len);
+        assert_eq!(report.file_len, valid_len + 16);
+    }
 }